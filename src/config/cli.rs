@@ -3,7 +3,7 @@ use clap::Parser;
 use url::Url;
 
 /// Command line interface arguments
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct CliArgs {
     /// WebSocket URL for cloud/proxy connection
@@ -22,6 +22,10 @@ pub struct CliArgs {
     #[arg(long, default_value_t = 3000, env = "PORI_PORT")]
     pub port: u16,
 
+    /// Local server URL, replacing --protocol and --port (e.g. unix:///run/app.sock)
+    #[arg(long, env = "PORI_UPSTREAM")]
+    pub upstream: Option<String>,
+
     /// Port for dashboard server
     #[arg(long, default_value_t = 7616, env = "PORI_DASHBOARD_PORT")]
     pub dashboard_port: u16,
@@ -50,10 +54,14 @@ pub struct CliArgs {
     #[arg(long, default_value_t = 0)]
     pub max_reconnects: u32,
 
-    /// Verify SSL certificates for a local server
-    #[arg(long, action = clap::ArgAction::SetTrue)]
+    /// Verify SSL certificates for a local server (the default)
+    #[arg(long, action = clap::ArgAction::SetTrue, overrides_with = "no_verify_ssl")]
     pub verify_ssl: bool,
 
+    /// Skip SSL certificate verification for a local server
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub no_verify_ssl: bool,
+
     /// Maximum connections to a local server
     #[arg(long, default_value_t = 10)]
     pub max_connections: usize,
@@ -61,6 +69,10 @@ pub struct CliArgs {
     /// HTTP version for local server communication (auto, http1, http2)
     #[arg(long, default_value = "http1", env = "PORI_HTTP_VERSION")]
     pub http_version: String,
+
+    /// Public subdomain to request from the relay
+    #[arg(long, env = "PORI_SUBDOMAIN")]
+    pub subdomain: Option<String>,
 }
 
 impl CliArgs {
@@ -168,6 +180,9 @@ impl CliArgs {
 
     /// Get the complete local server URL
     pub fn local_url(&self) -> Result<Url> {
+        if let Some(upstream) = &self.upstream {
+            return Url::parse(upstream).context("Invalid --upstream URL");
+        }
         let url_string = format!("{}://localhost:{}", self.protocol, self.port);
         Url::parse(&url_string).context("Failed to construct local server URL")
     }
@@ -0,0 +1,81 @@
+//! Persistent client identity
+//!
+//! The relay tells clients apart by the `client_id` in every envelope. It is
+//! generated once and stored in `pori/client-id` under the user config
+//! directory, readable only by its owner, so restarts keep the same identity.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Client id stored under the user config directory, created on first run
+pub fn client_id() -> String {
+    let id = client_id_path().and_then(|path| load_or_create(&path));
+    id.unwrap_or_else(|e| {
+        warn!("Using a temporary client id: {:#}", e);
+        new_client_id()
+    })
+}
+
+/// Read the client id from `path`, writing a new one if the file is missing
+pub fn load_or_create(path: &Path) -> Result<String> {
+    if path.exists() {
+        let id = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let id = id.trim();
+        if !id.is_empty() {
+            return Ok(id.to_string());
+        }
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let id = new_client_id();
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(format!("{id}\n").as_bytes()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(id)
+}
+
+/// Location of the stored client id
+pub fn client_id_path() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Could not determine the user config directory")?
+        .join("pori")
+        .join("client-id"))
+}
+
+fn new_client_id() -> String {
+    format!("pori_{}", uuid::Uuid::new_v4().simple())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_id_is_created_once() {
+        let dir = std::env::temp_dir().join(format!("pori-identity-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("client-id");
+
+        let id = load_or_create(&path).unwrap();
+        assert!(id.starts_with("pori_"));
+        assert_eq!(load_or_create(&path).unwrap(), id);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
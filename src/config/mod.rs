@@ -1,2 +1,5 @@
 pub mod cli;
+pub mod identity;
+pub mod relay;
+pub mod reload;
 pub mod settings;
@@ -0,0 +1,295 @@
+//! Config documents pushed by the relay
+//!
+//! The relay sends `ControlPayload::Config { config_type, config_data }` with
+//! one of the typed documents below. Accepted documents are validated against
+//! the rest of the settings, applied at runtime and stored on disk so they
+//! survive restarts and config file reloads.
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use super::settings::{
+    AppSettings, HeaderRuleConfig, HeaderRulesConfig, RelayOverridesConfig, DEFAULT_TUNNEL,
+};
+use crate::protocol::config::{ProtocolConfig, RateLimitConfig};
+use crate::protocol::tunnel::AccessControlConfig;
+use crate::AppState;
+
+/// Config types the relay may push
+pub const CONFIG_TYPES: &[&str] = &["logging", "headers", "access_control", "rate_limit"];
+
+/// A relay config document; versions must increase for each config type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigDocument<T> {
+    /// Relay-assigned version (0 = unversioned, always applied)
+    #[serde(default)]
+    pub version: u64,
+    #[serde(flatten)]
+    pub config: T,
+}
+
+/// Body of a `logging` document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggingOverride {
+    /// Level name or filter directives, e.g. `PROXY=debug,hyper=warn`
+    pub filter: String,
+}
+
+/// Documents accepted from the relay, layered over the config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayOverrides {
+    pub logging: Option<ConfigDocument<LoggingOverride>>,
+    pub headers: Option<ConfigDocument<HeaderRulesConfig>>,
+    pub access_control: Option<ConfigDocument<AccessControlConfig>>,
+    pub rate_limit: Option<ConfigDocument<RateLimitConfig>>,
+}
+
+impl RelayOverrides {
+    /// Read a tunnel's stored overrides; a missing file means none
+    pub fn load(config: &RelayOverridesConfig, tunnel: &str) -> Result<Self> {
+        let path = overrides_path(config, tunnel)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write a tunnel's overrides atomically
+    pub fn save(&self, config: &RelayOverridesConfig, tunnel: &str) -> Result<()> {
+        let path = overrides_path(config, tunnel)?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    /// Replace the matching settings sections
+    pub fn apply(&self, settings: &mut AppSettings) {
+        if let Some(logging) = &self.logging {
+            settings.logging.level = logging.config.filter.clone();
+        }
+        if let Some(headers) = &self.headers {
+            settings.headers = headers.config.clone();
+        }
+        if let Some(access_control) = &self.access_control {
+            settings.access_control = access_control.config.clone();
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            settings.rate_limit = rate_limit.config.clone();
+        }
+    }
+
+    /// Settings sections these overrides replace
+    pub fn sections(&self) -> Vec<&'static str> {
+        [
+            ("logging", self.logging.is_some()),
+            ("headers", self.headers.is_some()),
+            ("access_control", self.access_control.is_some()),
+            ("rate_limit", self.rate_limit.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect()
+    }
+
+    /// Merge one pushed document, returning its version
+    pub fn merge(&mut self, config_type: &str, data: &Value) -> Result<u64> {
+        match config_type {
+            "logging" => {
+                let mut document: ConfigDocument<LoggingOverride> = parse(config_type, data)?;
+                document.config.filter =
+                    crate::logging::resolve_directives(&document.config.filter)?;
+                replace(&mut self.logging, document, config_type)
+            }
+            "headers" => {
+                let document: ConfigDocument<HeaderRulesConfig> = parse(config_type, data)?;
+                check_header_limits(&document.config)?;
+                replace(&mut self.headers, document, config_type)
+            }
+            "access_control" => {
+                let document = parse(config_type, data)?;
+                replace(&mut self.access_control, document, config_type)
+            }
+            "rate_limit" => {
+                let document = parse(config_type, data)?;
+                replace(&mut self.rate_limit, document, config_type)
+            }
+            other => bail!(
+                "Unsupported config type '{other}' (supported: {})",
+                CONFIG_TYPES.join(", ")
+            ),
+        }
+    }
+}
+
+/// Validate, apply and persist a config update from the relay
+pub fn apply_update(app_state: &AppState, config_type: &str, data: &Value) -> Result<u64> {
+    let settings = app_state.settings();
+    if !settings.relay_overrides.enabled {
+        bail!("Relay config updates are disabled on this client");
+    }
+
+    let mut overrides = RelayOverrides::load(&settings.relay_overrides, &settings.tunnel_name)?;
+    let version = overrides.merge(config_type, data)?;
+
+    let mut updated = settings.as_ref().clone();
+    overrides.apply(&mut updated);
+    updated.validate()?;
+
+    if updated.logging.level != settings.logging.level {
+        crate::logging::set_filter(&updated.logging.level)?;
+    }
+    overrides.save(&settings.relay_overrides, &settings.tunnel_name)?;
+    app_state.apply_settings(updated);
+    Ok(version)
+}
+
+/// Location of a tunnel's stored overrides; named tunnels get a `-<name>` file suffix
+pub fn overrides_path(config: &RelayOverridesConfig, tunnel: &str) -> Result<PathBuf> {
+    let path = match &config.path {
+        Some(path) => PathBuf::from(path),
+        None => dirs::config_dir()
+            .context("Could not determine the user config directory")?
+            .join("pori")
+            .join("relay-overrides.json"),
+    };
+    if tunnel == DEFAULT_TUNNEL {
+        return Ok(path);
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}-{tunnel}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{tunnel}"),
+    };
+    Ok(path.with_file_name(file_name))
+}
+
+fn parse<T: DeserializeOwned>(config_type: &str, data: &Value) -> Result<ConfigDocument<T>> {
+    serde_json::from_value(data.clone())
+        .with_context(|| format!("Invalid '{config_type}' config document"))
+}
+
+/// Store a document unless an equal or newer version is already applied
+fn replace<T>(
+    slot: &mut Option<ConfigDocument<T>>,
+    document: ConfigDocument<T>,
+    config_type: &str,
+) -> Result<u64> {
+    if let Some(current) = slot {
+        if document.version != 0 && document.version <= current.version {
+            bail!(
+                "Stale '{config_type}' config version {} (current version is {})",
+                document.version,
+                current.version
+            );
+        }
+    }
+    let version = document.version;
+    *slot = Some(document);
+    Ok(version)
+}
+
+/// Keep rewritten headers within the protocol's header size limit
+fn check_header_limits(rules: &HeaderRulesConfig) -> Result<()> {
+    let max = ProtocolConfig::default().limits.max_header_size;
+    let too_long = |rule: &&HeaderRuleConfig| {
+        rule.name.len() + rule.value.as_deref().map(str::len).unwrap_or(0) > max
+    };
+    if let Some(rule) = rules.request.iter().chain(&rules.response).find(too_long) {
+        bail!(
+            "Header rule for '{}' exceeds the {max} byte header limit",
+            rule.name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_versions_and_round_trip() {
+        let mut overrides = RelayOverrides::default();
+
+        let version = overrides
+            .merge(
+                "access_control",
+                &json!({ "version": 2, "blocked_ips": ["203.0.113.0/24"] }),
+            )
+            .unwrap();
+        assert_eq!(version, 2);
+        assert!(overrides
+            .merge("access_control", &json!({ "version": 2 }))
+            .unwrap_err()
+            .to_string()
+            .contains("Stale"));
+
+        overrides
+            .merge(
+                "rate_limit",
+                &json!({ "version": 1, "enabled": true, "requests_per_minute": 30 }),
+            )
+            .unwrap();
+        overrides
+            .merge("logging", &json!({ "filter": "debug" }))
+            .unwrap();
+        assert!(overrides
+            .merge("logging", &json!({ "filter": "PROXY=chatty" }))
+            .is_err());
+        assert!(overrides.merge("tunnels", &json!({})).is_err());
+
+        let config = RelayOverridesConfig {
+            enabled: true,
+            path: Some(
+                std::env::temp_dir()
+                    .join(format!("pori-relay-{}", uuid::Uuid::new_v4()))
+                    .join("overrides.json")
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+        };
+        overrides.save(&config, DEFAULT_TUNNEL).unwrap();
+        let loaded = RelayOverrides::load(&config, DEFAULT_TUNNEL).unwrap();
+        assert_eq!(loaded, overrides);
+        assert_eq!(
+            loaded.access_control.unwrap().config.blocked_ips,
+            vec!["203.0.113.0/24"]
+        );
+        assert!(loaded
+            .logging
+            .unwrap()
+            .config
+            .filter
+            .starts_with("pori=debug"));
+
+        // Named tunnels keep their own file next to the default one
+        let named = overrides_path(&config, "api").unwrap();
+        assert!(named.ends_with("overrides-api.json"));
+        assert!(RelayOverrides::load(&config, "api")
+            .unwrap()
+            .logging
+            .is_none());
+
+        std::fs::remove_dir_all(named.parent().unwrap()).unwrap();
+    }
+}
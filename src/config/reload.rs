@@ -0,0 +1,352 @@
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+use super::relay::RelayOverrides;
+use super::settings::AppSettings;
+use crate::{AppState, DashboardEvent};
+
+/// How often the config file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a reload changed
+#[derive(Debug, Default, PartialEq)]
+pub struct ReloadSummary {
+    /// Sections applied to the running client
+    pub applied: Vec<&'static str>,
+    /// Sections that only take effect after a restart
+    pub restart_required: Vec<&'static str>,
+    /// Whether the relay connection is re-established
+    pub reconnect: bool,
+}
+
+impl ReloadSummary {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty() && !self.reconnect
+    }
+
+    /// One-line description for logs and the dashboard
+    pub fn describe(&self) -> String {
+        if self.is_empty() {
+            return "Configuration reloaded: no changes".to_string();
+        }
+
+        let mut parts = Vec::new();
+        if !self.applied.is_empty() {
+            parts.push(format!("applied {}", self.applied.join(", ")));
+        }
+        if self.reconnect {
+            parts.push("reconnecting to the relay".to_string());
+        }
+        if !self.restart_required.is_empty() {
+            parts.push(format!(
+                "restart required for {}",
+                self.restart_required.join(", ")
+            ));
+        }
+        format!("Configuration reloaded: {}", parts.join("; "))
+    }
+}
+
+/// Reloads the config file when it changes or on SIGHUP
+pub struct ConfigReloader {
+    app_state: Arc<AppState>,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigReloader {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        let modified = modified_time(app_state.settings().config_path.as_deref());
+        Self {
+            app_state,
+            modified,
+        }
+    }
+
+    /// Watch for changes until the task is aborted
+    pub async fn run(mut self) {
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        #[cfg(unix)]
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+            .map_err(|e| warn!("Failed to install SIGHUP handler: {}", e))
+            .ok();
+        #[cfg(not(unix))]
+        let mut hangup = ();
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let modified = modified_time(self.app_state.settings().config_path.as_deref());
+                    if modified == self.modified {
+                        continue;
+                    }
+                    self.modified = modified;
+                    info!("Configuration file changed, reloading");
+                }
+                _ = recv_hangup(&mut hangup) => {
+                    info!("Received SIGHUP signal - reloading the configuration");
+                }
+            }
+
+            reload_and_report(&self.app_state);
+            self.modified = modified_time(self.app_state.settings().config_path.as_deref());
+        }
+    }
+}
+
+/// Reload, then log and publish the result as a dashboard event
+pub fn reload_and_report(app_state: &AppState) {
+    let event = match reload(app_state) {
+        Ok(summary) => {
+            let message = summary.describe();
+            info!("{}", message);
+            DashboardEvent::ConfigReloaded(message)
+        }
+        Err(e) => {
+            let message =
+                format!("Configuration reload failed, keeping the current settings: {e:#}");
+            error!("{}", message);
+            DashboardEvent::ConfigReloadFailed(message)
+        }
+    };
+    let _ = app_state.dashboard_tx.send(event);
+}
+
+/// Load and validate the config file, then apply it atomically to every tunnel
+pub fn reload(app_state: &AppState) -> Result<ReloadSummary> {
+    let current = app_state.settings();
+    let updated = current.reload()?;
+    for settings in updated.tunnel_settings() {
+        warn_shadowed(&settings);
+    }
+    let mut summary = compare(&current, &updated);
+    let reconnect = summary.reconnect;
+
+    // The other tunnels pick up their own sections; adding or removing tunnels needs a restart
+    let mut tunnels = Vec::new();
+    for state in app_state.tunnel_states() {
+        if std::ptr::eq(state.as_ref(), app_state) {
+            continue;
+        }
+        let settings = state.settings();
+        if let Some(next) = updated.tunnel(&settings.tunnel_name) {
+            let changes = compare(&settings, next);
+            for name in changes.applied {
+                if !summary.applied.contains(&name) {
+                    summary.applied.push(name);
+                }
+            }
+            summary.reconnect |= changes.reconnect;
+            tunnels.push((state, next.clone(), changes.reconnect));
+        }
+    }
+    if tunnel_names(&current) != tunnel_names(&updated) {
+        summary.restart_required.push("tunnels");
+    }
+    if summary.is_empty() {
+        return Ok(summary);
+    }
+
+    if updated.logging.level != current.logging.level {
+        crate::logging::set_filter(&updated.logging.level)?;
+    }
+
+    app_state.apply_settings(updated);
+    if reconnect {
+        app_state.request_reconnect();
+    }
+    for (state, settings, reconnect) in tunnels {
+        state.apply_settings(settings);
+        if reconnect {
+            state.request_reconnect();
+        }
+    }
+    Ok(summary)
+}
+
+/// Relay overrides win over the file, so an edit of the same section has no effect
+fn warn_shadowed(settings: &AppSettings) {
+    if !settings.relay_overrides.enabled {
+        return;
+    }
+    let Ok(overrides) = RelayOverrides::load(&settings.relay_overrides, &settings.tunnel_name)
+    else {
+        return;
+    };
+    for section in overrides.sections() {
+        warn!(
+            "Relay override for '{}' (tunnel '{}') takes precedence over the config file",
+            section, settings.tunnel_name
+        );
+    }
+}
+
+fn tunnel_names(settings: &AppSettings) -> Vec<&str> {
+    if settings.tunnels.is_empty() {
+        return vec![settings.tunnel_name.as_str()];
+    }
+    settings
+        .tunnels
+        .iter()
+        .map(|tunnel| tunnel.tunnel_name.as_str())
+        .collect()
+}
+
+/// Classify the differences between two settings snapshots
+pub fn compare(current: &AppSettings, updated: &AppSettings) -> ReloadSummary {
+    let mut summary = ReloadSummary {
+        reconnect: current.websocket.url != updated.websocket.url
+            || current.websocket.token != updated.websocket.token
+            || current.websocket.tls != updated.websocket.tls
+            || current.websocket.proxy != updated.websocket.proxy,
+        ..ReloadSummary::default()
+    };
+
+    let mut applied = |name, changed: bool| {
+        if changed {
+            summary.applied.push(name);
+        }
+    };
+    applied("websocket", current.websocket != updated.websocket);
+    applied("local_server", current.local_server != updated.local_server);
+    applied(
+        "dashboard",
+        current.dashboard.enable_cors != updated.dashboard.enable_cors
+            || current.dashboard.static_file_cache != updated.dashboard.static_file_cache,
+    );
+    applied("logging", current.logging.level != updated.logging.level);
+    applied(
+        "access_control",
+        current.access_control != updated.access_control,
+    );
+    applied("auth", current.auth != updated.auth);
+    applied("webhooks", current.webhooks != updated.webhooks);
+    applied("headers", current.headers != updated.headers);
+    applied("forwarded", current.forwarded != updated.forwarded);
+    applied("features", current.features != updated.features);
+    applied(
+        "stats_reporting",
+        current.stats_reporting != updated.stats_reporting,
+    );
+    applied("access_log", current.access_log != updated.access_log);
+    applied("redaction", current.redaction != updated.redaction);
+    applied("rate_limit", current.rate_limit != updated.rate_limit);
+    applied(
+        "relay_overrides",
+        current.relay_overrides != updated.relay_overrides,
+    );
+
+    let mut restart = |name, changed: bool| {
+        if changed {
+            summary.restart_required.push(name);
+        }
+    };
+    restart(
+        "dashboard",
+        current.dashboard.port != updated.dashboard.port
+            || current.dashboard.bind_address != updated.dashboard.bind_address,
+    );
+    restart(
+        "logging",
+        current.logging.format != updated.logging.format
+            || current.logging.enable_color != updated.logging.enable_color
+            || current.logging.show_context != updated.logging.show_context,
+    );
+    restart("telemetry", current.telemetry != updated.telemetry);
+
+    summary
+}
+
+fn modified_time(path: Option<&Path>) -> Option<SystemTime> {
+    std::fs::metadata(path?).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(unix)]
+async fn recv_hangup(hangup: &mut Option<tokio::signal::unix::Signal>) {
+    if let Some(signal) = hangup {
+        if signal.recv().await.is_some() {
+            return;
+        }
+    }
+    std::future::pending::<()>().await
+}
+
+#[cfg(not(unix))]
+async fn recv_hangup(_hangup: &mut ()) {
+    std::future::pending::<()>().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::cli::CliArgs;
+
+    fn write_config(path: &Path, url: &str, timeout: u64) {
+        std::fs::write(
+            path,
+            format!(
+                "websocket:\n  url: \"{url}\"\n  token: \"test-token\"\nlocal_server:\n  timeout: {timeout}\ndashboard:\n  port: 7616\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    fn cli(path: &Path) -> CliArgs {
+        CliArgs {
+            url: None,
+            token: None,
+            protocol: "http".to_string(),
+            port: 3000,
+            dashboard_port: 7616,
+            log_level: "info".to_string(),
+            config: None,
+            yml: Some(path.to_string_lossy().into_owned()),
+            no_dashboard: false,
+            timeout: 30,
+            max_reconnects: 0,
+            verify_ssl: false,
+            no_verify_ssl: false,
+            max_connections: 10,
+            http_version: "http1".to_string(),
+            subdomain: None,
+            upstream: None,
+        }
+    }
+
+    #[test]
+    fn test_reload_applies_changes_and_rejects_invalid_files() {
+        let path = std::env::temp_dir().join(format!("pori-reload-{}.yml", uuid::Uuid::new_v4()));
+        write_config(&path, "wss://relay.example.com", 30);
+        let settings = AppSettings::from_cli(cli(&path)).unwrap();
+        assert_eq!(settings.config_path.as_deref(), Some(path.as_path()));
+        let (app_state, _channels) = AppState::new(settings);
+
+        // Timeout change is applied without a reconnect
+        write_config(&path, "wss://relay.example.com", 5);
+        let summary = reload(&app_state).unwrap();
+        assert_eq!(summary.applied, vec!["local_server"]);
+        assert!(!summary.reconnect);
+        assert_eq!(
+            app_state.settings().local_server.timeout,
+            Duration::from_secs(5)
+        );
+
+        // A new relay URL asks the client to reconnect
+        write_config(&path, "wss://other.example.com", 5);
+        assert!(reload(&app_state).unwrap().reconnect);
+
+        // Invalid files leave the current settings in place
+        std::fs::write(&path, "websocket:\n  url: \"wss://x\"\n  token: \"\"\n").unwrap();
+        assert!(reload(&app_state).is_err());
+        assert_eq!(
+            app_state.settings().websocket.url.as_str(),
+            "wss://other.example.com/"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
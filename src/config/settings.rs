@@ -1,9 +1,18 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
+use tracing::warn;
 use url::Url;
 
 use super::cli::CliArgs;
+use crate::protocol::config::{FeatureConfig, RateLimitConfig};
+use crate::protocol::http::HttpProxyConfig;
+use crate::protocol::tunnel::{AccessControlConfig, TlsConfig};
+
+/// Tunnel name used when the config file declares no `tunnels`
+pub const DEFAULT_TUNNEL: &str = "default";
 
 /// Application settings derived from CLI arguments and configuration files
 #[derive(Debug, Clone)]
@@ -12,11 +21,32 @@ pub struct AppSettings {
     pub local_server: LocalServerSettings,
     pub dashboard: DashboardSettings,
     pub logging: LoggingSettings,
+    pub access_control: AccessControlConfig,
+    pub auth: AuthConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub headers: HeaderRulesConfig,
+    pub forwarded: ForwardedConfig,
+    pub features: FeatureConfig,
+    pub stats_reporting: StatsReportingConfig,
+    pub telemetry: TelemetryConfig,
+    pub access_log: AccessLogConfig,
+    pub redaction: RedactionConfig,
+    pub rate_limit: RateLimitConfig,
+    pub relay_overrides: RelayOverridesConfig,
+    pub commands: CommandsConfig,
+    /// Name of the tunnel these settings belong to
+    pub tunnel_name: String,
+    /// Every named tunnel when the config file declares `tunnels` (the first is this one)
+    pub tunnels: Vec<AppSettings>,
     pub no_dashboard: bool,
+    /// Config file the settings were loaded from, if any
+    pub config_path: Option<PathBuf>,
+    /// CLI arguments, applied again on top of the file when it is reloaded
+    pub cli: CliArgs,
 }
 
 /// WebSocket connection settings
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WebSocketSettings {
     pub url: Url,
     pub token: String,
@@ -25,10 +55,16 @@ pub struct WebSocketSettings {
     pub requires_tls: bool,
     pub ping_interval: Duration,
     pub pong_timeout: Duration,
+    /// Public subdomain requested from the relay
+    pub subdomain: Option<String>,
+    /// TLS options for `wss://` relays
+    pub tls: TlsConfig,
+    /// Outbound proxy for the relay connection
+    pub proxy: HttpProxyConfig,
 }
 
 /// Local server configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LocalServerSettings {
     pub url: Url,
     pub timeout: Duration,
@@ -37,10 +73,16 @@ pub struct LocalServerSettings {
     pub keep_alive: Duration,
     pub connect_timeout: Duration,
     pub http_version: HttpVersion,
+    /// TLS options for HTTPS local servers
+    pub tls: LocalServerTlsConfig,
+    /// Options for `file://` upstreams
+    pub files: FileServerConfig,
+    /// Canned responses for the `mock:` upstream
+    pub mock: Vec<MockRouteConfig>,
 }
 
 /// HTTP version preference
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum HttpVersion {
     Auto,      // Try HTTP/2, fallback to HTTP/1.1
     Http1Only, // Force HTTP/1.1
@@ -48,7 +90,7 @@ pub enum HttpVersion {
 }
 
 /// Dashboard server settings
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DashboardSettings {
     pub port: u16,
     pub bind_address: String,
@@ -57,7 +99,7 @@ pub struct DashboardSettings {
 }
 
 /// Logging configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LoggingSettings {
     pub level: String,
     pub format: LogFormat,
@@ -66,7 +108,7 @@ pub struct LoggingSettings {
 }
 
 /// Log output format
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LogFormat {
     Pretty,
     Json,
@@ -74,15 +116,43 @@ pub enum LogFormat {
 }
 
 /// Configuration file structure (optional)
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ConfigFile {
     pub websocket: Option<WebSocketConfig>,
     pub local_server: Option<LocalServerConfig>,
     pub dashboard: Option<DashboardConfig>,
     pub logging: Option<LoggingConfig>,
+    pub access_control: Option<AccessControlConfig>,
+    pub auth: Option<AuthConfig>,
+    pub webhooks: Option<Vec<WebhookConfig>>,
+    pub headers: Option<HeaderRulesConfig>,
+    pub forwarded: Option<ForwardedConfig>,
+    pub features: Option<FeatureConfig>,
+    pub stats_reporting: Option<StatsReportingConfig>,
+    pub telemetry: Option<TelemetryConfig>,
+    pub access_log: Option<AccessLogConfig>,
+    pub redaction: Option<RedactionConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub relay_overrides: Option<RelayOverridesConfig>,
+    pub commands: Option<CommandsConfig>,
+    pub tunnels: Option<Vec<TunnelConfig>>,
+}
+
+/// A named tunnel; sections it leaves out are taken from the top level
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TunnelConfig {
+    pub name: String,
+    pub websocket: Option<WebSocketConfig>,
+    pub local_server: Option<LocalServerConfig>,
+    pub access_control: Option<AccessControlConfig>,
+    pub auth: Option<AuthConfig>,
+    pub webhooks: Option<Vec<WebhookConfig>>,
+    pub headers: Option<HeaderRulesConfig>,
+    pub forwarded: Option<ForwardedConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WebSocketConfig {
     pub url: Option<String>,
     pub token: Option<String>,
@@ -90,9 +160,12 @@ pub struct WebSocketConfig {
     pub max_reconnects: Option<u32>,
     pub ping_interval: Option<u64>,
     pub pong_timeout: Option<u64>,
+    pub subdomain: Option<String>,
+    pub tls: Option<TlsConfig>,
+    pub proxy: Option<HttpProxyConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LocalServerConfig {
     pub url: Option<String>,
     pub timeout: Option<u64>,
@@ -101,9 +174,82 @@ pub struct LocalServerConfig {
     pub keep_alive: Option<u64>,
     pub connect_timeout: Option<u64>,
     pub http_version: Option<String>,
+    pub tls: Option<LocalServerTlsConfig>,
+    pub files: Option<FileServerConfig>,
+    pub mock: Option<Vec<MockRouteConfig>>,
+}
+
+/// TLS options for HTTPS local servers
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LocalServerTlsConfig {
+    /// Client certificate chain (PEM) presented to the local server
+    pub cert_file: Option<String>,
+    /// Private key (PEM) for the client certificate
+    pub key_file: Option<String>,
+    /// Extra CA bundles (PEM), e.g. the mkcert root
+    pub ca_files: Vec<String>,
+    /// TLS server name (and Host) to use instead of the URL host
+    pub sni_hostname: Option<String>,
+}
+
+/// Options for serving a directory with a `file://` upstream
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FileServerConfig {
+    /// List directory contents when no index file exists
+    pub listing: bool,
+    /// File names served for a directory, in order of preference
+    pub index: Vec<String>,
+    /// File served for missing paths without an extension (single-page apps)
+    pub spa_fallback: Option<String>,
+    /// Accept `PUT` requests that write files into the directory
+    pub upload: bool,
+    /// Serve and list names starting with `.`, such as `.env` or `.git/`
+    pub dotfiles: bool,
+}
+
+impl Default for FileServerConfig {
+    fn default() -> Self {
+        Self {
+            listing: false,
+            index: vec!["index.html".to_string()],
+            spa_fallback: None,
+            upload: false,
+            dotfiles: false,
+        }
+    }
+}
+
+/// A canned response of the `mock:` upstream
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MockRouteConfig {
+    /// Method to match (any when unset)
+    pub method: Option<String>,
+    /// Exact path, or a prefix ending in `*`; the query string is ignored
+    pub path: String,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    /// Delay before answering, in milliseconds
+    pub latency_ms: u64,
+}
+
+impl Default for MockRouteConfig {
+    fn default() -> Self {
+        Self {
+            method: None,
+            path: "*".to_string(),
+            status: 200,
+            headers: HashMap::new(),
+            body: String::new(),
+            latency_ms: 0,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DashboardConfig {
     pub port: Option<u16>,
     pub bind_address: Option<String>,
@@ -111,7 +257,7 @@ pub struct DashboardConfig {
     pub static_file_cache: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LoggingConfig {
     pub level: Option<String>,
     pub format: Option<String>,
@@ -119,18 +265,523 @@ pub struct LoggingConfig {
     pub show_context: Option<bool>, // default: None
 }
 
+/// Authentication gate in front of the local server
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Username to password hash (bcrypt or argon2 PHC string)
+    pub users: HashMap<String, String>,
+    /// Static bearer tokens
+    pub tokens: Vec<String>,
+    /// Secret used to sign session cookies
+    pub cookie_secret: Option<String>,
+    /// Session cookie name
+    pub cookie_name: String,
+    /// Session lifetime in seconds (1 second to 1 year)
+    pub session_ttl: u64,
+    /// Path of the built-in login page
+    pub login_path: String,
+    /// Protected routes, first match wins
+    pub routes: Vec<AuthRouteConfig>,
+}
+
+/// Authentication requirement for a route
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuthRouteConfig {
+    /// Path prefix this rule applies to
+    pub path: String,
+    /// HTTP methods this rule applies to (empty = all)
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Authentication mode
+    pub mode: AuthMode,
+    /// Realm sent in WWW-Authenticate
+    #[serde(default)]
+    pub realm: Option<String>,
+}
+
+/// Supported authentication modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    /// No authentication (used to exempt paths)
+    None,
+    /// HTTP Basic against hashed credentials
+    Basic,
+    /// Static bearer token list
+    Bearer,
+    /// Signed session cookie issued by the login page
+    Cookie,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            users: HashMap::new(),
+            tokens: Vec::new(),
+            cookie_secret: None,
+            cookie_name: "pori_session".to_string(),
+            session_ttl: 86400,
+            login_path: "/.pori/login".to_string(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// Webhook signature verification rule
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// Path prefix this rule applies to
+    pub path: String,
+    /// Signature scheme
+    pub provider: WebhookProvider,
+    /// Shared signing secret
+    pub secret: String,
+    /// Allowed clock skew in seconds for timestamped signatures (default: 300)
+    #[serde(default)]
+    pub tolerance: Option<u64>,
+    /// Signature header (generic HMAC only)
+    #[serde(default)]
+    pub header: Option<String>,
+    /// Digest algorithm: sha1, sha256 or sha512 (generic HMAC only)
+    #[serde(default)]
+    pub algorithm: Option<String>,
+    /// Signature encoding: hex or base64 (generic HMAC only)
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Prefix stripped from the header value, e.g. `sha256=` (generic HMAC only)
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// Supported webhook signature schemes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookProvider {
+    /// GitHub `X-Hub-Signature-256`
+    Github,
+    /// Stripe `Stripe-Signature` with timestamp
+    Stripe,
+    /// Slack `X-Slack-Signature` with request timestamp
+    Slack,
+    /// Configurable HMAC header
+    Hmac,
+}
+
+/// Header rewriting rules, applied in order
+///
+/// Each list replaces the built-in defaults when present in the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HeaderRulesConfig {
+    /// Rules applied to requests sent to the local server
+    pub request: Vec<HeaderRuleConfig>,
+    /// Rules applied to responses sent back through the tunnel
+    pub response: Vec<HeaderRuleConfig>,
+}
+
+/// A single header rewriting rule
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HeaderRuleConfig {
+    /// What to do with the header
+    pub action: HeaderAction,
+    /// Header name (case-insensitive)
+    pub name: String,
+    /// Value template for add/set, e.g. `{request_id}` or `{client_ip}`
+    #[serde(default)]
+    pub value: Option<String>,
+    /// New header name for rename
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Only apply to paths under this prefix
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Only apply to these HTTP methods (empty = all)
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+/// Header rewriting actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderAction {
+    /// Add the header only if it is not already present
+    Add,
+    /// Add or replace the header
+    Set,
+    /// Remove the header
+    Remove,
+    /// Rename the header, keeping its value
+    Rename,
+}
+
+impl Default for HeaderRulesConfig {
+    fn default() -> Self {
+        let rule = |action, name: &str, value: Option<&str>| HeaderRuleConfig {
+            action,
+            name: name.to_string(),
+            value: value.map(str::to_string),
+            to: None,
+            path: None,
+            methods: Vec::new(),
+        };
+
+        Self {
+            request: vec![
+                rule(HeaderAction::Add, "X-Request-ID", Some("{request_id}")),
+                rule(HeaderAction::Add, "X-Forwarded-By", Some("pori-proxy")),
+            ],
+            response: vec![
+                rule(HeaderAction::Remove, "server", None),
+                rule(HeaderAction::Remove, "x-powered-by", None),
+            ],
+        }
+    }
+}
+
+/// Standard forwarding headers sent to the local app
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ForwardedConfig {
+    /// Add X-Forwarded-*, Forwarded and Via headers
+    pub enabled: bool,
+    /// Keep X-Forwarded-* and Via values the relay passed along
+    pub trust_incoming: bool,
+    /// Proxies, counting the relay, that append to `X-Forwarded-For`; the
+    /// entry this many places from the right is the client address
+    pub trusted_hops: usize,
+    /// Public hostname (default: Host of the tunneled request)
+    pub public_host: Option<String>,
+    /// Public scheme (default: https for wss relays, http otherwise)
+    pub proto: Option<String>,
+    /// Rewrite absolute `Location` headers pointing at the local server
+    pub rewrite_location: bool,
+    /// Rewrite `Set-Cookie` domains naming the local host
+    pub rewrite_cookie_domain: bool,
+}
+
+impl Default for ForwardedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            trust_incoming: false,
+            trusted_hops: 1,
+            public_host: None,
+            proto: None,
+            rewrite_location: false,
+            rewrite_cookie_domain: false,
+        }
+    }
+}
+
+/// Periodic stats reports sent to the relay (requires `features.metrics_collection`)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StatsReportingConfig {
+    /// Seconds between reports
+    pub interval: u64,
+    /// Also report process CPU and memory usage
+    pub system: bool,
+}
+
+impl Default for StatsReportingConfig {
+    fn default() -> Self {
+        Self {
+            interval: 60,
+            system: false,
+        }
+    }
+}
+
+/// OpenTelemetry trace export (requires the `otel` cargo feature)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Export a server span per tunneled request
+    pub enabled: bool,
+    /// OTLP/HTTP traces endpoint
+    pub endpoint: String,
+    /// `service.name` resource attribute
+    pub service_name: String,
+    /// Inject W3C `traceparent` into requests sent to the local server
+    pub propagate: bool,
+    /// Filter directives for exported spans, independent of the log filter
+    pub filter: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            service_name: "pori".to_string(),
+            propagate: true,
+            filter: "pori=info".to_string(),
+        }
+    }
+}
+
+/// Per-request access log written to its own file
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessLogConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub format: AccessLogFormat,
+    /// Rotate once the file reaches this size (0 disables size rotation)
+    pub max_size_mb: u64,
+    /// Time-based rotation
+    pub rotation: AccessLogRotation,
+    /// Rotated files to keep (0 keeps all)
+    pub max_files: usize,
+}
+
+/// Access log line format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    Common,
+    Combined,
+    Json,
+}
+
+/// Time-based access log rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogRotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "pori-access.log".to_string(),
+            format: AccessLogFormat::Combined,
+            max_size_mb: 100,
+            rotation: AccessLogRotation::Daily,
+            max_files: 7,
+        }
+    }
+}
+
+/// Masking of secrets in logs, dashboard events and the request inspector
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    /// Header names masked in addition to the built-in list
+    pub headers: Vec<String>,
+    /// Query parameter names masked in addition to the built-in list
+    pub query_params: Vec<String>,
+    /// JSON paths of body fields to mask, e.g. `$.password` or `$..token`
+    pub body_fields: Vec<String>,
+    /// Longest body preview written to logs, in bytes (0 omits bodies)
+    pub max_body_preview: usize,
+    /// Longest raw relay frame written to logs, in bytes
+    pub max_frame_preview: usize,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            headers: Vec::new(),
+            query_params: Vec::new(),
+            body_fields: vec!["$..password".to_string()],
+            max_body_preview: 1024,
+            max_frame_preview: 4096,
+        }
+    }
+}
+
+/// Config pushed by the relay at runtime
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RelayOverridesConfig {
+    /// Accept `config` control messages from the relay (opt-in)
+    pub enabled: bool,
+    /// Where accepted updates are stored (default: `pori/relay-overrides.json` in the user config dir)
+    pub path: Option<String>,
+}
+
+/// Remote commands the relay may run on this client
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CommandsConfig {
+    /// Command names accepted from the relay; anything else is refused
+    pub allowed: Vec<String>,
+    /// Operator-defined commands that run a local program
+    pub exec: HashMap<String, ExecCommandConfig>,
+}
+
+/// A command that runs a local program
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExecCommandConfig {
+    /// Program to run; it is never passed through a shell
+    pub program: String,
+    /// Fixed arguments
+    pub args: Vec<String>,
+    /// Append the arguments sent by the relay
+    pub allow_arguments: bool,
+    /// Environment variables the relay may set; the program sees only these and `PATH`
+    pub environment: Vec<String>,
+    /// Kill the program after this many seconds
+    pub timeout_seconds: u64,
+}
+
+impl Default for ExecCommandConfig {
+    fn default() -> Self {
+        Self {
+            program: String::new(),
+            args: Vec::new(),
+            allow_arguments: false,
+            environment: Vec::new(),
+            timeout_seconds: 30,
+        }
+    }
+}
+
+impl ConfigFile {
+    /// The top-level sections with a tunnel's own sections laid over them
+    fn for_tunnel(&self, tunnel: &TunnelConfig) -> Self {
+        let mut file = self.clone();
+        if let Some(websocket) = &tunnel.websocket {
+            file.websocket = Some(websocket.clone().or(self.websocket.as_ref()));
+        }
+        if let Some(local_server) = &tunnel.local_server {
+            file.local_server = Some(local_server.clone().or(self.local_server.as_ref()));
+        }
+        if let Some(access_control) = &tunnel.access_control {
+            file.access_control = Some(access_control.clone());
+        }
+        if let Some(auth) = &tunnel.auth {
+            file.auth = Some(auth.clone());
+        }
+        if let Some(webhooks) = &tunnel.webhooks {
+            file.webhooks = Some(webhooks.clone());
+        }
+        if let Some(headers) = &tunnel.headers {
+            file.headers = Some(headers.clone());
+        }
+        if let Some(forwarded) = &tunnel.forwarded {
+            file.forwarded = Some(forwarded.clone());
+        }
+        if let Some(rate_limit) = &tunnel.rate_limit {
+            file.rate_limit = Some(rate_limit.clone());
+        }
+        file
+    }
+}
+
+impl WebSocketConfig {
+    /// Fill fields left unset from `base`
+    fn or(self, base: Option<&Self>) -> Self {
+        let Some(base) = base else {
+            return self;
+        };
+        Self {
+            url: self.url.or_else(|| base.url.clone()),
+            token: self.token.or_else(|| base.token.clone()),
+            timeout: self.timeout.or(base.timeout),
+            max_reconnects: self.max_reconnects.or(base.max_reconnects),
+            ping_interval: self.ping_interval.or(base.ping_interval),
+            pong_timeout: self.pong_timeout.or(base.pong_timeout),
+            subdomain: self.subdomain.or_else(|| base.subdomain.clone()),
+            tls: self.tls.or_else(|| base.tls.clone()),
+            proxy: self.proxy.or_else(|| base.proxy.clone()),
+        }
+    }
+}
+
+impl LocalServerConfig {
+    /// Fill fields left unset from `base`
+    fn or(self, base: Option<&Self>) -> Self {
+        let Some(base) = base else {
+            return self;
+        };
+        Self {
+            url: self.url.or_else(|| base.url.clone()),
+            timeout: self.timeout.or(base.timeout),
+            verify_ssl: self.verify_ssl.or(base.verify_ssl),
+            max_connections: self.max_connections.or(base.max_connections),
+            keep_alive: self.keep_alive.or(base.keep_alive),
+            connect_timeout: self.connect_timeout.or(base.connect_timeout),
+            http_version: self.http_version.or_else(|| base.http_version.clone()),
+            tls: self.tls.or_else(|| base.tls.clone()),
+            files: self.files.or_else(|| base.files.clone()),
+            mock: self.mock.or_else(|| base.mock.clone()),
+        }
+    }
+}
+
+/// Apply one tunnel's persisted relay overrides
+fn apply_overrides(settings: &mut AppSettings) -> Result<()> {
+    if !settings.relay_overrides.enabled {
+        return Ok(());
+    }
+    super::relay::RelayOverrides::load(&settings.relay_overrides, &settings.tunnel_name)
+        .with_context(|| {
+            format!(
+                "Invalid relay overrides for tunnel '{}'",
+                settings.tunnel_name
+            )
+        })?
+        .apply(settings);
+    Ok(())
+}
+
 impl AppSettings {
     /// Create settings from CLI arguments
     pub fn from_cli(cli: CliArgs) -> Result<Self> {
         // Load config file if specified
-        let config_file = if let Some(yml_path) = &cli.yml {
-            Self::load_config_file(yml_path)?
+        let (mut config_file, config_path) = if let Some(yml_path) = &cli.yml {
+            (
+                Self::load_config_file(yml_path)?,
+                Some(PathBuf::from(yml_path)),
+            )
         } else if let Some(config_path) = &cli.config {
-            Self::load_config_file(config_path)?
+            (
+                Self::load_config_file(config_path)?,
+                Some(PathBuf::from(config_path)),
+            )
         } else {
             Self::try_load_default_config()?
         };
 
+        let tunnels = config_file.tunnels.take().unwrap_or_default();
+        if tunnels.is_empty() {
+            return Self::build(cli, config_file, config_path, DEFAULT_TUNNEL);
+        }
+
+        // Each named tunnel gets its own settings; the first also serves as the process settings
+        let tunnels = tunnels
+            .iter()
+            .map(|tunnel| {
+                Self::build(
+                    cli.clone(),
+                    config_file.for_tunnel(tunnel),
+                    config_path.clone(),
+                    &tunnel.name,
+                )
+                .with_context(|| format!("Invalid tunnel '{}'", tunnel.name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut settings = tunnels[0].clone();
+        settings.tunnels = tunnels;
+        Ok(settings)
+    }
+
+    /// Build the settings of one tunnel from the CLI arguments and config file
+    fn build(
+        cli: CliArgs,
+        config_file: ConfigFile,
+        config_path: Option<PathBuf>,
+        tunnel_name: &str,
+    ) -> Result<Self> {
         // Get URL from CLI or config file
         let url = cli
             .url
@@ -190,16 +841,35 @@ impl AppSettings {
                         .and_then(|ws| ws.pong_timeout)
                         .unwrap_or(10),
                 ),
+                subdomain: cli.subdomain.clone().or_else(|| {
+                    config_file
+                        .websocket
+                        .as_ref()
+                        .and_then(|ws| ws.subdomain.clone())
+                }),
+                tls: config_file
+                    .websocket
+                    .as_ref()
+                    .and_then(|ws| ws.tls.clone())
+                    .unwrap_or_default(),
+                proxy: config_file
+                    .websocket
+                    .as_ref()
+                    .and_then(|ws| ws.proxy.clone())
+                    .unwrap_or_default(),
             },
             local_server: LocalServerSettings {
-                url: config_file
+                // An explicit --upstream wins over the file, --protocol/--port do not
+                url: match config_file
                     .local_server
                     .as_ref()
                     .and_then(|ls| ls.url.as_ref())
-                    .map(|url| url.parse())
-                    .transpose()
-                    .context("Invalid local server URL in config file")?
-                    .unwrap_or_else(|| cli.local_url().expect("Failed to construct local URL")),
+                {
+                    Some(url) if cli.upstream.is_none() => url
+                        .parse()
+                        .context("Invalid local server URL in config file")?,
+                    _ => cli.local_url()?,
+                },
                 timeout: Duration::from_secs(
                     config_file
                         .local_server
@@ -207,11 +877,14 @@ impl AppSettings {
                         .and_then(|ls| ls.timeout)
                         .unwrap_or(cli.timeout),
                 ),
-                verify_ssl: config_file
-                    .local_server
-                    .as_ref()
-                    .and_then(|ls| ls.verify_ssl)
-                    .unwrap_or(cli.verify_ssl),
+                // Verification is on unless turned off on the command line or in the file
+                verify_ssl: !cli.no_verify_ssl
+                    && (cli.verify_ssl
+                        || config_file
+                            .local_server
+                            .as_ref()
+                            .and_then(|ls| ls.verify_ssl)
+                            .unwrap_or(true)),
                 max_connections: config_file
                     .local_server
                     .as_ref()
@@ -243,6 +916,21 @@ impl AppSettings {
                         _ => HttpVersion::Http1Only, // Default to HTTP/1.1 for "http1" and any unknown values
                     }
                 },
+                tls: config_file
+                    .local_server
+                    .as_ref()
+                    .and_then(|ls| ls.tls.clone())
+                    .unwrap_or_default(),
+                files: config_file
+                    .local_server
+                    .as_ref()
+                    .and_then(|ls| ls.files.clone())
+                    .unwrap_or_default(),
+                mock: config_file
+                    .local_server
+                    .as_ref()
+                    .and_then(|ls| ls.mock.clone())
+                    .unwrap_or_default(),
             },
             dashboard: DashboardSettings {
                 port: config_file
@@ -271,7 +959,7 @@ impl AppSettings {
                     .logging
                     .as_ref()
                     .and_then(|l| l.level.clone())
-                    .unwrap_or(cli.log_level),
+                    .unwrap_or_else(|| cli.log_level.clone()),
                 format: config_file
                     .logging
                     .as_ref()
@@ -293,10 +981,68 @@ impl AppSettings {
                     .and_then(|l| l.show_context)
                     .unwrap_or(false),
             },
+            access_control: config_file.access_control.unwrap_or_default(),
+            auth: config_file.auth.unwrap_or_default(),
+            webhooks: config_file.webhooks.unwrap_or_default(),
+            headers: config_file.headers.unwrap_or_default(),
+            forwarded: config_file.forwarded.unwrap_or_default(),
+            features: config_file.features.unwrap_or_default(),
+            stats_reporting: config_file.stats_reporting.unwrap_or_default(),
+            telemetry: config_file.telemetry.unwrap_or_default(),
+            access_log: config_file.access_log.unwrap_or_default(),
+            redaction: config_file.redaction.unwrap_or_default(),
+            rate_limit: config_file.rate_limit.unwrap_or_default(),
+            relay_overrides: config_file.relay_overrides.unwrap_or_default(),
+            commands: config_file.commands.unwrap_or_default(),
+            tunnel_name: tunnel_name.to_string(),
+            tunnels: Vec::new(),
             no_dashboard: cli.no_dashboard,
+            config_path,
+            cli,
         })
     }
 
+    /// Apply config pushed by the relay and persisted from an earlier run
+    ///
+    /// Every tunnel whose overrides load is updated; the first failure is
+    /// returned so the caller can report it once logging is available.
+    pub fn apply_relay_overrides(&mut self) -> Result<()> {
+        let mut result = apply_overrides(self);
+        for tunnel in &mut self.tunnels {
+            let applied = apply_overrides(tunnel);
+            result = result.and(applied);
+        }
+        result
+    }
+
+    /// Settings of every tunnel this process runs
+    pub fn tunnel_settings(&self) -> Vec<AppSettings> {
+        if self.tunnels.is_empty() {
+            vec![self.clone()]
+        } else {
+            self.tunnels.clone()
+        }
+    }
+
+    /// Settings of the named tunnel
+    pub fn tunnel(&self, name: &str) -> Option<&AppSettings> {
+        if self.tunnels.is_empty() {
+            (self.tunnel_name == name).then_some(self)
+        } else {
+            self.tunnels.iter().find(|t| t.tunnel_name == name)
+        }
+    }
+
+    /// Load and validate the config file again with the same CLI arguments
+    pub fn reload(&self) -> Result<Self> {
+        let mut settings = Self::from_cli(self.cli.clone())?;
+        if let Err(e) = settings.apply_relay_overrides() {
+            warn!("Ignoring relay config overrides: {e:#}");
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
     /// Load configuration from a specified file
     fn load_config_file(path: &str) -> Result<ConfigFile> {
         let content = std::fs::read_to_string(path)
@@ -323,7 +1069,7 @@ impl AppSettings {
     }
 
     /// Try to load default configuration files
-    fn try_load_default_config() -> Result<ConfigFile> {
+    fn try_load_default_config() -> Result<(ConfigFile, Option<PathBuf>)> {
         // Try common config file locations
         let possible_paths = [
             "./pori.yml",
@@ -353,18 +1099,13 @@ impl AppSettings {
 
             if std::path::Path::new(&expanded_path).exists() {
                 if let Ok(config) = Self::load_config_file(&expanded_path) {
-                    return Ok(config);
+                    return Ok((config, Some(PathBuf::from(expanded_path))));
                 }
             }
         }
 
         // Return empty config if no file found
-        Ok(ConfigFile {
-            websocket: None,
-            local_server: None,
-            dashboard: None,
-            logging: None,
-        })
+        Ok((ConfigFile::default(), None))
     }
 
     /// Get dashboard bind address including port
@@ -388,20 +1129,191 @@ impl AppSettings {
         }
 
         // Validate local server URL
-        if self.local_server.url.host_str().is_none() {
-            anyhow::bail!("Local server URL must have a valid host");
+        if let crate::proxy::upstream::Upstream::Files(root) =
+            crate::proxy::upstream::Upstream::from_url(&self.local_server.url)?
+        {
+            crate::proxy::files::FileServer::from_config(&root, &self.local_server.files)?;
         }
+        crate::proxy::mock::MockServer::from_config(&self.local_server.mock)?;
 
         // Validate token
         if self.websocket.token.trim().is_empty() {
             anyhow::bail!("WebSocket authentication token cannot be empty");
         }
 
+        // Validate the requested subdomain
+        if let Some(subdomain) = &self.websocket.subdomain {
+            let valid = (1..=63).contains(&subdomain.len())
+                && subdomain
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                && !subdomain.starts_with('-')
+                && !subdomain.ends_with('-');
+            if !valid {
+                anyhow::bail!(
+                    "Subdomain '{subdomain}' must be 1-63 lowercase letters, digits or '-', not starting or ending with '-'"
+                );
+            }
+        }
+
         // Validate dashboard port
         if self.dashboard.port == 0 {
             anyhow::bail!("Dashboard port must be greater than 0");
         }
 
+        // Validate the client address source
+        if self.forwarded.trusted_hops == 0 {
+            anyhow::bail!("forwarded.trusted_hops must be at least 1 (the relay)");
+        }
+
+        // Validate access control rules
+        crate::proxy::access::AccessControl::from_config(&self.access_control)?;
+
+        // Validate authentication gate
+        crate::proxy::auth::AuthGate::from_config(&self.auth)?;
+
+        // Validate webhook signature rules
+        crate::proxy::webhook::WebhookVerifier::from_config(&self.webhooks)?;
+
+        // Validate header rewriting rules
+        crate::proxy::rewrite::HeaderRewriter::from_config(&self.headers)?;
+
+        // Validate redaction body field paths
+        crate::logging::redact::Redactor::from_config(&self.redaction)?;
+
+        // Validate rate limits
+        crate::proxy::ratelimit::RateLimiter::from_config(&self.rate_limit)?;
+
+        // Validate local server TLS certificates
+        crate::proxy::client::UpstreamTls::from_config(&self.local_server.tls)?;
+
+        // Validate relay TLS certificates and pins
+        crate::websocket::tls::RelayTls::from_config(&self.websocket.tls)?;
+
+        // Validate the relay proxy
+        crate::websocket::proxy::RelayProxy::from_config(
+            &self.websocket.proxy,
+            &self.websocket.url,
+        )?;
+
+        // Validate the remote command allowlist
+        crate::websocket::commands::CommandRunner::from_config(&self.commands)?;
+
+        // Validate named tunnels
+        let mut names = std::collections::HashSet::new();
+        let mut subdomains = std::collections::HashSet::new();
+        for tunnel in &self.tunnels {
+            let name = &tunnel.tunnel_name;
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                anyhow::bail!(
+                    "Tunnel name '{name}' must be non-empty and use only letters, digits, '-' and '_'"
+                );
+            }
+            if !names.insert(name) {
+                anyhow::bail!("Duplicate tunnel name '{name}'");
+            }
+            if let Some(subdomain) = &tunnel.websocket.subdomain {
+                if !subdomains.insert(subdomain) {
+                    anyhow::bail!("Subdomain '{subdomain}' is requested by more than one tunnel");
+                }
+            }
+            tunnel
+                .validate()
+                .with_context(|| format!("Invalid tunnel '{name}'"))?;
+        }
+
+        // Validate stats reporting
+        if self.features.metrics_collection && self.stats_reporting.interval == 0 {
+            anyhow::bail!("Stats reporting interval must be greater than 0");
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(path: &std::path::Path) -> CliArgs {
+        CliArgs {
+            url: None,
+            token: None,
+            protocol: "http".to_string(),
+            port: 3000,
+            dashboard_port: 7616,
+            log_level: "info".to_string(),
+            config: None,
+            yml: Some(path.to_string_lossy().into_owned()),
+            no_dashboard: false,
+            timeout: 30,
+            max_reconnects: 0,
+            verify_ssl: false,
+            no_verify_ssl: false,
+            max_connections: 10,
+            http_version: "http1".to_string(),
+            subdomain: None,
+            upstream: None,
+        }
+    }
+
+    #[test]
+    fn test_named_tunnels_inherit_top_level_sections() {
+        let path = std::env::temp_dir().join(format!("pori-tunnels-{}.yml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+websocket:
+  url: "wss://relay.example.com"
+  token: "shared-token"
+local_server:
+  timeout: 5
+rate_limit:
+  enabled: true
+  requests_per_minute: 60
+  burst_size: 10
+tunnels:
+  - name: api
+    local_server:
+      url: "http://localhost:8080"
+  - name: web
+    websocket:
+      url: "wss://eu.relay.example.com"
+    local_server:
+      url: "http://localhost:5173"
+    rate_limit:
+      enabled: false
+"#,
+        )
+        .unwrap();
+
+        let settings = AppSettings::from_cli(cli(&path)).unwrap();
+        settings.validate().unwrap();
+        assert_eq!(settings.tunnel_name, "api");
+
+        let tunnels = settings.tunnel_settings();
+        assert_eq!(tunnels.len(), 2);
+        assert_eq!(
+            tunnels[0].local_server.url.as_str(),
+            "http://localhost:8080/"
+        );
+        assert_eq!(tunnels[0].local_server.timeout, Duration::from_secs(5));
+        assert!(tunnels[0].rate_limit.enabled);
+
+        let web = settings.tunnel("web").unwrap();
+        assert_eq!(web.websocket.url.as_str(), "wss://eu.relay.example.com/");
+        assert_eq!(web.websocket.token, "shared-token");
+        assert_eq!(web.local_server.timeout, Duration::from_secs(5));
+        assert!(!web.rate_limit.enabled);
+
+        let mut duplicate = settings.clone();
+        duplicate.tunnels[1].tunnel_name = "api".to_string();
+        assert!(duplicate.validate().is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,27 +1,49 @@
 pub mod config;
 pub mod logging;
+pub mod metrics;
 pub mod protocol;
 pub mod proxy;
 pub mod server;
+pub mod telemetry;
 pub mod utils;
 pub mod websocket;
 
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
-use tracing::{error, info};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::{self, JoinSet};
+use tracing::{error, info, warn};
 
 use config::settings::AppSettings;
+use logging::access::AccessLog;
+use logging::redact::Redactor;
+use metrics::{Metrics, StatsSnapshot};
 use protocol::http::HttpMessage;
 use protocol::tunnel::TunnelMessage;
+use proxy::inspector::{InspectedRequest, RequestInspector};
 
 /// Shared application state
 pub struct AppState {
-    pub settings: AppSettings,
+    settings: RwLock<Arc<AppSettings>>,
     pub dashboard_tx: mpsc::UnboundedSender<DashboardEvent>,
     pub proxy_tx: mpsc::UnboundedSender<HttpMessage>,
     pub websocket_tx: mpsc::UnboundedSender<TunnelMessage>,
-    pub stats: Arc<RwLock<AppStats>>,
+    pub inspector: Arc<RequestInspector>,
+    /// Process-wide metrics, shown on the dashboard and `/metrics`
+    pub metrics: Arc<Metrics>,
+    /// This tunnel's share of `metrics`, reported to its relay
+    pub tunnel_metrics: Arc<Metrics>,
+    pub access_log: Arc<AccessLog>,
+    redactor: RwLock<Arc<Redactor>>,
+    reconnect: Notify,
+    draining: AtomicBool,
+    status: RwLock<ConnectionStatus>,
+    session: RwLock<RelaySession>,
+    client_id: String,
+    tunnels: Arc<RwLock<Vec<Weak<AppState>>>>,
 }
 
 /// Dashboard events for real-time updates
@@ -30,8 +52,13 @@ pub enum DashboardEvent {
     RequestForwarded(String),
     ResponseReceived(u16, usize),
     Error(String),
+    AccessDenied(String),
+    AuthenticationFailed(String),
+    ConfigReloaded(String),
+    ConfigReloadFailed(String),
+    RequestCompleted(Box<InspectedRequest>),
     ConnectionStatus(ConnectionStatus),
-    Statistics(AppStats),
+    Statistics(StatsSnapshot),
 }
 
 /// Connection status enumeration
@@ -44,16 +71,28 @@ pub enum ConnectionStatus {
     Error(String),
 }
 
-/// Application statistics
-#[derive(Debug, Clone, Default)]
-pub struct AppStats {
-    pub requests_processed: u64,
-    pub requests_successful: u64,
-    pub requests_failed: u64,
-    pub bytes_forwarded: u64,
-    pub uptime_seconds: u64,
-    pub websocket_reconnects: u64,
-    pub connection_status: String,
+/// What the relay reported about this tunnel's connection
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RelaySession {
+    /// Session id from the authentication response
+    pub session_id: Option<String>,
+    /// Relay server handling the connection (`server_id` in its envelopes)
+    pub server_id: Option<String>,
+    /// Public URL assigned to the tunnel
+    pub public_url: Option<String>,
+}
+
+impl ConnectionStatus {
+    /// Lowercase state name, as reported in stats
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionStatus::Connecting => "connecting",
+            ConnectionStatus::Connected => "connected",
+            ConnectionStatus::Disconnected => "disconnected",
+            ConnectionStatus::Reconnecting => "reconnecting",
+            ConnectionStatus::Error(_) => "error",
+        }
+    }
 }
 
 impl AppState {
@@ -62,12 +101,26 @@ impl AppState {
         let (proxy_tx, proxy_rx) = mpsc::unbounded_channel();
         let (websocket_tx, websocket_rx) = mpsc::unbounded_channel();
 
+        let access_log = Arc::new(AccessLog::new(settings.access_log.clone()));
+        // Paths are checked by `AppSettings::validate`; fall back to the built-in masks
+        let redactor = Arc::new(Redactor::from_config(&settings.redaction).unwrap_or_default());
+        let metrics = Arc::new(Metrics::new());
         let state = Self {
-            settings,
+            settings: RwLock::new(Arc::new(settings)),
             dashboard_tx,
             proxy_tx,
             websocket_tx,
-            stats: Arc::new(RwLock::new(AppStats::default())),
+            inspector: Arc::new(RequestInspector::new()),
+            tunnel_metrics: Arc::new(Metrics::for_tunnel(metrics.clone())),
+            metrics,
+            access_log,
+            redactor: RwLock::new(redactor),
+            reconnect: Notify::new(),
+            draining: AtomicBool::new(false),
+            status: RwLock::new(ConnectionStatus::Disconnected),
+            session: RwLock::new(RelaySession::default()),
+            client_id: "pori-client".to_string(),
+            tunnels: Arc::new(RwLock::new(Vec::new())),
         };
 
         let channels = AppChannels {
@@ -79,16 +132,142 @@ impl AppState {
         (state, channels)
     }
 
-    pub async fn update_stats<F>(&self, updater: F)
-    where
-        F: FnOnce(&mut AppStats),
-    {
-        let mut stats = self.stats.write().await;
-        updater(&mut stats);
+    /// State for another tunnel that shares this process's dashboard, metrics,
+    /// inspector and access log
+    pub fn for_tunnel(&self, settings: AppSettings) -> Result<(Self, TunnelChannels)> {
+        let (proxy_tx, proxy_rx) = mpsc::unbounded_channel();
+        let (websocket_tx, websocket_rx) = mpsc::unbounded_channel();
+
+        let redactor = Arc::new(Redactor::from_config(&settings.redaction)?);
+        let state = Self {
+            settings: RwLock::new(Arc::new(settings)),
+            dashboard_tx: self.dashboard_tx.clone(),
+            proxy_tx,
+            websocket_tx,
+            inspector: self.inspector.clone(),
+            metrics: self.metrics.clone(),
+            tunnel_metrics: Arc::new(Metrics::for_tunnel(self.metrics.clone())),
+            access_log: self.access_log.clone(),
+            redactor: RwLock::new(redactor),
+            reconnect: Notify::new(),
+            draining: AtomicBool::new(false),
+            status: RwLock::new(ConnectionStatus::Disconnected),
+            session: RwLock::new(RelaySession::default()),
+            client_id: self.client_id.clone(),
+            tunnels: self.tunnels.clone(),
+        };
+
+        Ok((
+            state,
+            TunnelChannels {
+                proxy_rx,
+                websocket_rx,
+            },
+        ))
+    }
+
+    /// Add a tunnel to the list shared by every tunnel of this process
+    pub fn register_tunnel(state: &Arc<Self>) {
+        state
+            .tunnels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::downgrade(state));
+    }
+
+    /// Every registered tunnel of this process, in config order
+    pub fn tunnel_states(&self) -> Vec<Arc<AppState>> {
+        self.tunnels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
+    /// Tunnel id sent in every envelope
+    pub fn tunnel_id(&self) -> String {
+        self.settings().tunnel_name.clone()
+    }
+
+    /// Client id sent in every envelope
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// What the relay last reported about this tunnel
+    pub fn relay_session(&self) -> RelaySession {
+        self.session
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Update the relay session details
+    pub fn update_relay_session(&self, update: impl FnOnce(&mut RelaySession)) {
+        update(&mut self.session.write().unwrap_or_else(|e| e.into_inner()));
     }
 
-    pub async fn get_stats(&self) -> AppStats {
-        self.stats.read().await.clone()
+    /// Last reported relay connection state of this tunnel
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.status
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Current settings; a reload swaps in a new snapshot
+    pub fn settings(&self) -> Arc<AppSettings> {
+        self.settings
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Current redactor for logs, dashboard events and the inspector
+    pub fn redactor(&self) -> Arc<Redactor> {
+        self.redactor
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Swap in validated settings and rebuild the state derived from them
+    pub fn apply_settings(&self, settings: AppSettings) {
+        if let Ok(redactor) = Redactor::from_config(&settings.redaction) {
+            *self.redactor.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(redactor);
+        }
+        self.access_log.reconfigure(settings.access_log.clone());
+        *self.settings.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(settings);
+    }
+
+    /// Ask the WebSocket client to close the relay connection and connect again
+    pub fn request_reconnect(&self) {
+        self.reconnect.notify_one();
+    }
+
+    /// Wait until a reconnect is requested
+    pub async fn reconnect_requested(&self) {
+        self.reconnect.notified().await;
+    }
+
+    /// Stop (or resume) accepting new tunneled requests
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::Relaxed);
+    }
+
+    /// Whether new tunneled requests are refused while in-flight ones finish
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Record a relay connection state change and notify the dashboard
+    pub fn report_connection_status(&self, status: ConnectionStatus) {
+        self.tunnel_metrics.set_connection_state(&status);
+        *self.status.write().unwrap_or_else(|e| e.into_inner()) = status.clone();
+        let _ = self
+            .dashboard_tx
+            .send(DashboardEvent::ConnectionStatus(status));
     }
 }
 
@@ -99,21 +278,30 @@ pub struct AppChannels {
     pub websocket_rx: mpsc::UnboundedReceiver<TunnelMessage>,
 }
 
+/// Channel receivers for an additional tunnel
+pub struct TunnelChannels {
+    pub proxy_rx: mpsc::UnboundedReceiver<HttpMessage>,
+    pub websocket_rx: mpsc::UnboundedReceiver<TunnelMessage>,
+}
+
 /// Initialize and run the application
-pub async fn run_application(settings: AppSettings) -> Result<()> {
+pub async fn run_application(settings: AppSettings, client_id: String) -> Result<()> {
     info!("Initializing pori application components");
 
-    // Create application state and channels
-    let (app_state, channels) = AppState::new(settings);
+    // Create application state and channels; the first tunnel owns the dashboard
+    let tunnels = settings.tunnel_settings();
+    let (mut app_state, channels) = AppState::new(settings);
+    app_state.client_id = client_id;
     let app_state = Arc::new(app_state);
+    AppState::register_tunnel(&app_state);
 
     info!("Application state initialized");
 
     // Start application components concurrently
-    let dashboard_task = if !app_state.settings.no_dashboard {
+    let dashboard_task = if !app_state.settings().no_dashboard {
         local_log!(
             "Starting a dashboard server on port {}",
-            app_state.settings.dashboard.port
+            app_state.settings().dashboard.port
         );
         Some(tokio::spawn({
             let state = app_state.clone();
@@ -128,67 +316,123 @@ pub async fn run_application(settings: AppSettings) -> Result<()> {
         None
     };
 
-    info!(
-        "Starting proxy forwarder for local server: {}",
-        app_state.settings.local_server.url
+    // Every tunnel runs its own proxy forwarder and relay connection
+    let mut tunnel_tasks = JoinSet::new();
+    let mut tunnel_names = HashMap::new();
+    spawn_tunnel(
+        &mut tunnel_tasks,
+        &mut tunnel_names,
+        app_state.clone(),
+        channels.proxy_rx,
+        channels.websocket_rx,
     );
-    let proxy_task = tokio::spawn({
-        let state = app_state.clone();
-        async move {
-            if let Err(e) = proxy::run_proxy_forwarder(state, channels.proxy_rx).await {
-                error!("Proxy forwarder error: {}", e);
-            }
-        }
-    });
-
-    proxy_log!(
-        "Starting WebSocket client connecting to {}",
-        app_state.settings.websocket.url
-    );
-    let websocket_task = tokio::spawn({
-        let state = app_state.clone();
-        async move {
-            if let Err(e) = websocket::run_websocket_client(state, channels.websocket_rx).await {
-                error!("WebSocket client error: {}", e);
-            }
-        }
-    });
+    for settings in tunnels.into_iter().skip(1) {
+        let (state, channels) = app_state.for_tunnel(settings)?;
+        let state = Arc::new(state);
+        AppState::register_tunnel(&state);
+        spawn_tunnel(
+            &mut tunnel_tasks,
+            &mut tunnel_names,
+            state,
+            channels.proxy_rx,
+            channels.websocket_rx,
+        );
+    }
 
     info!("All components started successfully - Pori is ready!");
 
+    // Reopen the access log on SIGUSR1
+    utils::signals::setup_signal_handlers(app_state.clone());
+
+    // Apply config file changes on edit or SIGHUP
+    let reload_task = tokio::spawn(config::reload::ConfigReloader::new(app_state.clone()).run());
+
     // Wait for a shutdown signal
-    let shutdown_task = tokio::spawn(async {
+    let mut shutdown_task = tokio::spawn(async {
         utils::signals::wait_for_shutdown().await;
         info!("Shutdown signal received");
     });
 
-    // Wait for any task to complete (which should only happen on shutdown or error)
-    tokio::select! {
-        _ = shutdown_task => info!("Application shutting down gracefully"),
-        result = proxy_task => {
-            if let Err(e) = result {
-                error!("Proxy task panicked: {}", e);
-            }
+    let dashboard = async {
+        if let Some(task) = dashboard_task {
+            task.await
+        } else {
+            // If no dashboard, wait forever
+            std::future::pending().await
         }
-        result = websocket_task => {
-            if let Err(e) = result {
-                error!("WebSocket task panicked: {}", e);
+    };
+    tokio::pin!(dashboard);
+
+    // One tunnel stopping leaves the others running; exit on shutdown, a
+    // dashboard failure or once every tunnel has stopped
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_task => {
+                info!("Application shutting down gracefully");
+                break;
             }
-        }
-        result = async {
-            if let Some(task) = dashboard_task {
-                task.await
-            } else {
-                // If no dashboard, wait forever
-                std::future::pending().await
+            Some(result) = tunnel_tasks.join_next_with_id() => {
+                match result {
+                    Ok((id, ())) => {
+                        let name = tunnel_names.remove(&id).unwrap_or_default();
+                        warn!("[{}] Tunnel task stopped", name);
+                    }
+                    Err(e) => {
+                        let name = tunnel_names.remove(&e.id()).unwrap_or_default();
+                        error!("[{}] Tunnel task panicked: {}", name, e);
+                    }
+                }
+                if tunnel_tasks.is_empty() {
+                    error!("All tunnels stopped");
+                    break;
+                }
             }
-        } => {
-            if let Err(e) = result {
-                error!("Dashboard task panicked: {}", e);
+            result = &mut dashboard => {
+                if let Err(e) = result {
+                    error!("Dashboard task panicked: {}", e);
+                }
+                break;
             }
         }
     }
 
+    reload_task.abort();
     info!("Application stopped");
     Ok(())
 }
+
+/// Start the proxy forwarder and WebSocket client of one tunnel
+fn spawn_tunnel(
+    tasks: &mut JoinSet<()>,
+    names: &mut HashMap<task::Id, String>,
+    state: Arc<AppState>,
+    proxy_rx: mpsc::UnboundedReceiver<HttpMessage>,
+    websocket_rx: mpsc::UnboundedReceiver<TunnelMessage>,
+) {
+    let settings = state.settings();
+    info!(
+        "[{}] Starting proxy forwarder for local server: {}",
+        settings.tunnel_name, settings.local_server.url
+    );
+    let forwarder = tasks.spawn({
+        let state = state.clone();
+        async move {
+            if let Err(e) = proxy::run_proxy_forwarder(state, proxy_rx).await {
+                error!("Proxy forwarder error: {}", e);
+            }
+        }
+    });
+    names.insert(forwarder.id(), settings.tunnel_name.clone());
+
+    proxy_log!(
+        "[{}] Starting WebSocket client connecting to {}",
+        settings.tunnel_name,
+        settings.websocket.url
+    );
+    let client = tasks.spawn(async move {
+        if let Err(e) = websocket::run_websocket_client(state, websocket_rx).await {
+            error!("WebSocket client error: {}", e);
+        }
+    });
+    names.insert(client.id(), settings.tunnel_name.clone());
+}
@@ -0,0 +1,425 @@
+use chrono::{DateTime, Local};
+use serde_json::json;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::settings::{AccessLogConfig, AccessLogFormat, AccessLogRotation};
+
+/// One completed request
+#[derive(Debug, Clone)]
+pub struct AccessEntry {
+    pub timestamp: DateTime<Local>,
+    pub request_id: String,
+    pub client_ip: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub bytes: usize,
+    /// Time spent waiting on the local server, if the request reached it
+    pub upstream_latency: Option<Duration>,
+    /// Total time from receiving the request to sending the response
+    pub duration: Duration,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Access log with size and time based rotation
+///
+/// File I/O happens on a dedicated writer thread, started with the first
+/// entry, so recording never blocks the async runtime.
+pub struct AccessLog {
+    writer: Arc<Writer>,
+    sender: OnceLock<mpsc::Sender<Command>>,
+}
+
+/// Work for the writer thread
+enum Command {
+    Record(Box<AccessEntry>),
+    Reopen,
+}
+
+/// State owned by the writer thread
+struct Writer {
+    config: RwLock<AccessLogConfig>,
+    file: Mutex<Option<OpenFile>>,
+}
+
+/// Currently open log file
+struct OpenFile {
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+impl AccessLog {
+    pub fn new(config: AccessLogConfig) -> Self {
+        Self {
+            writer: Arc::new(Writer {
+                config: RwLock::new(config),
+                file: Mutex::new(None),
+            }),
+            sender: OnceLock::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.writer.config().enabled
+    }
+
+    /// Switch to a new configuration, reopening the file on the next entry
+    pub fn reconfigure(&self, config: AccessLogConfig) {
+        *self
+            .writer
+            .config
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = config;
+        self.reopen();
+    }
+
+    /// Queue an entry for the writer thread
+    pub fn record(&self, entry: AccessEntry) {
+        if !self.is_enabled() {
+            return;
+        }
+        let sender = self
+            .sender
+            .get_or_init(|| spawn_writer(self.writer.clone()));
+        let _ = sender.send(Command::Record(Box::new(entry)));
+    }
+
+    /// Close the file so the next entry reopens it (after external rotation)
+    pub fn reopen(&self) {
+        if let Some(sender) = self.sender.get() {
+            let _ = sender.send(Command::Reopen);
+        }
+    }
+}
+
+/// Start the thread that writes queued entries in order
+fn spawn_writer(writer: Arc<Writer>) -> mpsc::Sender<Command> {
+    let (sender, receiver) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("access-log".to_string())
+        .spawn(move || {
+            for command in receiver {
+                match command {
+                    Command::Record(entry) => writer.write(&entry),
+                    Command::Reopen => writer.close(),
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start the access log writer: {}", e);
+    }
+    sender
+}
+
+impl Writer {
+    fn config(&self) -> AccessLogConfig {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Append an entry, rotating first if needed
+    fn write(&self, entry: &AccessEntry) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let config = self.config();
+        if !config.enabled {
+            return;
+        }
+
+        let mut line = format_entry(config.format, entry);
+        line.push('\n');
+
+        if let Err(e) = write_line(&config, &mut file, &line, entry.timestamp) {
+            warn!("Failed to write access log {}: {}", config.path, e);
+            *file = None;
+        }
+    }
+
+    /// Close the file so the next entry reopens it
+    fn close(&self) {
+        *self.file.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Write one line, rotating the file first if it is full or its period ended
+fn write_line(
+    config: &AccessLogConfig,
+    file: &mut Option<OpenFile>,
+    line: &str,
+    now: DateTime<Local>,
+) -> std::io::Result<()> {
+    let period = period_key(config.rotation, now);
+    let max_size = config.max_size_mb * 1024 * 1024;
+
+    if file.is_none() {
+        *file = Some(open_file(config)?);
+    }
+
+    let needs_rotation = file.as_ref().is_some_and(|open| {
+        let size_exceeded =
+            max_size > 0 && open.size > 0 && open.size + line.len() as u64 > max_size;
+        let period_changed = open.period.is_some() && open.period != period;
+        size_exceeded || period_changed
+    });
+    if needs_rotation {
+        *file = None;
+        rotate(config, now)?;
+        *file = Some(open_file(config)?);
+    }
+
+    if let Some(open) = file.as_mut() {
+        open.file.write_all(line.as_bytes())?;
+        open.size += line.len() as u64;
+        if open.period.is_none() {
+            open.period = period;
+        }
+    }
+    Ok(())
+}
+
+/// Open the log for appending, creating parent directories
+fn open_file(config: &AccessLogConfig) -> std::io::Result<OpenFile> {
+    let path = Path::new(&config.path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    // Files carried over from a previous run belong to the period they were last written in
+    let period = metadata
+        .modified()
+        .ok()
+        .filter(|_| metadata.len() > 0)
+        .and_then(|modified| period_key(config.rotation, modified.into()));
+
+    Ok(OpenFile {
+        file,
+        size: metadata.len(),
+        period,
+    })
+}
+
+/// Move the current file aside and prune old ones
+fn rotate(config: &AccessLogConfig, now: DateTime<Local>) -> std::io::Result<()> {
+    let path = PathBuf::from(&config.path);
+    let stamp = now.format("%Y%m%d-%H%M%S").to_string();
+    let mut target = PathBuf::from(format!("{}.{stamp}", config.path));
+    let mut suffix = 1;
+    while target.exists() {
+        target = PathBuf::from(format!("{}.{stamp}-{suffix}", config.path));
+        suffix += 1;
+    }
+    fs::rename(&path, &target)?;
+
+    if config.max_files > 0 {
+        let mut rotated = rotated_files(config)?;
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(config.max_files);
+        for old in rotated.into_iter().take(excess) {
+            fs::remove_file(old)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rotated files next to the log (`<name>.%Y%m%d-%H%M%S[-N]`); files
+/// other tools left there, such as compressed archives, are not touched
+fn rotated_files(config: &AccessLogConfig) -> std::io::Result<Vec<PathBuf>> {
+    let path = Path::new(&config.path);
+    let dir = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => dir.to_path_buf(),
+        None => PathBuf::from("."),
+    };
+    let prefix = format!(
+        "{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+
+    Ok(fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|name| {
+                    name.to_string_lossy()
+                        .strip_prefix(&prefix)
+                        .is_some_and(is_rotation_stamp)
+                })
+                .unwrap_or(false)
+        })
+        .collect())
+}
+
+/// Whether a suffix is a rotation timestamp with an optional counter
+fn is_rotation_stamp(suffix: &str) -> bool {
+    let digits = |s: &[u8]| !s.is_empty() && s.iter().all(u8::is_ascii_digit);
+    let bytes = suffix.as_bytes();
+    if bytes.len() < 15 || bytes[8] != b'-' || !digits(&bytes[..8]) || !digits(&bytes[9..15]) {
+        return false;
+    }
+    match &bytes[15..] {
+        [] => true,
+        [b'-', counter @ ..] => digits(counter),
+        _ => false,
+    }
+}
+
+/// Key identifying the rotation period a timestamp falls in
+fn period_key(rotation: AccessLogRotation, time: DateTime<Local>) -> Option<String> {
+    match rotation {
+        AccessLogRotation::Never => None,
+        AccessLogRotation::Hourly => Some(time.format("%Y%m%d%H").to_string()),
+        AccessLogRotation::Daily => Some(time.format("%Y%m%d").to_string()),
+    }
+}
+
+/// Render one access log line (without the trailing newline)
+pub fn format_entry(format: AccessLogFormat, entry: &AccessEntry) -> String {
+    let upstream_ms = entry
+        .upstream_latency
+        .map(|latency| latency.as_secs_f64() * 1000.0);
+
+    match format {
+        AccessLogFormat::Json => json!({
+            "timestamp": entry.timestamp.to_rfc3339(),
+            "request_id": entry.request_id,
+            "client_ip": entry.client_ip,
+            "method": entry.method,
+            "path": entry.path,
+            "status": entry.status,
+            "bytes": entry.bytes,
+            "upstream_ms": upstream_ms,
+            "duration_ms": entry.duration.as_secs_f64() * 1000.0,
+            "referer": entry.referer,
+            "user_agent": entry.user_agent,
+        })
+        .to_string(),
+        AccessLogFormat::Common | AccessLogFormat::Combined => {
+            let mut line = format!(
+                "{} - - [{}] \"{} {} HTTP/1.1\" {} {}",
+                entry.client_ip,
+                entry.timestamp.format("%d/%b/%Y:%H:%M:%S %z"),
+                entry.method,
+                entry.path,
+                entry.status,
+                if entry.bytes == 0 {
+                    "-".to_string()
+                } else {
+                    entry.bytes.to_string()
+                }
+            );
+            if format == AccessLogFormat::Combined {
+                line.push_str(&format!(
+                    " \"{}\" \"{}\"",
+                    quoted(entry.referer.as_deref()),
+                    quoted(entry.user_agent.as_deref())
+                ));
+            }
+            let upstream = upstream_ms
+                .map(|ms| format!("{ms:.3}"))
+                .unwrap_or_else(|| "-".to_string());
+            line.push_str(&format!(
+                " request_id={} upstream_ms={upstream}",
+                entry.request_id
+            ));
+            line
+        }
+    }
+}
+
+/// Escape a header value for a quoted log field
+fn quoted(value: Option<&str>) -> String {
+    value
+        .map(|v| v.replace('\\', "\\\\").replace('"', "\\\""))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry() -> AccessEntry {
+        AccessEntry {
+            timestamp: Local.with_ymd_and_hms(2025, 7, 14, 9, 30, 0).unwrap(),
+            request_id: "req-1".to_string(),
+            client_ip: "203.0.113.7".to_string(),
+            method: "GET".to_string(),
+            path: "/api/users?page=2".to_string(),
+            status: 200,
+            bytes: 512,
+            upstream_latency: Some(Duration::from_millis(12)),
+            duration: Duration::from_millis(15),
+            referer: None,
+            user_agent: Some("curl/8.5.0".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_line_formats() {
+        let common = format_entry(AccessLogFormat::Common, &entry());
+        assert!(common.starts_with("203.0.113.7 - - [14/Jul/2025:09:30:00 "));
+        assert!(common.ends_with(
+            "\"GET /api/users?page=2 HTTP/1.1\" 200 512 request_id=req-1 upstream_ms=12.000"
+        ));
+
+        let combined = format_entry(AccessLogFormat::Combined, &entry());
+        assert!(combined.contains("200 512 \"-\" \"curl/8.5.0\" request_id=req-1"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_entry(AccessLogFormat::Json, &entry())).unwrap();
+        assert_eq!(json["request_id"], "req-1");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["upstream_ms"], 12.0);
+    }
+
+    #[test]
+    fn test_size_rotation_and_retention() {
+        let dir = std::env::temp_dir().join(format!("pori-access-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("access.log");
+        let log = AccessLog::new(AccessLogConfig {
+            enabled: true,
+            path: path.to_string_lossy().into_owned(),
+            format: AccessLogFormat::Json,
+            max_size_mb: 1,
+            rotation: AccessLogRotation::Never,
+            max_files: 2,
+        });
+
+        // Each 700 KB line pushes the file past 1 MB, so every write after the first rotates
+        let mut big = entry();
+        big.path = format!("/{}", "x".repeat(700 * 1024));
+
+        // Archives made by other tools are not counted or pruned
+        fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("access.log.1.gz");
+        fs::write(&archive, b"old").unwrap();
+
+        for i in 0..4 {
+            let mut e = big.clone();
+            e.timestamp += chrono::Duration::seconds(i);
+            log.writer.write(&e);
+        }
+
+        assert_eq!(rotated_files(&log.writer.config()).unwrap().len(), 2);
+        assert!(path.exists());
+        assert!(archive.exists());
+
+        // Reopen continues appending to the same path
+        log.writer.close();
+        log.writer.write(&entry());
+        let current = fs::read_to_string(&path).unwrap();
+        assert!(current.lines().last().unwrap().contains("\"req-1\""));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
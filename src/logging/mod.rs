@@ -1,12 +1,31 @@
+pub mod access;
+pub mod redact;
+
 use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+use tracing::field::{Field, Visit};
 use tracing::Level;
 use tracing_subscriber::{
-    fmt::{self, format::Writer, time::FormatTime, FormatEvent, FormatFields},
+    fmt::{
+        self,
+        format::{JsonFields, Writer},
+        time::FormatTime,
+        FormatEvent, FormatFields, FormattedFields, MakeWriter,
+    },
     prelude::*,
     registry::LookupSpan,
-    EnvFilter,
+    reload, EnvFilter, Registry,
 };
 
+use crate::config::settings::{LogFormat, LoggingSettings, TelemetryConfig};
+use crate::telemetry::BoxedLayer;
+
+/// Handle for swapping the log output filter at runtime
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+static FILTER: OnceLock<FilterHandle> = OnceLock::new();
+
 /// Custom time formatter for human-readable timestamps
 pub struct HumanTime;
 
@@ -17,6 +36,15 @@ impl FormatTime for HumanTime {
     }
 }
 
+/// Short time-of-day timestamps for the compact format
+pub struct CompactTime;
+
+impl FormatTime for CompactTime {
+    fn format_time(&self, w: &mut fmt::format::Writer<'_>) -> std::fmt::Result {
+        write!(w, "{}", chrono::Local::now().format("%H:%M:%S%.3f"))
+    }
+}
+
 /// Custom event formatter that replaces INFO with PROXY/LOCAL when appropriate
 pub struct CustomFormatter {
     pub show_context: bool,
@@ -102,32 +130,204 @@ where
     }
 }
 
-/// Initialize logging system
-pub fn init_with_context(log_level: &str, show_context: bool) -> Result<()> {
-    // Parse log level
-    let level = parse_log_level(log_level)?;
+/// Initialize logging in the configured format, plus OpenTelemetry export when configured
+pub fn init(logging: &LoggingSettings, telemetry: &TelemetryConfig) -> Result<()> {
+    // A level name or full filter directives (relay overrides may set the latter)
+    let directives = resolve_directives(&logging.level)?;
 
-    // Create environment filter
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| match level {
-        Level::INFO => EnvFilter::new("pori=info,PROXY=info,LOCAL=info,tower=warn,hyper=warn"),
-        Level::DEBUG => EnvFilter::new("pori=debug,PROXY=debug,LOCAL=debug,tower=warn,hyper=warn"),
-        Level::TRACE => EnvFilter::new("pori=trace,PROXY=trace,LOCAL=trace,tower=warn,hyper=warn"),
-        Level::WARN => EnvFilter::new("pori=warn,PROXY=warn,LOCAL=warn,tower=warn,hyper=warn"),
-        Level::ERROR => EnvFilter::new("pori=error,PROXY=error,LOCAL=error,tower=warn,hyper=warn"),
-    });
+    // Create environment filter, reloadable at runtime. It only applies to
+    // log output; exported spans have their own filter.
+    let env_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(directives));
+    let (env_filter, filter_handle) = reload::Layer::new(env_filter);
 
-    tracing_subscriber::registry()
-        .with(
-            fmt::layer()
-                .event_format(CustomFormatter { show_context })
-                .with_ansi(atty::is(atty::Stream::Stdout)),
-        )
-        .with(env_filter)
-        .init();
+    let mut layers = vec![format_layer(logging, std::io::stdout)
+        .with_filter(env_filter)
+        .boxed()];
+    layers.extend(crate::telemetry::layer(telemetry)?);
+
+    tracing_subscriber::registry().with(layers).init();
+    let _ = FILTER.set(filter_handle);
 
     Ok(())
 }
 
+/// Filter directives used for a plain `--log-level`
+fn default_directives(level: Level) -> String {
+    let level = level.as_str().to_lowercase();
+    format!("pori={level},PROXY={level},LOCAL={level},tower=warn,hyper=warn")
+}
+
+/// Current filter directives, if logging has been initialized
+pub fn current_filter() -> Option<String> {
+    FILTER
+        .get()
+        .and_then(|handle| handle.with_current(|filter| filter.to_string()).ok())
+}
+
+/// Replace the active filter, returning the directives now in effect
+///
+/// Accepts either a directive string (`PROXY=debug,hyper=warn`) or a bare
+/// level name, which expands to the same defaults as `--log-level`.
+pub fn set_filter(directives: &str) -> Result<String> {
+    let directives = resolve_directives(directives)?;
+    let handle = FILTER
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Logging is not initialized"))?;
+
+    handle.reload(EnvFilter::try_new(&directives)?)?;
+    tracing::info!("Log filter changed to {}", directives);
+    Ok(directives)
+}
+
+/// Expand level names and validate directive syntax
+pub fn resolve_directives(directives: &str) -> Result<String> {
+    let directives = directives.trim();
+    if directives.is_empty() {
+        anyhow::bail!("Log filter cannot be empty");
+    }
+
+    let directives = match parse_log_level(directives) {
+        Ok(level) => default_directives(level),
+        Err(_) => directives.to_string(),
+    };
+    EnvFilter::try_new(&directives)
+        .map_err(|e| anyhow::anyhow!("Invalid log filter '{}': {}", directives, e))?;
+    Ok(directives)
+}
+
+/// Build the formatting layer for the configured log format
+fn format_layer<W>(logging: &LoggingSettings, writer: W) -> BoxedLayer
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    // Colors only make sense on a terminal and never in JSON
+    let ansi = logging.enable_color && atty::is(atty::Stream::Stdout);
+
+    match logging.format {
+        LogFormat::Pretty => fmt::layer()
+            .event_format(CustomFormatter {
+                show_context: logging.show_context,
+            })
+            .with_ansi(ansi)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Compact => fmt::layer()
+            .compact()
+            .with_timer(CompactTime)
+            .with_ansi(ansi)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Json => fmt::layer()
+            .event_format(JsonFormatter)
+            .fmt_fields(JsonFields::new())
+            .with_ansi(false)
+            .with_writer(writer)
+            .boxed(),
+    }
+}
+
+/// Event formatter that writes one JSON object per line
+///
+/// Span fields are collected from the current scope (outermost first) and a
+/// request ID found on any span is promoted to a top-level `request_id`.
+/// Requires `JsonFields` as the field formatter.
+pub struct JsonFormatter;
+
+impl<S, N> FormatEvent<S, N> for JsonFormatter
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &fmt::FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> std::fmt::Result {
+        let metadata = event.metadata();
+        let mut object = Map::new();
+        object.insert(
+            "timestamp".to_string(),
+            json!(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        );
+        object.insert("level".to_string(), json!(metadata.level().as_str()));
+        object.insert("target".to_string(), json!(metadata.target()));
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        if let Some(message) = fields.remove("message") {
+            object.insert("message".to_string(), message);
+        }
+
+        let mut spans = Vec::new();
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                let span_fields = extensions
+                    .get::<FormattedFields<N>>()
+                    .and_then(|formatted| {
+                        serde_json::from_str::<Map<String, Value>>(formatted).ok()
+                    })
+                    .unwrap_or_default();
+
+                for key in ["request_id", "requestId"] {
+                    if let Some(id) = span_fields.get(key) {
+                        object.insert("request_id".to_string(), id.clone());
+                    }
+                }
+
+                let mut entry = Map::new();
+                entry.insert("name".to_string(), json!(span.name()));
+                entry.extend(span_fields);
+                spans.push(Value::Object(entry));
+            }
+        }
+
+        if let Some(id) = fields.remove("request_id") {
+            object.insert("request_id".to_string(), id);
+        }
+        if !fields.is_empty() {
+            object.insert("fields".to_string(), Value::Object(fields));
+        }
+        if !spans.is_empty() {
+            object.insert("spans".to_string(), Value::Array(spans));
+        }
+
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
+
+/// Collects event fields into a JSON map
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), json!(format!("{value:?}")));
+    }
+}
+
 /// Parse log level string to tracing Level
 fn parse_log_level(level: &str) -> Result<Level> {
     match level.to_lowercase().as_str() {
@@ -157,3 +357,89 @@ macro_rules! local_log {
         tracing::info!(target: "LOCAL", $($arg)*)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn settings(format: LogFormat) -> LoggingSettings {
+        LoggingSettings {
+            level: "info".to_string(),
+            format,
+            enable_color: false,
+            show_context: false,
+        }
+    }
+
+    fn capture(format: LogFormat) -> String {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(format_layer(&settings(format), move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "tunnel_request",
+                requestId = "req-7",
+                http.route = "/users/:id"
+            );
+            let _guard = span.enter();
+            crate::proxy_log!(status = 200, "Response sent");
+        });
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_directives() {
+        assert_eq!(
+            resolve_directives("debug").unwrap(),
+            "pori=debug,PROXY=debug,LOCAL=debug,tower=warn,hyper=warn"
+        );
+        assert_eq!(
+            resolve_directives(" PROXY=debug,hyper=warn ").unwrap(),
+            "PROXY=debug,hyper=warn"
+        );
+        assert!(resolve_directives("").is_err());
+        assert!(resolve_directives("pori=loud").is_err());
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let output = capture(LogFormat::Json);
+        assert_eq!(output.lines().count(), 1);
+
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["target"], "PROXY");
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "Response sent");
+        assert_eq!(line["request_id"], "req-7");
+        assert_eq!(line["fields"]["status"], 200);
+        assert_eq!(line["spans"][0]["name"], "tunnel_request");
+        assert_eq!(line["spans"][0]["http.route"], "/users/:id");
+    }
+
+    #[test]
+    fn test_compact_respects_enable_color() {
+        let output = capture(LogFormat::Compact);
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.contains("Response sent"));
+        assert!(output.contains("PROXY"));
+        assert!(!output.contains('\x1b'));
+    }
+}
@@ -0,0 +1,470 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::settings::RedactionConfig;
+
+/// Replacement for masked values
+pub const MASK: &str = "[REDACTED]";
+
+/// Headers that are always masked when redaction is enabled
+pub const DEFAULT_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "x-auth-token",
+    "x-csrf-token",
+    "x-xsrf-token",
+    "x-hub-signature",
+    "x-hub-signature-256",
+    "stripe-signature",
+    "x-slack-signature",
+];
+
+/// Query parameters that are always masked when redaction is enabled
+pub const DEFAULT_QUERY_PARAMS: &[&str] = &[
+    "token",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "api_key",
+    "apikey",
+    "key",
+    "secret",
+    "client_secret",
+    "password",
+    "signature",
+    "sig",
+    "code",
+];
+
+/// Masks secrets before requests reach logs, the dashboard or the inspector
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    enabled: bool,
+    headers: HashSet<String>,
+    query_params: HashSet<String>,
+    body_fields: Vec<Vec<Segment>>,
+    max_body_preview: usize,
+    max_frame_preview: usize,
+}
+
+/// One step of a body field path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Any,
+    /// `..key`: the key at any depth
+    Descendant(String),
+}
+
+impl Redactor {
+    /// Build a redactor, validating the body field paths
+    pub fn from_config(config: &RedactionConfig) -> Result<Self> {
+        let lowercase = |names: &[String], defaults: &[&str]| -> HashSet<String> {
+            defaults
+                .iter()
+                .map(|name| name.to_string())
+                .chain(names.iter().map(|name| name.to_ascii_lowercase()))
+                .collect()
+        };
+
+        Ok(Self {
+            enabled: config.enabled,
+            headers: lowercase(&config.headers, DEFAULT_HEADERS),
+            query_params: lowercase(&config.query_params, DEFAULT_QUERY_PARAMS),
+            body_fields: config
+                .body_fields
+                .iter()
+                .map(|path| parse_path(path))
+                .collect::<Result<_>>()?,
+            max_body_preview: config.max_body_preview,
+            max_frame_preview: config.max_frame_preview,
+        })
+    }
+
+    /// Copy of the headers with sensitive values masked
+    pub fn headers(&self, headers: &HashMap<String, String>) -> HashMap<String, String> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.is_sensitive_header(name) {
+                    MASK.to_string()
+                } else {
+                    value.clone()
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Mask sensitive query parameters in a URL, path or any text containing URLs
+    pub fn url(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find(['?', '&']) {
+            out.push_str(&rest[..=pos]);
+            rest = &rest[pos + 1..];
+
+            let name_end = rest
+                .find(|c: char| c == '=' || c == '&' || c == '?' || ends_query(c))
+                .unwrap_or(rest.len());
+            if rest[name_end..].starts_with('=') && self.is_sensitive_param(&rest[..name_end]) {
+                out.push_str(&rest[..=name_end]);
+                out.push_str(MASK);
+                let value = &rest[name_end + 1..];
+                let value_end = value
+                    .find(|c: char| c == '&' || ends_query(c))
+                    .unwrap_or(value.len());
+                rest = &value[value_end..];
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Loggable preview of a request or response body
+    pub fn body(&self, body: &[u8], content_type: Option<&str>) -> String {
+        if self.max_body_preview == 0 {
+            return format!("<{} bytes>", body.len());
+        }
+        let Ok(text) = std::str::from_utf8(body) else {
+            return format!("<binary, {} bytes>", body.len());
+        };
+
+        let content_type = content_type.unwrap_or_default().to_ascii_lowercase();
+        let redacted = if !self.enabled {
+            text.to_string()
+        } else if content_type.contains("x-www-form-urlencoded") {
+            self.url(&format!("&{text}"))[1..].to_string()
+        } else {
+            match serde_json::from_str::<Value>(text) {
+                Ok(mut json) if json.is_object() || json.is_array() => {
+                    self.mask_body_fields(&mut json);
+                    json.to_string()
+                }
+                _ => text.to_string(),
+            }
+        };
+
+        truncate(redacted, self.max_body_preview, body.len())
+    }
+
+    /// Loggable preview of a raw relay frame
+    pub fn frame(&self, text: &str) -> String {
+        let redacted = match serde_json::from_str::<Value>(text) {
+            Ok(mut json) if self.enabled => {
+                self.mask_frame(&mut json);
+                json.to_string()
+            }
+            _ => self.url(text),
+        };
+        truncate(redacted, self.max_frame_preview, text.len())
+    }
+
+    fn is_sensitive_header(&self, name: &str) -> bool {
+        self.enabled && self.headers.contains(&name.to_ascii_lowercase())
+    }
+
+    fn is_sensitive_param(&self, name: &str) -> bool {
+        self.query_params.contains(&name.to_ascii_lowercase())
+    }
+
+    fn mask_body_fields(&self, json: &mut Value) {
+        for path in &self.body_fields {
+            mask_path(json, path);
+        }
+    }
+
+    /// Mask headers, tokens, URLs and bodies anywhere in a relay frame
+    fn mask_frame(&self, json: &mut Value) {
+        match json {
+            Value::Object(map) => {
+                let content_type = map
+                    .get("headers")
+                    .and_then(Value::as_object)
+                    .and_then(|headers| {
+                        headers
+                            .iter()
+                            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    })
+                    .and_then(|(_, value)| value.as_str())
+                    .map(str::to_string);
+                for (key, value) in map.iter_mut() {
+                    let lower = key.to_ascii_lowercase();
+                    if !value.is_object()
+                        && !value.is_array()
+                        && (self.headers.contains(&lower) || self.query_params.contains(&lower))
+                    {
+                        *value = Value::String(MASK.to_string());
+                        continue;
+                    }
+                    if key == "body" {
+                        // Same preview as logged bodies, whichever shape the frame used
+                        if let Some(body) = frame_body(value) {
+                            *value = Value::String(self.body(&body, content_type.as_deref()));
+                            continue;
+                        }
+                    }
+                    self.mask_frame(value);
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.mask_frame(item)),
+            Value::String(text) => *text = self.url(text),
+            _ => {}
+        }
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::from_config(&RedactionConfig::default()).expect("default redaction config is valid")
+    }
+}
+
+/// Raw bytes of a frame body sent as a JSON value, a string or a byte array
+fn frame_body(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.as_bytes().to_vec()),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .or_else(|| Some(value.to_string().into_bytes())),
+        _ => Some(value.to_string().into_bytes()),
+    }
+}
+
+/// Characters that end a query value embedded in surrounding text
+fn ends_query(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '#' | '"' | '\'' | '<' | '>' | ')')
+}
+
+/// Cut a preview at `max` bytes on a character boundary
+fn truncate(mut text: String, max: usize, total: usize) -> String {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!("… ({total} bytes total)"));
+    text
+}
+
+/// Parse a JSON path like `$.user.password`, `$..token` or `$.items[*].secret`
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let trimmed = path.trim();
+    let mut rest = trimmed.strip_prefix('$').unwrap_or(trimmed);
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            let (name, next) = take_name(after);
+            if name.is_empty() || name == "*" {
+                bail!("Invalid body field path '{path}': '..' must be followed by a field name");
+            }
+            segments.push(Segment::Descendant(name.to_string()));
+            rest = next;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let Some(end) = after.find(']') else {
+                bail!("Invalid body field path '{path}': unclosed '['");
+            };
+            let inner = after[..end].trim();
+            segments.push(if inner == "*" {
+                Segment::Any
+            } else if let Ok(index) = inner.parse() {
+                Segment::Index(index)
+            } else {
+                let name = inner.trim_matches(|c| c == '\'' || c == '"');
+                if name.is_empty() {
+                    bail!("Invalid body field path '{path}': empty brackets");
+                }
+                Segment::Key(name.to_string())
+            });
+            rest = &after[end + 1..];
+        } else {
+            let after = rest.strip_prefix('.').unwrap_or(rest);
+            let (name, next) = take_name(after);
+            if name.is_empty() {
+                bail!("Invalid body field path '{path}': empty field name");
+            }
+            segments.push(if name == "*" {
+                Segment::Any
+            } else {
+                Segment::Key(name.to_string())
+            });
+            rest = next;
+        }
+    }
+
+    if segments.is_empty() {
+        bail!("Invalid body field path '{path}': no fields selected");
+    }
+    Ok(segments)
+}
+
+/// Split a field name off the front of a path
+fn take_name(path: &str) -> (&str, &str) {
+    let end = path.find(['.', '[']).unwrap_or(path.len());
+    (&path[..end], &path[end..])
+}
+
+/// Replace every value matched by `path` with the mask
+fn mask_path(value: &mut Value, path: &[Segment]) {
+    let Some((segment, rest)) = path.split_first() else {
+        *value = Value::String(MASK.to_string());
+        return;
+    };
+
+    match segment {
+        Segment::Key(key) => {
+            if let Some(child) = value.get_mut(key.as_str()) {
+                mask_path(child, rest);
+            }
+        }
+        Segment::Index(index) => {
+            if let Some(child) = value.get_mut(*index) {
+                mask_path(child, rest);
+            }
+        }
+        Segment::Any => match value {
+            Value::Object(map) => map.values_mut().for_each(|child| mask_path(child, rest)),
+            Value::Array(items) => items.iter_mut().for_each(|child| mask_path(child, rest)),
+            _ => {}
+        },
+        Segment::Descendant(key) => match value {
+            Value::Object(map) => {
+                for (name, child) in map.iter_mut() {
+                    if name == key {
+                        mask_path(child, rest);
+                    } else {
+                        mask_path(child, path);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|child| mask_path(child, path)),
+            _ => {}
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor(body_fields: &[&str]) -> Redactor {
+        Redactor::from_config(&RedactionConfig {
+            headers: vec!["X-Internal-Secret".to_string()],
+            body_fields: body_fields.iter().map(|f| f.to_string()).collect(),
+            max_body_preview: 256,
+            ..RedactionConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_headers_and_urls() {
+        let redactor = redactor(&[]);
+        let headers = HashMap::from([
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+            ("x-internal-secret".to_string(), "s3cret".to_string()),
+            ("Accept".to_string(), "*/*".to_string()),
+        ]);
+        let masked = redactor.headers(&headers);
+        assert_eq!(masked["Authorization"], MASK);
+        assert_eq!(masked["x-internal-secret"], MASK);
+        assert_eq!(masked["Accept"], "*/*");
+
+        assert_eq!(
+            redactor.url("/cb?code=xyz&state=1&Token=t0k#frag"),
+            "/cb?code=[REDACTED]&state=1&Token=[REDACTED]#frag"
+        );
+        assert_eq!(
+            redactor.url("error sending request for url (http://localhost/x?api_key=k)"),
+            "error sending request for url (http://localhost/x?api_key=[REDACTED])"
+        );
+        assert_eq!(redactor.url("/plain/path"), "/plain/path");
+    }
+
+    #[test]
+    fn test_body_fields_and_preview() {
+        let redactor = redactor(&["$.password", "$..token", "$.cards[*].number"]);
+        let body = json!({
+            "user": "ann",
+            "password": "hunter2",
+            "session": { "token": "abc" },
+            "cards": [{ "number": "4111", "exp": "12/30" }]
+        })
+        .to_string();
+        let preview: Value =
+            serde_json::from_str(&redactor.body(body.as_bytes(), Some("application/json")))
+                .unwrap();
+        assert_eq!(preview["password"], MASK);
+        assert_eq!(preview["session"]["token"], MASK);
+        assert_eq!(preview["cards"][0]["number"], MASK);
+        assert_eq!(preview["cards"][0]["exp"], "12/30");
+
+        let form = redactor.body(
+            b"user=ann&password=hunter2",
+            Some("application/x-www-form-urlencoded"),
+        );
+        assert_eq!(form, "user=ann&password=[REDACTED]");
+
+        let long = redactor.body("é".repeat(200).as_bytes(), Some("text/plain"));
+        assert!(long.ends_with("… (400 bytes total)"));
+        assert_eq!(redactor.body(&[0xff, 0xfe], None), "<binary, 2 bytes>");
+
+        assert!(parse_path("$.items[").is_err());
+        assert!(parse_path("$").is_err());
+    }
+
+    #[test]
+    fn test_frame() {
+        let redactor = redactor(&["$.password"]);
+        let frame = json!({
+            "type": "http",
+            "url": "/login?token=abc",
+            "headers": { "Cookie": "sid=1", "Accept": "*/*" },
+            "body": { "password": "hunter2" }
+        })
+        .to_string();
+        let masked: Value = serde_json::from_str(&redactor.frame(&frame)).unwrap();
+        assert_eq!(masked["url"], "/login?token=[REDACTED]");
+        assert_eq!(masked["headers"]["Cookie"], MASK);
+        assert_eq!(masked["headers"]["Accept"], "*/*");
+        let body: Value = serde_json::from_str(masked["body"].as_str().unwrap()).unwrap();
+        assert_eq!(body["password"], MASK);
+
+        // Form bodies, including as byte arrays, mask their first parameter too
+        let form = b"password=hunter2&user=ann";
+        for body in [json!("password=hunter2&user=ann"), json!(form.to_vec())] {
+            let frame = json!({
+                "headers": { "Content-Type": "application/x-www-form-urlencoded" },
+                "body": body
+            })
+            .to_string();
+            let masked: Value = serde_json::from_str(&redactor.frame(&frame)).unwrap();
+            assert_eq!(masked["body"], "password=[REDACTED]&user=ann");
+        }
+
+        // Long bodies are cut to the preview size
+        let frame = json!({ "body": "x".repeat(1000) }).to_string();
+        let masked: Value = serde_json::from_str(&redactor.frame(&frame)).unwrap();
+        assert!(masked["body"]
+            .as_str()
+            .unwrap()
+            .ends_with("… (1000 bytes total)"));
+    }
+}
@@ -1,8 +1,11 @@
 use anyhow::Result;
 use clap::Parser;
-use tracing::info;
+use tracing::{info, warn};
 
-use pori::{config::cli::CliArgs, config::settings::AppSettings, logging, run_application};
+use pori::{
+    config, config::cli::CliArgs, config::settings::AppSettings, logging, run_application,
+    telemetry,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -13,13 +16,36 @@ async fn main() -> Result<()> {
     cli_args.validate()?;
 
     // Create application settings from CLI arguments
-    let settings = AppSettings::from_cli(cli_args)?;
+    let mut settings = AppSettings::from_cli(cli_args)?;
+    // Overrides may change the log level, so they apply before logging starts;
+    // a bad overrides file is reported below and does not block startup
+    let overrides = settings.apply_relay_overrides();
+    settings.validate()?;
 
-    // Initialize logging with show_context from settings
-    logging::init_with_context(&settings.logging.level, settings.logging.show_context)?;
+    // Initialize logging in the configured format
+    logging::init(&settings.logging, &settings.telemetry)?;
+    if let Err(e) = overrides {
+        warn!("Ignoring relay config overrides: {e:#}");
+    }
+
+    // Stable id the relay uses to tell clients apart across restarts
+    let client_id = config::identity::client_id();
 
-    // Print startup banner
+    // Print startup banner; public URLs follow once the relay assigns them
     println!("Starting Pori v{}", env!("CARGO_PKG_VERSION"));
+    println!("Client ID: {client_id}");
+    for tunnel in settings.tunnel_settings() {
+        let subdomain = tunnel
+            .websocket
+            .subdomain
+            .as_deref()
+            .map(|subdomain| format!(" (requested subdomain: {subdomain})"))
+            .unwrap_or_default();
+        println!(
+            "Tunnel {}: {} -> {}{}",
+            tunnel.tunnel_name, tunnel.websocket.url, tunnel.local_server.url, subdomain
+        );
+    }
     info!("Starting pori application");
     info!("WebSocket URL: {}", settings.websocket.url);
     info!("Local server: {}", settings.local_server.url);
@@ -30,8 +56,10 @@ async fn main() -> Result<()> {
 
     // ...existing code...
 
-    // Run application
-    run_application(settings).await?;
+    // Run application, then flush any buffered trace spans
+    let result = run_application(settings, client_id).await;
+    telemetry::shutdown();
+    result?;
 
     Ok(())
 }
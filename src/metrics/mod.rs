@@ -0,0 +1,653 @@
+pub mod prometheus;
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::ConnectionStatus;
+
+/// HTTP methods tracked with their own label, everything else is `OTHER`
+pub const METHODS: [&str; 8] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "OTHER",
+];
+
+/// Status classes tracked per method
+pub const STATUS_CLASSES: [&str; 5] = ["1xx", "2xx", "3xx", "4xx", "5xx"];
+
+/// Connection states exported as a one-hot gauge
+pub const CONNECTION_STATES: [&str; 5] = [
+    "connecting",
+    "connected",
+    "disconnected",
+    "reconnecting",
+    "error",
+];
+
+/// Upper bounds (seconds) of the request latency histogram buckets
+///
+/// Buckets are fine enough that percentiles read from them are within one
+/// bucket of the true value.
+pub const LATENCY_BUCKETS: [f64; 28] = [
+    0.001, 0.002, 0.003, 0.005, 0.0075, 0.01, 0.015, 0.02, 0.03, 0.05, 0.075, 0.1, 0.15, 0.2, 0.3,
+    0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 5.0, 7.5, 10.0, 15.0, 20.0, 30.0, 60.0,
+];
+
+/// Maximum number of distinct routes tracked; later routes are grouped
+const MAX_ROUTES: usize = 200;
+
+/// Route label used once `MAX_ROUTES` is reached
+const OVERFLOW_ROUTE: &str = "(other)";
+
+/// Internal queues whose depth is exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Queue {
+    /// Requests waiting for the proxy forwarder
+    Proxy,
+    /// Messages waiting to be sent to the relay
+    Websocket,
+}
+
+/// How a request ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The local server (or a built-in page) produced the response
+    Success,
+    /// Pori rejected the request or could not reach the local server
+    Failure,
+}
+
+/// Metrics registry shared by all components
+///
+/// Every counter is an atomic, so recording never blocks. The per-route table
+/// takes a write lock only the first time a route is seen. Each tunnel records
+/// into its own registry, which also feeds the process-wide parent.
+pub struct Metrics {
+    parent: Option<Arc<Metrics>>,
+    started: Instant,
+    requests: [[AtomicU64; STATUS_CLASSES.len()]; METHODS.len()],
+    requests_successful: AtomicU64,
+    requests_failed: AtomicU64,
+    latency: Histogram,
+    routes: RwLock<HashMap<String, Arc<RouteMetrics>>>,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    reconnects: AtomicU64,
+    relay_errors: AtomicU64,
+    proxy_queue: AtomicI64,
+    websocket_queue: AtomicI64,
+    in_flight: AtomicI64,
+    connection_state: AtomicU8,
+}
+
+/// Counters and latency histogram for one route
+pub struct RouteMetrics {
+    requests: AtomicU64,
+    failed: AtomicU64,
+    latency: Histogram,
+}
+
+/// Fixed-bucket latency histogram
+pub struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+/// Point-in-time view of the registry used by the API, dashboard and relay
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsSnapshot {
+    pub requests_processed: u64,
+    pub requests_successful: u64,
+    pub requests_failed: u64,
+    pub requests_in_flight: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    /// Same as `bytes_sent`, kept for existing API consumers
+    pub bytes_forwarded: u64,
+    pub uptime_seconds: u64,
+    pub websocket_reconnects: u64,
+    pub relay_errors: u64,
+    pub connection_status: String,
+    pub average_response_time_ms: f64,
+    pub latency: LatencySummary,
+}
+
+/// Latency percentiles in milliseconds
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// Per-route statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteSnapshot {
+    pub route: String,
+    pub requests: u64,
+    pub failed: u64,
+    pub average_response_time_ms: f64,
+    pub latency: LatencySummary,
+}
+
+impl Metrics {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            parent: None,
+            started: Instant::now(),
+            requests: std::array::from_fn(|_| std::array::from_fn(|_| AtomicU64::new(0))),
+            requests_successful: AtomicU64::new(0),
+            requests_failed: AtomicU64::new(0),
+            latency: Histogram::new(),
+            routes: RwLock::new(HashMap::new()),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            relay_errors: AtomicU64::new(0),
+            proxy_queue: AtomicI64::new(0),
+            websocket_queue: AtomicI64::new(0),
+            in_flight: AtomicI64::new(0),
+            connection_state: AtomicU8::new(2),
+        }
+    }
+
+    /// Registry for one tunnel; everything it records also goes to `parent`
+    pub fn for_tunnel(parent: Arc<Metrics>) -> Self {
+        Self {
+            parent: Some(parent),
+            ..Self::new()
+        }
+    }
+
+    /// Record a request arriving from the tunnel
+    pub fn request_started(&self, request_bytes: usize) {
+        if let Some(parent) = &self.parent {
+            parent.request_started(request_bytes);
+        }
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(request_bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Record the response sent back for a request
+    pub fn request_finished(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        duration: Duration,
+        response_bytes: usize,
+        outcome: Outcome,
+    ) {
+        if let Some(parent) = &self.parent {
+            parent.request_finished(method, path, status, duration, response_bytes, outcome);
+        }
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.requests[method_index(method)][status_class_index(status)]
+            .fetch_add(1, Ordering::Relaxed);
+        self.latency.observe(duration);
+        self.bytes_sent
+            .fetch_add(response_bytes as u64, Ordering::Relaxed);
+
+        let route = self.route(path);
+        route.requests.fetch_add(1, Ordering::Relaxed);
+        route.latency.observe(duration);
+
+        match outcome {
+            Outcome::Success => self.requests_successful.fetch_add(1, Ordering::Relaxed),
+            Outcome::Failure => {
+                route.failed.fetch_add(1, Ordering::Relaxed);
+                self.requests_failed.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+    }
+
+    /// Record a reconnection attempt to the relay
+    pub fn record_reconnect(&self) {
+        if let Some(parent) = &self.parent {
+            parent.record_reconnect();
+        }
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an error message received from the relay
+    pub fn record_relay_error(&self) {
+        if let Some(parent) = &self.parent {
+            parent.record_relay_error();
+        }
+        self.relay_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the current relay connection state; the parent only keeps the
+    /// latest change, so per-tunnel state is read from each tunnel registry
+    pub fn set_connection_state(&self, status: &ConnectionStatus) {
+        if let Some(parent) = &self.parent {
+            parent.set_connection_state(status);
+        }
+        let index = match status {
+            ConnectionStatus::Connecting => 0,
+            ConnectionStatus::Connected => 1,
+            ConnectionStatus::Disconnected => 2,
+            ConnectionStatus::Reconnecting => 3,
+            ConnectionStatus::Error(_) => 4,
+        };
+        self.connection_state.store(index, Ordering::Relaxed);
+    }
+
+    /// Record a message entering a queue
+    pub fn queue_push(&self, queue: Queue) {
+        self.queue(queue).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a message leaving a queue
+    pub fn queue_pop(&self, queue: Queue) {
+        self.queue(queue).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Current depth of a queue
+    pub fn queue_depth(&self, queue: Queue) -> i64 {
+        self.queue(queue).load(Ordering::Relaxed).max(0)
+    }
+
+    /// Requests currently being processed
+    pub fn in_flight(&self) -> i64 {
+        self.in_flight.load(Ordering::Relaxed).max(0)
+    }
+
+    /// Request count for a method and status class
+    pub fn requests(&self, method: &str, status_class: usize) -> u64 {
+        self.requests[method_index(method)][status_class].load(Ordering::Relaxed)
+    }
+
+    /// Total request body bytes received from the tunnel
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// Total response body bytes sent back through the tunnel
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    /// Total reconnection attempts
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Total error messages received from the relay
+    pub fn relay_errors(&self) -> u64 {
+        self.relay_errors.load(Ordering::Relaxed)
+    }
+
+    /// Current connection state label
+    pub fn connection_state(&self) -> &'static str {
+        CONNECTION_STATES[self.connection_state.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Request latency histogram
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    /// Time since the registry was created
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Take a snapshot of the global counters
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let successful = self.requests_successful.load(Ordering::Relaxed);
+        let failed = self.requests_failed.load(Ordering::Relaxed);
+        let bytes_sent = self.bytes_sent();
+
+        StatsSnapshot {
+            requests_processed: successful + failed,
+            requests_successful: successful,
+            requests_failed: failed,
+            requests_in_flight: self.in_flight() as u64,
+            bytes_received: self.bytes_received(),
+            bytes_sent,
+            bytes_forwarded: bytes_sent,
+            uptime_seconds: self.uptime().as_secs(),
+            websocket_reconnects: self.reconnects(),
+            relay_errors: self.relay_errors(),
+            connection_status: self.connection_state().to_string(),
+            average_response_time_ms: self.latency.average_ms(),
+            latency: self.latency.summary(),
+        }
+    }
+
+    /// Per-route statistics, busiest first
+    pub fn routes(&self) -> Vec<RouteSnapshot> {
+        let routes = self.routes.read().unwrap_or_else(|e| e.into_inner());
+        let mut snapshots: Vec<RouteSnapshot> = routes
+            .iter()
+            .map(|(route, metrics)| RouteSnapshot {
+                route: route.clone(),
+                requests: metrics.requests.load(Ordering::Relaxed),
+                failed: metrics.failed.load(Ordering::Relaxed),
+                average_response_time_ms: metrics.latency.average_ms(),
+                latency: metrics.latency.summary(),
+            })
+            .collect();
+
+        snapshots.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.route.cmp(&b.route)));
+        snapshots
+    }
+
+    /// Look up or create the metrics for a request path
+    fn route(&self, path: &str) -> Arc<RouteMetrics> {
+        let key = route_key(path);
+        if let Some(route) = self
+            .routes
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+        {
+            return route.clone();
+        }
+
+        let mut routes = self.routes.write().unwrap_or_else(|e| e.into_inner());
+        let key = if routes.len() >= MAX_ROUTES && !routes.contains_key(&key) {
+            OVERFLOW_ROUTE.to_string()
+        } else {
+            key
+        };
+        routes
+            .entry(key)
+            .or_insert_with(|| Arc::new(RouteMetrics::new()))
+            .clone()
+    }
+
+    fn queue(&self, queue: Queue) -> &AtomicI64 {
+        match queue {
+            Queue::Proxy => &self.proxy_queue,
+            Queue::Websocket => &self.websocket_queue,
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouteMetrics {
+    fn new() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            latency: Histogram::new(),
+        }
+    }
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Record one observation
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Cumulative bucket counts, one per entry in `LATENCY_BUCKETS`
+    pub fn cumulative_buckets(&self) -> Vec<u64> {
+        let mut total = 0;
+        self.buckets
+            .iter()
+            .map(|bucket| {
+                total += bucket.load(Ordering::Relaxed);
+                total
+            })
+            .collect()
+    }
+
+    /// Sum of all observations in seconds
+    pub fn sum_seconds(&self) -> f64 {
+        self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Mean observation in milliseconds
+    pub fn average_ms(&self) -> f64 {
+        match self.count() {
+            0 => 0.0,
+            count => self.sum_seconds() * 1000.0 / count as f64,
+        }
+    }
+
+    /// Estimate a quantile in seconds, interpolating inside the matching bucket
+    pub fn quantile(&self, q: f64) -> f64 {
+        let count = self.count();
+        if count == 0 {
+            return 0.0;
+        }
+
+        let rank = (q.clamp(0.0, 1.0) * count as f64).ceil().max(1.0);
+        let mut previous = 0u64;
+        for (index, cumulative) in self.cumulative_buckets().into_iter().enumerate() {
+            if cumulative as f64 >= rank {
+                let lower = if index == 0 {
+                    0.0
+                } else {
+                    LATENCY_BUCKETS[index - 1]
+                };
+                let upper = LATENCY_BUCKETS[index];
+                let in_bucket = (cumulative - previous) as f64;
+                let position = (rank - previous as f64) / in_bucket;
+                return lower + (upper - lower) * position;
+            }
+            previous = cumulative;
+        }
+
+        // Observation beyond the last bucket
+        LATENCY_BUCKETS[LATENCY_BUCKETS.len() - 1]
+    }
+
+    /// p50/p95/p99 in milliseconds
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            p50_ms: self.quantile(0.50) * 1000.0,
+            p95_ms: self.quantile(0.95) * 1000.0,
+            p99_ms: self.quantile(0.99) * 1000.0,
+        }
+    }
+}
+
+/// Normalize a request path into a route label
+///
+/// Query strings are dropped and ID-like segments (numbers, UUIDs, long hex
+/// strings) collapse to `:id` so each resource doesn't become its own route.
+pub fn route_key(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or(path);
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            if is_id_segment(segment) {
+                ":id"
+            } else {
+                segment
+            }
+        })
+        .collect();
+
+    format!("/{}", segments.join("/"))
+}
+
+fn is_id_segment(segment: &str) -> bool {
+    let all_digits = segment.chars().all(|c| c.is_ascii_digit());
+    let hex_like = segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit());
+    let uuid_like =
+        segment.len() == 36 && segment.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+
+    all_digits || hex_like || uuid_like
+}
+
+/// Index of a method label
+fn method_index(method: &str) -> usize {
+    METHODS
+        .iter()
+        .position(|m| m.eq_ignore_ascii_case(method))
+        .unwrap_or(METHODS.len() - 1)
+}
+
+/// Index of a status class label
+fn status_class_index(status: u16) -> usize {
+    (status / 100).clamp(1, 5) as usize - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_counters_and_histogram() {
+        let metrics = Metrics::new();
+        metrics.request_started(10);
+        metrics.request_started(5);
+        assert_eq!(metrics.in_flight(), 2);
+
+        metrics.request_finished(
+            "get",
+            "/users/42",
+            200,
+            Duration::from_millis(20),
+            100,
+            Outcome::Success,
+        );
+        metrics.request_finished(
+            "PROPFIND",
+            "/users/7?x=1",
+            502,
+            Duration::from_secs(90),
+            0,
+            Outcome::Failure,
+        );
+
+        assert_eq!(metrics.in_flight(), 0);
+        assert_eq!(metrics.requests("GET", 1), 1);
+        assert_eq!(metrics.requests("OTHER", 4), 1);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests_processed, 2);
+        assert_eq!(snapshot.requests_successful, 1);
+        assert_eq!(snapshot.requests_failed, 1);
+        assert_eq!(snapshot.bytes_received, 15);
+        assert_eq!(snapshot.bytes_sent, 100);
+
+        // 20ms lands in the 20ms bucket; 90s only counts toward +Inf
+        let buckets = metrics.latency().cumulative_buckets();
+        assert_eq!(buckets[6], 0);
+        assert_eq!(buckets[7], 1);
+        assert_eq!(*buckets.last().unwrap(), 1);
+        assert_eq!(metrics.latency().count(), 2);
+
+        let routes = metrics.routes();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].route, "/users/:id");
+        assert_eq!(routes[0].requests, 2);
+        assert_eq!(routes[0].failed, 1);
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let metrics = Metrics::new();
+        for ms in 1..=100 {
+            metrics.request_started(0);
+            metrics.request_finished(
+                "GET",
+                "/api",
+                200,
+                Duration::from_millis(ms),
+                0,
+                Outcome::Success,
+            );
+        }
+
+        let latency = metrics.snapshot().latency;
+        assert!((40.0..=60.0).contains(&latency.p50_ms), "{latency:?}");
+        assert!((90.0..=100.0).contains(&latency.p95_ms), "{latency:?}");
+        assert!((95.0..=100.0).contains(&latency.p99_ms), "{latency:?}");
+        assert!(latency.p50_ms <= latency.p95_ms && latency.p95_ms <= latency.p99_ms);
+    }
+
+    #[test]
+    fn test_route_keys() {
+        assert_eq!(route_key("/"), "/");
+        assert_eq!(
+            route_key("/api/users/123/posts?page=2"),
+            "/api/users/:id/posts"
+        );
+        assert_eq!(
+            route_key("/orders/550e8400-e29b-41d4-a716-446655440000"),
+            "/orders/:id"
+        );
+        assert_eq!(route_key("/assets/app.js"), "/assets/app.js");
+    }
+
+    #[test]
+    fn test_queue_and_connection_state() {
+        let metrics = Metrics::new();
+        assert_eq!(metrics.connection_state(), "disconnected");
+
+        metrics.set_connection_state(&ConnectionStatus::Connected);
+        metrics.queue_push(Queue::Proxy);
+        metrics.queue_push(Queue::Proxy);
+        metrics.queue_pop(Queue::Proxy);
+
+        assert_eq!(metrics.connection_state(), "connected");
+        assert_eq!(metrics.queue_depth(Queue::Proxy), 1);
+        assert_eq!(metrics.queue_depth(Queue::Websocket), 0);
+    }
+
+    #[test]
+    fn test_tunnel_registries_feed_parent() {
+        let process = Arc::new(Metrics::new());
+        let api = Metrics::for_tunnel(process.clone());
+        let web = Metrics::for_tunnel(process.clone());
+
+        api.set_connection_state(&ConnectionStatus::Connected);
+        web.set_connection_state(&ConnectionStatus::Reconnecting);
+        api.request_started(10);
+        web.request_started(20);
+        web.request_finished(
+            "GET",
+            "/",
+            200,
+            Duration::from_millis(5),
+            0,
+            Outcome::Success,
+        );
+
+        assert_eq!(api.connection_state(), "connected");
+        assert_eq!(web.connection_state(), "reconnecting");
+        assert_eq!(api.in_flight(), 1);
+        assert_eq!(web.in_flight(), 0);
+        assert!(api.routes().is_empty());
+        assert_eq!(web.routes().len(), 1);
+        assert_eq!(process.in_flight(), 1);
+        assert_eq!(process.bytes_received(), 30);
+        assert_eq!(process.snapshot().requests_processed, 1);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Write;
+
+use super::{Metrics, Queue, CONNECTION_STATES, LATENCY_BUCKETS, METHODS, STATUS_CLASSES};
+
+/// Content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Render the registry in the Prometheus text exposition format; the
+/// connection state is exported per tunnel from each tunnel's registry
+pub fn render(metrics: &Metrics, tunnels: &[(String, &Metrics)]) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "pori_requests_total",
+        "counter",
+        "Tunneled requests by method and status class",
+    );
+    for method in METHODS {
+        for (class_index, class) in STATUS_CLASSES.iter().enumerate() {
+            let count = metrics.requests(method, class_index);
+            if count > 0 {
+                let _ = writeln!(
+                    out,
+                    "pori_requests_total{{method=\"{method}\",status_class=\"{class}\"}} {count}"
+                );
+            }
+        }
+    }
+
+    header(
+        &mut out,
+        "pori_request_duration_seconds",
+        "histogram",
+        "Time from receiving a tunneled request to sending its response",
+    );
+    let latency = metrics.latency();
+    for (bound, count) in LATENCY_BUCKETS.iter().zip(latency.cumulative_buckets()) {
+        let _ = writeln!(
+            out,
+            "pori_request_duration_seconds_bucket{{le=\"{bound}\"}} {count}"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "pori_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+        latency.count()
+    );
+    let _ = writeln!(
+        out,
+        "pori_request_duration_seconds_sum {}",
+        latency.sum_seconds()
+    );
+    let _ = writeln!(
+        out,
+        "pori_request_duration_seconds_count {}",
+        latency.count()
+    );
+
+    header(
+        &mut out,
+        "pori_route_duration_seconds",
+        "summary",
+        "Request latency quantiles per normalized route",
+    );
+    for route in metrics.routes() {
+        let label = escape_label(&route.route);
+        for (quantile, ms) in [
+            ("0.5", route.latency.p50_ms),
+            ("0.95", route.latency.p95_ms),
+            ("0.99", route.latency.p99_ms),
+        ] {
+            let _ = writeln!(
+                out,
+                "pori_route_duration_seconds{{route=\"{label}\",quantile=\"{quantile}\"}} {}",
+                ms / 1000.0
+            );
+        }
+        let _ = writeln!(
+            out,
+            "pori_route_duration_seconds_sum{{route=\"{label}\"}} {}",
+            route.average_response_time_ms * route.requests as f64 / 1000.0
+        );
+        let _ = writeln!(
+            out,
+            "pori_route_duration_seconds_count{{route=\"{label}\"}} {}",
+            route.requests
+        );
+    }
+
+    simple(
+        &mut out,
+        "pori_bytes_received_total",
+        "counter",
+        "Request body bytes received from the relay",
+        metrics.bytes_received(),
+    );
+    simple(
+        &mut out,
+        "pori_bytes_sent_total",
+        "counter",
+        "Response body bytes sent back to the relay",
+        metrics.bytes_sent(),
+    );
+    simple(
+        &mut out,
+        "pori_websocket_reconnects_total",
+        "counter",
+        "Reconnection attempts to the relay",
+        metrics.reconnects(),
+    );
+    simple(
+        &mut out,
+        "pori_relay_errors_total",
+        "counter",
+        "Error messages received from the relay",
+        metrics.relay_errors(),
+    );
+
+    header(
+        &mut out,
+        "pori_queue_depth",
+        "gauge",
+        "Messages waiting in internal queues",
+    );
+    for (queue, label) in [(Queue::Proxy, "proxy"), (Queue::Websocket, "websocket")] {
+        let _ = writeln!(
+            out,
+            "pori_queue_depth{{queue=\"{label}\"}} {}",
+            metrics.queue_depth(queue)
+        );
+    }
+
+    simple(
+        &mut out,
+        "pori_requests_in_flight",
+        "gauge",
+        "Requests currently being processed",
+        metrics.in_flight(),
+    );
+
+    header(
+        &mut out,
+        "pori_connection_state",
+        "gauge",
+        "Relay connection state per tunnel (1 for the current state)",
+    );
+    for (name, tunnel) in tunnels {
+        let name = escape_label(name);
+        let current = tunnel.connection_state();
+        for state in CONNECTION_STATES {
+            let _ = writeln!(
+                out,
+                "pori_connection_state{{tunnel=\"{name}\",state=\"{state}\"}} {}",
+                u8::from(state == current)
+            );
+        }
+    }
+
+    simple(
+        &mut out,
+        "pori_uptime_seconds",
+        "gauge",
+        "Seconds since the client started",
+        metrics.uptime().as_secs(),
+    );
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Escape a label value for the exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn simple(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    header(out, name, kind, help);
+    let _ = writeln!(out, "{name} {value}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Outcome;
+    use crate::ConnectionStatus;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_exposition() {
+        let metrics = Arc::new(Metrics::new());
+        let api = Metrics::for_tunnel(metrics.clone());
+        let web = Metrics::for_tunnel(metrics.clone());
+        api.set_connection_state(&ConnectionStatus::Connected);
+        metrics.request_started(0);
+        metrics.request_finished(
+            "POST",
+            "/hooks/1",
+            201,
+            Duration::from_millis(3),
+            12,
+            Outcome::Success,
+        );
+
+        let text = render(
+            &metrics,
+            &[("api".to_string(), &api), ("web".to_string(), &web)],
+        );
+        assert!(text.contains("pori_requests_total{method=\"POST\",status_class=\"2xx\"} 1"));
+        assert!(text.contains("pori_request_duration_seconds_bucket{le=\"0.005\"} 1"));
+        assert!(text.contains("pori_request_duration_seconds_bucket{le=\"+Inf\"} 1"));
+        assert!(text.contains("pori_bytes_sent_total 12"));
+        assert!(text.contains("pori_connection_state{tunnel=\"api\",state=\"connected\"} 1"));
+        assert!(text.contains("pori_connection_state{tunnel=\"web\",state=\"connected\"} 0"));
+        assert!(text.contains("pori_connection_state{tunnel=\"web\",state=\"disconnected\"} 1"));
+        assert!(text.contains("# TYPE pori_request_duration_seconds histogram"));
+        assert!(text.contains("pori_route_duration_seconds_count{route=\"/hooks/:id\"} 1"));
+    }
+}
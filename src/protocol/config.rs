@@ -31,7 +31,8 @@ pub struct SecurityConfig {
 }
 
 /// Rate limiting configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RateLimitConfig {
     pub enabled: bool,
     pub requests_per_minute: u32,
@@ -65,7 +66,8 @@ pub struct LimitConfig {
 }
 
 /// Feature toggles for protocol capabilities
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FeatureConfig {
     pub streaming_enabled: bool,
     pub websocket_compression: bool,
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use super::messages::{HttpPayload, MessagePayload, ProtocolMessage};
+use crate::utils::http::header_value;
 use crate::utils::http::status::get_status_text;
 
 /// HTTP-specific message wrapper for local server communication
@@ -166,19 +167,21 @@ pub struct HttpTlsConfig {
     pub protocols: Vec<String>,
 }
 
-/// HTTP proxy configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// HTTP proxy configuration, also used for the relay connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct HttpProxyConfig {
-    /// Proxy URL
+    /// `http://`, `socks5://` or `socks5h://` proxy URL, with optional `user:password@`;
+    /// when unset, `HTTPS_PROXY`/`HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` are used
     pub url: Option<String>,
-    /// Authentication
+    /// Authentication, instead of credentials in the URL
     pub auth: Option<ProxyAuth>,
-    /// Bypass list
+    /// Hosts, domain suffixes or IP ranges reached directly
     pub bypass: Vec<String>,
 }
 
 /// Proxy authentication
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProxyAuth {
     /// Authentication type
     pub auth_type: String,
@@ -427,6 +430,55 @@ impl HttpMessage {
     }
 }
 
+impl ProxyInfo {
+    /// Build proxy information from the headers the relay attached to a request
+    ///
+    /// The client is the `X-Forwarded-For` entry `trusted_hops` from the right:
+    /// the relay appends the peer it saw, and anything left of that is supplied
+    /// by the requester. `X-Real-IP` is ignored for the same reason.
+    pub fn from_headers(headers: &HashMap<String, String>, trusted_hops: usize) -> Self {
+        let header = |name: &str| {
+            header_value(headers, name)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let list = |name: &str| {
+            header(name)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|part| part.trim().to_string())
+                        .filter(|part| !part.is_empty())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let forwarded_for = list("x-forwarded-for");
+        let client_ip = forwarded_for
+            .iter()
+            .rev()
+            .nth(trusted_hops.saturating_sub(1))
+            .or(forwarded_for.first())
+            .cloned()
+            .unwrap_or_else(|| "unknown".to_string());
+
+        Self {
+            proxy_id: "pori-proxy".to_string(),
+            client_ip,
+            user_agent: header("user-agent"),
+            forwarded_for,
+            via: list("via"),
+        }
+    }
+
+    /// Parse the client IP address, if known
+    pub fn client_addr(&self) -> Option<std::net::IpAddr> {
+        self.client_ip.parse().ok()
+    }
+}
+
 impl Default for HttpTimeouts {
     fn default() -> Self {
         Self {
@@ -546,4 +598,33 @@ mod tests {
         assert_eq!(url, "/api/data");
         assert_eq!(extracted_headers, headers);
     }
+
+    #[test]
+    fn test_proxy_info_from_headers() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "X-Forwarded-For".to_string(),
+            "10.0.0.1, 203.0.113.7".to_string(),
+        );
+        headers.insert("user-agent".to_string(), "curl/8.0".to_string());
+
+        let info = ProxyInfo::from_headers(&headers, 1);
+        assert_eq!(info.client_ip, "203.0.113.7");
+        assert_eq!(info.forwarded_for, vec!["10.0.0.1", "203.0.113.7"]);
+        assert_eq!(info.user_agent.as_deref(), Some("curl/8.0"));
+        assert!(info.client_addr().is_some());
+
+        // The requester controls X-Real-IP and everything left of the relay's entry
+        headers.insert("x-real-ip".to_string(), "198.51.100.2".to_string());
+        assert_eq!(
+            ProxyInfo::from_headers(&headers, 1).client_ip,
+            "203.0.113.7"
+        );
+        assert_eq!(ProxyInfo::from_headers(&headers, 2).client_ip, "10.0.0.1");
+        assert_eq!(ProxyInfo::from_headers(&headers, 5).client_ip, "10.0.0.1");
+
+        assert!(ProxyInfo::from_headers(&HashMap::new(), 1)
+            .client_addr()
+            .is_none());
+    }
 }
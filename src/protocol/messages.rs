@@ -98,6 +98,9 @@ pub enum AuthPayload {
         session_id: String,
         expires_at: Option<u64>,
         permissions: Vec<String>,
+        /// Public URL assigned to the tunnel
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        public_url: Option<String>,
     },
     /// Authentication failure
     Failure {
@@ -151,6 +154,9 @@ pub enum ControlPayload {
         status: String,
         message: String,
         timestamp: String,
+        /// Public URL assigned to the tunnel
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        public_url: Option<String>,
     },
     /// Error message
     Error {
@@ -185,6 +191,13 @@ pub enum ControlPayload {
         arguments: Vec<String>,
         environment: HashMap<String, String>,
     },
+    /// Result of a command, correlated with the command message
+    CommandResult {
+        command: String,
+        success: bool,
+        output: serde_json::Value,
+        error: Option<String>,
+    },
     /// Shutdown notification
     Shutdown {
         reason: String,
@@ -637,7 +650,7 @@ mod body_serializer {
                             _ => Err(D::Error::custom("Invalid byte array format")),
                         })
                         .collect();
-                    Ok(Some(bytes.map_err(|e| D::Error::custom(e))?))
+                    Ok(Some(bytes.map_err(D::Error::custom)?))
                 }
                 // Handle null values
                 Value::Null => Ok(None),
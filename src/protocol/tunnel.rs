@@ -169,33 +169,31 @@ pub struct TunnelSecurityConfig {
     pub rate_limiting: RateLimitConfig,
 }
 
-/// TLS configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// TLS options for the relay connection
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TlsConfig {
-    /// Enable TLS
-    pub enabled: bool,
-    /// Certificate file path
+    /// Client certificate chain (PEM) presented to the relay
     pub cert_file: Option<String>,
-    /// Private key file path
+    /// Private key (PEM) for the client certificate
     pub key_file: Option<String>,
-    /// CA certificate file path
-    pub ca_file: Option<String>,
-    /// Verify peer certificate
-    pub verify_peer: bool,
-    /// Supported protocols
-    pub protocols: Vec<String>,
-    /// Cipher suites
-    pub cipher_suites: Vec<String>,
+    /// Extra CA bundles (PEM) trusted in addition to the system roots
+    pub ca_files: Vec<String>,
+    /// Accepted base64 SHA-256 hashes of the relay's public key (SPKI)
+    pub pin_sha256: Vec<String>,
+    /// Skip certificate chain and hostname checks; for local testing only
+    pub insecure: bool,
 }
 
 /// Access control configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AccessControlConfig {
-    /// Allowed origins
+    /// Allowed origins (`*` allows any, `https://*.example.com` matches subdomains)
     pub allowed_origins: Vec<String>,
-    /// Allowed IP addresses
+    /// Allowed IP addresses or CIDR ranges (empty allows any)
     pub allowed_ips: Vec<String>,
-    /// Blocked IP addresses
+    /// Blocked IP addresses or CIDR ranges
     pub blocked_ips: Vec<String>,
     /// Authentication required
     pub auth_required: bool,
@@ -457,20 +455,6 @@ impl Default for WebSocketConfig {
     }
 }
 
-impl Default for TlsConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            cert_file: None,
-            key_file: None,
-            ca_file: None,
-            verify_peer: false,
-            protocols: vec!["TLSv1.2".to_string(), "TLSv1.3".to_string()],
-            cipher_suites: Vec::new(),
-        }
-    }
-}
-
 impl Default for AccessControlConfig {
     fn default() -> Self {
         Self {
@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::protocol::tunnel::AccessControlConfig;
+
+/// Compiled access control rules for tunneled traffic
+#[derive(Debug, Clone, Default)]
+pub struct AccessControl {
+    allowed_networks: Vec<IpNet>,
+    blocked_networks: Vec<IpNet>,
+    allowed_origins: Vec<String>,
+    allow_any_origin: bool,
+}
+
+/// Reason a request was rejected by access control
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessDenied {
+    /// Client address matched a blocked network
+    BlockedIp(IpAddr),
+    /// Client address did not match any allowed network
+    IpNotAllowed(IpAddr),
+    /// Client address is unknown while an allow list is configured
+    UnknownClientIp,
+    /// Origin header is not in the allowed origins list
+    OriginNotAllowed(String),
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessDenied::BlockedIp(ip) => write!(f, "client IP {ip} is blocked"),
+            AccessDenied::IpNotAllowed(ip) => write!(f, "client IP {ip} is not allowed"),
+            AccessDenied::UnknownClientIp => write!(f, "client IP could not be determined"),
+            AccessDenied::OriginNotAllowed(origin) => write!(f, "origin {origin} is not allowed"),
+        }
+    }
+}
+
+impl AccessControl {
+    /// Compile access control rules from configuration
+    pub fn from_config(config: &AccessControlConfig) -> Result<Self> {
+        let allowed_networks = parse_networks(&config.allowed_ips)
+            .context("Invalid entry in access_control.allowed_ips")?;
+        let blocked_networks = parse_networks(&config.blocked_ips)
+            .context("Invalid entry in access_control.blocked_ips")?;
+
+        let allow_any_origin =
+            config.allowed_origins.is_empty() || config.allowed_origins.iter().any(|o| o == "*");
+
+        Ok(Self {
+            allowed_networks,
+            blocked_networks,
+            allowed_origins: config
+                .allowed_origins
+                .iter()
+                .map(|o| o.trim_end_matches('/').to_lowercase())
+                .collect(),
+            allow_any_origin,
+        })
+    }
+
+    /// Check whether any rule is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_networks.is_empty()
+            || !self.blocked_networks.is_empty()
+            || !self.allow_any_origin
+    }
+
+    /// Check a request against the configured rules
+    pub fn check(
+        &self,
+        client_ip: Option<IpAddr>,
+        headers: &HashMap<String, String>,
+    ) -> std::result::Result<(), AccessDenied> {
+        self.check_ip(client_ip)?;
+
+        let origin = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("origin"))
+            .map(|(_, value)| value.as_str());
+        self.check_origin(origin)
+    }
+
+    /// Check the client address against the deny and allow lists
+    pub fn check_ip(&self, client_ip: Option<IpAddr>) -> std::result::Result<(), AccessDenied> {
+        if self.allowed_networks.is_empty() && self.blocked_networks.is_empty() {
+            return Ok(());
+        }
+
+        let Some(ip) = client_ip.map(normalize_ip) else {
+            // Fail closed when an allow list exists but we can't tell who is calling
+            return if self.allowed_networks.is_empty() {
+                Ok(())
+            } else {
+                Err(AccessDenied::UnknownClientIp)
+            };
+        };
+
+        // Deny rules always win over allow rules
+        if self.blocked_networks.iter().any(|net| net.contains(&ip)) {
+            return Err(AccessDenied::BlockedIp(ip));
+        }
+
+        if !self.allowed_networks.is_empty()
+            && !self.allowed_networks.iter().any(|net| net.contains(&ip))
+        {
+            return Err(AccessDenied::IpNotAllowed(ip));
+        }
+
+        Ok(())
+    }
+
+    /// Check the Origin header against the allowed origins list
+    ///
+    /// Requests without an Origin header (non-browser clients, webhooks) are allowed.
+    pub fn check_origin(&self, origin: Option<&str>) -> std::result::Result<(), AccessDenied> {
+        let Some(origin) = origin else {
+            return Ok(());
+        };
+
+        if self.allow_any_origin {
+            return Ok(());
+        }
+
+        let normalized = origin.trim_end_matches('/').to_lowercase();
+        if self
+            .allowed_origins
+            .iter()
+            .any(|pattern| origin_matches(pattern, &normalized))
+        {
+            Ok(())
+        } else {
+            Err(AccessDenied::OriginNotAllowed(origin.to_string()))
+        }
+    }
+}
+
+/// Parse a list of IP addresses or CIDR ranges
+fn parse_networks(entries: &[String]) -> Result<Vec<IpNet>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = entry.trim();
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .with_context(|| format!("'{entry}' is not an IP address or CIDR range"))
+        })
+        .collect()
+}
+
+/// Map IPv4-mapped IPv6 addresses back to IPv4 so they match IPv4 ranges
+fn normalize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(v6)),
+        v4 => v4,
+    }
+}
+
+/// Match an origin against a pattern, supporting `scheme://*.domain` wildcards
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    if pattern == origin {
+        return true;
+    }
+
+    if let Some((scheme, host_pattern)) = pattern.split_once("://") {
+        if let Some(suffix) = host_pattern.strip_prefix("*.") {
+            if let Some(host) = origin
+                .strip_prefix(scheme)
+                .and_then(|o| o.strip_prefix("://"))
+            {
+                return host.ends_with(&format!(".{suffix}"));
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(allowed: &[&str], blocked: &[&str], origins: &[&str]) -> AccessControlConfig {
+        AccessControlConfig {
+            allowed_ips: allowed.iter().map(|s| s.to_string()).collect(),
+            blocked_ips: blocked.iter().map(|s| s.to_string()).collect(),
+            allowed_origins: origins.iter().map(|s| s.to_string()).collect(),
+            ..AccessControlConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_cidr_allow_and_deny() {
+        let access = AccessControl::from_config(&config(
+            &["10.0.0.0/8", "192.168.1.10"],
+            &["10.1.0.0/16"],
+            &["*"],
+        ))
+        .unwrap();
+
+        assert!(access.check_ip(Some("10.2.3.4".parse().unwrap())).is_ok());
+        assert!(access
+            .check_ip(Some("192.168.1.10".parse().unwrap()))
+            .is_ok());
+        assert_eq!(
+            access.check_ip(Some("10.1.2.3".parse().unwrap())),
+            Err(AccessDenied::BlockedIp("10.1.2.3".parse().unwrap()))
+        );
+        assert_eq!(
+            access.check_ip(Some("8.8.8.8".parse().unwrap())),
+            Err(AccessDenied::IpNotAllowed("8.8.8.8".parse().unwrap()))
+        );
+        assert_eq!(access.check_ip(None), Err(AccessDenied::UnknownClientIp));
+
+        // IPv4-mapped IPv6 addresses match IPv4 ranges
+        assert!(access
+            .check_ip(Some("::ffff:10.2.3.4".parse().unwrap()))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_origin_checks() {
+        let access = AccessControl::from_config(&config(
+            &[],
+            &[],
+            &["https://admin.example.com", "https://*.office.example.com"],
+        ))
+        .unwrap();
+
+        assert!(access.check_origin(None).is_ok());
+        assert!(access
+            .check_origin(Some("https://admin.example.com"))
+            .is_ok());
+        assert!(access
+            .check_origin(Some("https://ui.office.example.com"))
+            .is_ok());
+        assert!(access.check_origin(Some("https://evil.com")).is_err());
+        assert!(access
+            .check_origin(Some("http://ui.office.example.com"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_network_rejected() {
+        assert!(AccessControl::from_config(&config(&["not-an-ip"], &[], &[])).is_err());
+    }
+}
@@ -0,0 +1,711 @@
+use anyhow::{Context, Result};
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+use super::client::LocalServerResponse;
+use super::path_matches;
+use crate::config::settings::{AuthConfig, AuthMode};
+use crate::utils::http::{header_value, remove_header};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Longest accepted session lifetime (one year)
+const MAX_SESSION_TTL: i64 = 365 * 24 * 60 * 60;
+
+/// Authentication gate applied before requests reach the local server
+#[derive(Clone)]
+pub struct AuthGate {
+    routes: Vec<AuthRoute>,
+    users: Arc<HashMap<String, String>>,
+    tokens: Arc<Vec<String>>,
+    cookie: Option<CookieSigner>,
+    login_path: String,
+}
+
+/// Compiled route rule
+#[derive(Debug, Clone)]
+struct AuthRoute {
+    path: String,
+    methods: Vec<String>,
+    mode: AuthMode,
+    realm: String,
+}
+
+/// Signs and verifies session cookies
+#[derive(Clone)]
+struct CookieSigner {
+    secret: Vec<u8>,
+    name: String,
+    ttl: i64,
+}
+
+/// Outcome of running a request through the gate
+#[derive(Debug)]
+pub enum AuthDecision {
+    /// Request may be forwarded; credentials consumed by the gate have been removed
+    Allow { user: Option<String> },
+    /// Request was rejected and must be answered with this response
+    Reject {
+        reason: String,
+        response: LocalServerResponse,
+    },
+    /// Request was handled by the gate itself (login/logout)
+    Respond(LocalServerResponse),
+}
+
+impl AuthGate {
+    /// Compile the gate from configuration
+    pub fn from_config(config: &AuthConfig) -> Result<Self> {
+        for (user, hash) in &config.users {
+            if !is_bcrypt_hash(hash) {
+                PasswordHash::new(hash).map_err(|e| {
+                    anyhow::anyhow!("Invalid password hash for auth user '{user}': {e}")
+                })?;
+            }
+        }
+
+        let mut routes = Vec::with_capacity(config.routes.len());
+        for route in &config.routes {
+            if !route.path.starts_with('/') {
+                anyhow::bail!("Auth route path must start with '/': {}", route.path);
+            }
+            match route.mode {
+                AuthMode::Basic | AuthMode::Cookie if config.users.is_empty() => {
+                    anyhow::bail!(
+                        "Auth route {} uses {:?} mode but no users are configured",
+                        route.path,
+                        route.mode
+                    );
+                }
+                AuthMode::Bearer if config.tokens.is_empty() => {
+                    anyhow::bail!(
+                        "Auth route {} uses bearer mode but no tokens are configured",
+                        route.path
+                    );
+                }
+                AuthMode::Cookie if config.cookie_secret.is_none() => {
+                    anyhow::bail!(
+                        "Auth route {} uses cookie mode but auth.cookie_secret is not set",
+                        route.path
+                    );
+                }
+                _ => {}
+            }
+
+            routes.push(AuthRoute {
+                path: route.path.clone(),
+                methods: route.methods.iter().map(|m| m.to_uppercase()).collect(),
+                mode: route.mode,
+                realm: route.realm.clone().unwrap_or_else(|| "Pori".to_string()),
+            });
+        }
+
+        let cookie = config
+            .cookie_secret
+            .as_ref()
+            .map(|secret| {
+                if secret.len() < 16 {
+                    anyhow::bail!("auth.cookie_secret must be at least 16 characters");
+                }
+                let ttl = i64::try_from(config.session_ttl)
+                    .ok()
+                    .filter(|ttl| (1..=MAX_SESSION_TTL).contains(ttl))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "auth.session_ttl must be between 1 and {MAX_SESSION_TTL} seconds"
+                        )
+                    })?;
+                Ok(CookieSigner {
+                    secret: secret.as_bytes().to_vec(),
+                    name: config.cookie_name.clone(),
+                    ttl,
+                })
+            })
+            .transpose()
+            .context("Invalid cookie configuration")?;
+
+        Ok(Self {
+            routes,
+            users: Arc::new(config.users.clone()),
+            tokens: Arc::new(config.tokens.clone()),
+            cookie,
+            login_path: config.login_path.clone(),
+        })
+    }
+
+    /// Check whether any route requires authentication
+    pub fn is_enabled(&self) -> bool {
+        self.routes.iter().any(|r| r.mode != AuthMode::None)
+    }
+
+    /// Evaluate a request against the configured routes
+    pub async fn evaluate(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &mut HashMap<String, String>,
+        body: Option<&[u8]>,
+    ) -> AuthDecision {
+        let route_path = path.split('?').next().unwrap_or(path);
+
+        // The login page belongs to the gate itself
+        if let Some(cookie) = &self.cookie {
+            if route_path == self.login_path {
+                return self.handle_login(cookie, method, path, body).await;
+            }
+        }
+
+        let Some(route) = self.match_route(method, route_path) else {
+            return AuthDecision::Allow { user: None };
+        };
+
+        match route.mode {
+            AuthMode::None => AuthDecision::Allow { user: None },
+            AuthMode::Basic => match basic_credentials(headers) {
+                Some((user, password)) if self.verify_user(&user, &password).await => {
+                    remove_header(headers, "authorization");
+                    AuthDecision::Allow { user: Some(user) }
+                }
+                credentials => AuthDecision::Reject {
+                    reason: if credentials.is_some() {
+                        "invalid basic credentials".to_string()
+                    } else {
+                        "missing basic credentials".to_string()
+                    },
+                    response: unauthorized(
+                        format!("Basic realm=\"{}\", charset=\"UTF-8\"", route.realm),
+                        "Authentication required",
+                    ),
+                },
+            },
+            AuthMode::Bearer => match bearer_token(headers) {
+                Some(token) if self.verify_token(&token) => {
+                    remove_header(headers, "authorization");
+                    AuthDecision::Allow { user: None }
+                }
+                token => AuthDecision::Reject {
+                    reason: if token.is_some() {
+                        "invalid bearer token".to_string()
+                    } else {
+                        "missing bearer token".to_string()
+                    },
+                    response: unauthorized(
+                        if token.is_some() {
+                            format!("Bearer realm=\"{}\", error=\"invalid_token\"", route.realm)
+                        } else {
+                            format!("Bearer realm=\"{}\"", route.realm)
+                        },
+                        "Authentication required",
+                    ),
+                },
+            },
+            AuthMode::Cookie => {
+                let Some(cookie) = &self.cookie else {
+                    return AuthDecision::Allow { user: None };
+                };
+                match cookie_value(headers, &cookie.name).and_then(|v| cookie.verify(&v)) {
+                    Some(user) if self.users.contains_key(&user) => {
+                        AuthDecision::Allow { user: Some(user) }
+                    }
+                    _ => AuthDecision::Reject {
+                        reason: "missing or invalid session cookie".to_string(),
+                        response: self.login_page(&route.realm, path, None, 401),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Find the first route matching the request
+    fn match_route(&self, method: &str, path: &str) -> Option<&AuthRoute> {
+        self.routes.iter().find(|route| {
+            path_matches(&route.path, path)
+                && (route.methods.is_empty()
+                    || route.methods.iter().any(|m| m.eq_ignore_ascii_case(method)))
+        })
+    }
+
+    /// Verify a username and password against the configured hash
+    async fn verify_user(&self, user: &str, password: &str) -> bool {
+        let Some(hash) = self.users.get(user).cloned() else {
+            return false;
+        };
+        let password = password.to_string();
+
+        // Password hashing is deliberately slow, keep it off the async workers
+        tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Verify a bearer token in constant time
+    fn verify_token(&self, token: &str) -> bool {
+        self.tokens.iter().fold(false, |found, t| {
+            found | bool::from(t.as_bytes().ct_eq(token.as_bytes()))
+        })
+    }
+
+    /// Handle the login page (GET renders the form, POST issues a session cookie)
+    async fn handle_login(
+        &self,
+        cookie: &CookieSigner,
+        method: &str,
+        path: &str,
+        body: Option<&[u8]>,
+    ) -> AuthDecision {
+        let next = query_param(path, "next").unwrap_or_else(|| "/".to_string());
+
+        if !method.eq_ignore_ascii_case("POST") {
+            return AuthDecision::Respond(self.login_page("Pori", &next, None, 200));
+        }
+
+        let form: HashMap<String, String> = url::form_urlencoded::parse(body.unwrap_or_default())
+            .into_owned()
+            .collect();
+        let user = form.get("username").cloned().unwrap_or_default();
+        let password = form.get("password").cloned().unwrap_or_default();
+        let next = form.get("next").cloned().unwrap_or(next);
+
+        if !self.verify_user(&user, &password).await {
+            return AuthDecision::Reject {
+                reason: format!("failed login for user '{user}'"),
+                response: self.login_page("Pori", &next, Some("Invalid username or password"), 401),
+            };
+        }
+
+        let Some(session) = cookie.sign(&user) else {
+            return AuthDecision::Respond(self.login_page(
+                "Pori",
+                &next,
+                Some("Could not start a session"),
+                500,
+            ));
+        };
+
+        let mut headers = HashMap::new();
+        headers.insert("location".to_string(), safe_redirect(&next));
+        headers.insert(
+            "set-cookie".to_string(),
+            format!(
+                "{}={}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+                cookie.name, session, cookie.ttl
+            ),
+        );
+        headers.insert("cache-control".to_string(), "no-store".to_string());
+
+        AuthDecision::Respond(LocalServerResponse {
+            status: 303,
+            status_text: "See Other".to_string(),
+            headers,
+            body: None,
+        })
+    }
+
+    /// Render the login page
+    fn login_page(
+        &self,
+        realm: &str,
+        next: &str,
+        error: Option<&str>,
+        status: u16,
+    ) -> LocalServerResponse {
+        let error_html = error
+            .map(|e| format!(r#"<p class="error">{}</p>"#, html_escape(e)))
+            .unwrap_or_default();
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Sign in - {realm}</title>
+    <style>
+        body {{ font-family: Arial, sans-serif; margin: 40px; }}
+        form {{ max-width: 320px; }}
+        input {{ display: block; width: 100%; margin: 8px 0; padding: 6px; }}
+        .error {{ color: #d32f2f; }}
+    </style>
+</head>
+<body>
+    <h1>Sign in</h1>
+    {error_html}
+    <form method="post" action="{action}">
+        <input type="hidden" name="next" value="{next}">
+        <input name="username" placeholder="Username" autocomplete="username" required>
+        <input name="password" type="password" placeholder="Password" autocomplete="current-password" required>
+        <button type="submit">Sign in</button>
+    </form>
+</body>
+</html>"#,
+            realm = html_escape(realm),
+            action = html_escape(&self.login_path),
+            next = html_escape(next),
+        );
+
+        let mut headers = HashMap::new();
+        headers.insert(
+            "content-type".to_string(),
+            "text/html; charset=utf-8".to_string(),
+        );
+        headers.insert("cache-control".to_string(), "no-store".to_string());
+        if status == 401 {
+            headers.insert(
+                "www-authenticate".to_string(),
+                format!(
+                    "Cookie realm=\"{realm}\", form-action=\"{}\"",
+                    self.login_path
+                ),
+            );
+        }
+
+        LocalServerResponse {
+            status,
+            status_text: crate::utils::http::status::get_status_text(status),
+            headers,
+            body: Some(html.into_bytes()),
+        }
+    }
+}
+
+impl CookieSigner {
+    /// Create a signed cookie value: `user.expires.signature`
+    fn sign(&self, user: &str) -> Option<String> {
+        let expires = chrono::Utc::now().timestamp().checked_add(self.ttl)?;
+        let payload = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(user),
+            expires
+        );
+        let signature = general_purpose::URL_SAFE_NO_PAD.encode(self.mac(&payload));
+        Some(format!("{payload}.{signature}"))
+    }
+
+    /// Verify a cookie value, returning the user name if valid and not expired
+    fn verify(&self, value: &str) -> Option<String> {
+        let (payload, signature) = value.rsplit_once('.')?;
+        let signature = general_purpose::URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        let mut mac = HmacSha256::new_from_slice(&self.secret).ok()?;
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+
+        let (user, expires) = payload.split_once('.')?;
+        if expires.parse::<i64>().ok()? < chrono::Utc::now().timestamp() {
+            return None;
+        }
+
+        String::from_utf8(general_purpose::URL_SAFE_NO_PAD.decode(user).ok()?).ok()
+    }
+
+    fn mac(&self, payload: &str) -> Vec<u8> {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+/// Check whether a hash uses the bcrypt format
+fn is_bcrypt_hash(hash: &str) -> bool {
+    hash.starts_with("$2a$") || hash.starts_with("$2b$") || hash.starts_with("$2y$")
+}
+
+/// Verify a password against a bcrypt or argon2 hash
+fn verify_password(password: &str, hash: &str) -> bool {
+    if is_bcrypt_hash(hash) {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    } else {
+        PasswordHash::new(hash)
+            .map(|parsed| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &parsed)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Extract HTTP Basic credentials
+fn basic_credentials(headers: &HashMap<String, String>) -> Option<(String, String)> {
+    let value = header_value(headers, "authorization")?;
+    let (scheme, encoded) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+/// Extract a bearer token
+fn bearer_token(headers: &HashMap<String, String>) -> Option<String> {
+    let value = header_value(headers, "authorization")?;
+    let (scheme, token) = value.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim().to_string())
+}
+
+/// Extract a cookie value by name
+fn cookie_value(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    header_value(headers, "cookie")?
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Extract a query parameter from a request path
+fn query_param(path: &str, name: &str) -> Option<String> {
+    let (_, query) = path.split_once('?')?;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Only allow same-site relative redirects after login
+fn safe_redirect(next: &str) -> String {
+    // Browsers read `/\evil.com` as protocol-relative and drop tabs and newlines
+    if next.starts_with('/')
+        && !next.starts_with("//")
+        && !next.chars().any(|c| c == '\\' || c.is_control())
+    {
+        next.to_string()
+    } else {
+        "/".to_string()
+    }
+}
+
+/// Build a 401 response with a challenge
+fn unauthorized(challenge: String, message: &str) -> LocalServerResponse {
+    let mut headers = HashMap::new();
+    headers.insert("www-authenticate".to_string(), challenge);
+    headers.insert(
+        "content-type".to_string(),
+        "text/plain; charset=utf-8".to_string(),
+    );
+
+    LocalServerResponse {
+        status: 401,
+        status_text: "Unauthorized".to_string(),
+        headers,
+        body: Some(message.as_bytes().to_vec()),
+    }
+}
+
+/// Minimal HTML escaping for values rendered into the login page
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::AuthRouteConfig;
+
+    fn gate() -> AuthGate {
+        let mut users = HashMap::new();
+        users.insert("alice".to_string(), bcrypt::hash("wonderland", 4).unwrap());
+
+        let config = AuthConfig {
+            users,
+            tokens: vec!["secret-token".to_string()],
+            cookie_secret: Some("0123456789abcdef0123".to_string()),
+            routes: vec![
+                AuthRouteConfig {
+                    path: "/admin/health".to_string(),
+                    methods: Vec::new(),
+                    mode: AuthMode::None,
+                    realm: None,
+                },
+                AuthRouteConfig {
+                    path: "/admin".to_string(),
+                    methods: Vec::new(),
+                    mode: AuthMode::Basic,
+                    realm: Some("Admin".to_string()),
+                },
+                AuthRouteConfig {
+                    path: "/api".to_string(),
+                    methods: vec!["post".to_string()],
+                    mode: AuthMode::Bearer,
+                    realm: None,
+                },
+                AuthRouteConfig {
+                    path: "/app".to_string(),
+                    methods: Vec::new(),
+                    mode: AuthMode::Cookie,
+                    realm: None,
+                },
+            ],
+            ..AuthConfig::default()
+        };
+        AuthGate::from_config(&config).unwrap()
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() {
+        let gate = gate();
+
+        let decision = gate
+            .evaluate("GET", "/admin/users", &mut HashMap::new(), None)
+            .await;
+        match decision {
+            AuthDecision::Reject { response, .. } => {
+                assert_eq!(response.status, 401);
+                assert!(response.headers["www-authenticate"].starts_with("Basic realm=\"Admin\""));
+            }
+            other => panic!("expected rejection, got {other:?}"),
+        }
+
+        let credentials = general_purpose::STANDARD.encode("alice:wonderland");
+        let mut ok_headers = headers(&[("Authorization", &format!("Basic {credentials}"))]);
+        let decision = gate
+            .evaluate("GET", "/admin/users", &mut ok_headers, None)
+            .await;
+        assert!(matches!(decision, AuthDecision::Allow { user: Some(ref u) } if u == "alice"));
+        assert!(ok_headers.is_empty(), "credentials should be stripped");
+
+        let bad = general_purpose::STANDARD.encode("alice:wrong");
+        let decision = gate
+            .evaluate(
+                "GET",
+                "/admin",
+                &mut headers(&[("authorization", &format!("Basic {bad}"))]),
+                None,
+            )
+            .await;
+        assert!(matches!(decision, AuthDecision::Reject { .. }));
+
+        // Exempted and unprotected paths pass through
+        let decision = gate
+            .evaluate("GET", "/admin/health", &mut HashMap::new(), None)
+            .await;
+        assert!(matches!(decision, AuthDecision::Allow { .. }));
+        let decision = gate
+            .evaluate("GET", "/administrator", &mut HashMap::new(), None)
+            .await;
+        assert!(matches!(decision, AuthDecision::Allow { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_bearer_auth_with_methods() {
+        let gate = gate();
+
+        let decision = gate
+            .evaluate("GET", "/api/items", &mut HashMap::new(), None)
+            .await;
+        assert!(matches!(decision, AuthDecision::Allow { .. }));
+
+        let decision = gate
+            .evaluate("POST", "/api/items", &mut HashMap::new(), None)
+            .await;
+        assert!(matches!(decision, AuthDecision::Reject { .. }));
+
+        let decision = gate
+            .evaluate(
+                "POST",
+                "/api/items",
+                &mut headers(&[("authorization", "Bearer secret-token")]),
+                None,
+            )
+            .await;
+        assert!(matches!(decision, AuthDecision::Allow { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_cookie_login_flow() {
+        let gate = gate();
+
+        let decision = gate
+            .evaluate("GET", "/app", &mut HashMap::new(), None)
+            .await;
+        let AuthDecision::Reject { response, .. } = decision else {
+            panic!("expected login page");
+        };
+        assert_eq!(response.status, 401);
+        assert!(response.headers.contains_key("www-authenticate"));
+
+        let form = b"username=alice&password=wonderland&next=%2Fapp%2Fhome";
+        let decision = gate
+            .evaluate("POST", "/.pori/login", &mut HashMap::new(), Some(form))
+            .await;
+        let AuthDecision::Respond(response) = decision else {
+            panic!("expected redirect after login");
+        };
+        assert_eq!(response.status, 303);
+        assert_eq!(response.headers["location"], "/app/home");
+
+        for next in ["%2F%5Cevil.com", "%2F%09%2Fevil.com", "%2F%2Fevil.com"] {
+            let form = format!("username=alice&password=wonderland&next={next}");
+            let decision = gate
+                .evaluate(
+                    "POST",
+                    "/.pori/login",
+                    &mut HashMap::new(),
+                    Some(form.as_bytes()),
+                )
+                .await;
+            let AuthDecision::Respond(redirect) = decision else {
+                panic!("expected redirect after login");
+            };
+            assert_eq!(redirect.headers["location"], "/");
+        }
+
+        let set_cookie = &response.headers["set-cookie"];
+        let cookie = set_cookie.split(';').next().unwrap();
+        let decision = gate
+            .evaluate(
+                "GET",
+                "/app/home",
+                &mut headers(&[("cookie", cookie)]),
+                None,
+            )
+            .await;
+        assert!(matches!(decision, AuthDecision::Allow { user: Some(ref u) } if u == "alice"));
+
+        let tampered = format!("{cookie}x");
+        let decision = gate
+            .evaluate("GET", "/app", &mut headers(&[("cookie", &tampered)]), None)
+            .await;
+        assert!(matches!(decision, AuthDecision::Reject { .. }));
+    }
+
+    #[test]
+    fn test_invalid_config_rejected() {
+        let config = AuthConfig {
+            routes: vec![AuthRouteConfig {
+                path: "/".to_string(),
+                methods: Vec::new(),
+                mode: AuthMode::Basic,
+                realm: None,
+            }],
+            ..AuthConfig::default()
+        };
+        assert!(AuthGate::from_config(&config).is_err());
+
+        // Session lifetimes must stay well inside the timestamp range
+        for session_ttl in [0, u64::MAX, i64::MAX as u64] {
+            let config = AuthConfig {
+                cookie_secret: Some("0123456789abcdef0123".to_string()),
+                session_ttl,
+                ..AuthConfig::default()
+            };
+            assert!(AuthGate::from_config(&config).is_err());
+        }
+    }
+}
@@ -1,20 +1,144 @@
-use anyhow::{Context, Result};
-use reqwest::{Client, ClientBuilder};
+use anyhow::{bail, Context, Result};
+use reqwest::{Certificate, Client, ClientBuilder, Identity};
 use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, info};
 use url::Url;
 
-use crate::config::settings::HttpVersion;
+use super::files::FileServer;
+use super::mock::MockServer;
+use super::upstream::Upstream;
+use crate::config::settings::{
+    FileServerConfig, HttpVersion, LocalServerTlsConfig, MockRouteConfig,
+};
+use crate::utils::http::find_key;
 
 /// HTTP client for local server communication
 #[derive(Clone)]
 pub struct LocalServerClient {
-    client: Client,
+    transport: Transport,
     base_url: Url,
     timeout: Duration,
 }
 
+/// How requests reach the local server
+#[derive(Clone)]
+enum Transport {
+    Http(Client),
+    #[cfg(unix)]
+    Unix {
+        socket: std::path::PathBuf,
+        base_path: String,
+    },
+    Files(std::sync::Arc<FileServer>),
+    Echo,
+    Mock(std::sync::Arc<MockServer>),
+}
+
+/// Certificates and server name for HTTPS local servers
+pub struct UpstreamTls {
+    roots: Vec<Certificate>,
+    identity: Option<Identity>,
+    sni_hostname: Option<String>,
+}
+
+impl UpstreamTls {
+    /// Load the CA bundles and client certificate named in the config
+    pub fn from_config(config: &LocalServerTlsConfig) -> Result<Self> {
+        let mut roots = Vec::new();
+        for path in &config.ca_files {
+            let pem = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA bundle {path}"))?;
+            if certs.is_empty() {
+                bail!("No certificates found in {path}");
+            }
+            roots.extend(certs);
+        }
+
+        let identity = match (&config.cert_file, &config.key_file) {
+            (Some(cert_file), Some(key_file)) => {
+                let mut pem = std::fs::read(cert_file)
+                    .with_context(|| format!("Failed to read {cert_file}"))?;
+                pem.push(b'\n');
+                pem.extend(
+                    std::fs::read(key_file)
+                        .with_context(|| format!("Failed to read {key_file}"))?,
+                );
+                Some(Identity::from_pem(&pem).with_context(|| {
+                    format!("Invalid client certificate {cert_file} or key {key_file}")
+                })?)
+            }
+            (None, None) => None,
+            _ => bail!(
+                "local_server.tls.cert_file and local_server.tls.key_file must be set together"
+            ),
+        };
+
+        if let Some(name) = &config.sni_hostname {
+            if !matches!(url::Host::parse(name), Ok(url::Host::Domain(_))) {
+                bail!("Invalid local_server.tls.sni_hostname '{name}'");
+            }
+        }
+
+        Ok(Self {
+            roots,
+            identity,
+            sni_hostname: config.sni_hostname.clone(),
+        })
+    }
+
+    /// Add the certificates to `builder`; with an SNI override, requests go to
+    /// the server name, which is pinned to the addresses of `base_url`
+    fn apply(self, mut builder: ClientBuilder, base_url: &mut Url) -> Result<ClientBuilder> {
+        for root in self.roots {
+            builder = builder.add_root_certificate(root);
+        }
+        if let Some(identity) = self.identity {
+            builder = builder.identity(identity);
+        }
+        if let Some(name) = self.sni_hostname {
+            // Never resolve here: this runs on the runtime during reloads
+            builder = match base_url.host() {
+                Some(url::Host::Ipv4(ip)) => builder.resolve(&name, (ip, 0).into()),
+                Some(url::Host::Ipv6(ip)) => builder.resolve(&name, (ip, 0).into()),
+                Some(url::Host::Domain(host)) => {
+                    builder.dns_resolver(std::sync::Arc::new(SniResolver {
+                        name: name.clone(),
+                        host: host.to_string(),
+                    }))
+                }
+                None => bail!("Local server URL {base_url} has no host"),
+            };
+            base_url
+                .set_host(Some(&name))
+                .with_context(|| format!("Invalid SNI hostname '{name}'"))?;
+        }
+        Ok(builder)
+    }
+}
+
+/// Resolves the SNI override name to the configured host when a request connects
+struct SniResolver {
+    name: String,
+    host: String,
+}
+
+impl reqwest::dns::Resolve for SniResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = if name.as_str() == self.name {
+            self.host.clone()
+        } else {
+            name.as_str().to_string()
+        };
+        Box::pin(async move {
+            // The connector replaces port 0 with the request's port
+            let addrs: Vec<_> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
 /// Response from a local server
 #[derive(Debug)]
 pub struct LocalServerResponse {
@@ -27,18 +151,63 @@ pub struct LocalServerResponse {
 impl LocalServerClient {
     /// Create a new local server client
     pub fn new(
-        base_url: Url,
+        mut base_url: Url,
         timeout: Duration,
         verify_ssl: bool,
         http_version: &HttpVersion,
+        tls: &LocalServerTlsConfig,
+        files: &FileServerConfig,
+        mock: &[MockRouteConfig],
     ) -> Result<Self> {
-        let mut builder = ClientBuilder::new()
+        match Upstream::from_url(&base_url)? {
+            Upstream::Http(_) => {}
+            #[cfg(unix)]
+            Upstream::Unix { socket, base_path } => {
+                info!("Local server: Unix socket {}", socket.display());
+                return Ok(Self {
+                    transport: Transport::Unix { socket, base_path },
+                    base_url,
+                    timeout,
+                });
+            }
+            #[cfg(not(unix))]
+            Upstream::Unix { .. } => bail!("Unix socket upstreams are not supported here"),
+            Upstream::Files(root) => {
+                let server = FileServer::from_config(&root, files)?;
+                info!("Local server: serving files from {}", root.display());
+                return Ok(Self {
+                    transport: Transport::Files(std::sync::Arc::new(server)),
+                    base_url,
+                    timeout,
+                });
+            }
+            Upstream::Echo => {
+                info!("Local server: echoing requests");
+                return Ok(Self {
+                    transport: Transport::Echo,
+                    base_url,
+                    timeout,
+                });
+            }
+            Upstream::Mock => {
+                let server = MockServer::from_config(mock)?;
+                info!("Local server: {} mock routes", mock.len());
+                return Ok(Self {
+                    transport: Transport::Mock(std::sync::Arc::new(server)),
+                    base_url,
+                    timeout,
+                });
+            }
+        }
+
+        let builder = ClientBuilder::new()
             .timeout(timeout)
             .connect_timeout(Duration::from_secs(10))
             .danger_accept_invalid_certs(!verify_ssl)
             .pool_max_idle_per_host(10)
             .pool_idle_timeout(Duration::from_secs(60))
             .tcp_keepalive(Duration::from_secs(30));
+        let mut builder = UpstreamTls::from_config(tls)?.apply(builder, &mut base_url)?;
 
         // Configure HTTP version based on settings
         builder = match http_version {
@@ -60,7 +229,7 @@ impl LocalServerClient {
         let client = builder.build().context("Failed to create an HTTP client")?;
 
         Ok(Self {
-            client,
+            transport: Transport::Http(client),
             base_url,
             timeout,
         })
@@ -73,21 +242,65 @@ impl LocalServerClient {
         path: &str,
         headers: HashMap<String, String>,
         body: Option<Vec<u8>>,
+    ) -> Result<LocalServerResponse> {
+        // Drop proxy-specific headers
+        let headers: HashMap<String, String> = headers
+            .into_iter()
+            .filter(|(key, _)| !self.should_skip_header(key))
+            .collect();
+
+        let start_time = std::time::Instant::now();
+        let local_response = match &self.transport {
+            Transport::Http(client) => {
+                self.forward_http(client, method, path, headers, body)
+                    .await?
+            }
+            #[cfg(unix)]
+            Transport::Unix { socket, base_path } => tokio::time::timeout(
+                self.timeout,
+                self.forward_unix(socket, base_path, method, path, headers, body),
+            )
+            .await
+            .context("Local server request timed out")??,
+            Transport::Files(server) => server.handle(method, path, &headers, body).await?,
+            Transport::Echo => super::mock::echo(method, path, &headers, body.as_deref()),
+            // Mock latency counts against the timeout, like a slow local server
+            Transport::Mock(server) => {
+                tokio::time::timeout(self.timeout, server.handle(method, path))
+                    .await
+                    .context("Local server request timed out")?
+            }
+        };
+
+        info!(
+            "Local server response: {} {} -> {} ({:?})",
+            method,
+            path,
+            local_response.status,
+            start_time.elapsed()
+        );
+
+        Ok(local_response)
+    }
+
+    /// Send a request with reqwest
+    async fn forward_http(
+        &self,
+        client: &Client,
+        method: &str,
+        path: &str,
+        headers: HashMap<String, String>,
+        body: Option<Vec<u8>>,
     ) -> Result<LocalServerResponse> {
         let url = self.build_url(path)?;
 
         debug!("Forwarding {} {} to a local server", method, url);
 
         // Build request
-        let mut request_builder = self
-            .client
-            .request(method.parse().context("Invalid HTTP method")?, url.clone());
-
-        // Add headers (excluding certain proxy-specific headers)
+        let mut request_builder =
+            client.request(method.parse().context("Invalid HTTP method")?, url);
         for (key, value) in headers {
-            if !self.should_skip_header(&key) {
-                request_builder = request_builder.header(&key, &value);
-            }
+            request_builder = request_builder.header(&key, &value);
         }
 
         // Add body if present
@@ -96,37 +309,83 @@ impl LocalServerClient {
         }
 
         // Send request
-        let start_time = std::time::Instant::now();
         let response = request_builder
             .send()
             .await
             .context("Failed to send a request to a local server")?;
 
-        let duration = start_time.elapsed();
-        let status = response.status();
+        // Convert response
+        self.convert_response(response).await
+    }
 
-        info!(
-            "Local server response: {} {} -> {} ({:?})",
-            method, path, status, duration
+    /// Send a request over a Unix domain socket with HTTP/1.1
+    #[cfg(unix)]
+    async fn forward_unix(
+        &self,
+        socket: &std::path::Path,
+        base_path: &str,
+        method: &str,
+        path: &str,
+        headers: HashMap<String, String>,
+        body: Option<Vec<u8>>,
+    ) -> Result<LocalServerResponse> {
+        let uri = if path.starts_with('/') {
+            format!("{base_path}{path}")
+        } else {
+            format!("{base_path}/{path}")
+        };
+
+        debug!(
+            "Forwarding {} {} to a local server at {}",
+            method,
+            uri,
+            socket.display()
         );
 
-        // Convert response
-        let local_response = self.convert_response(response).await?;
+        let mut request = hyper::Request::builder().method(method).uri(uri.as_str());
+        if find_key(&headers, "host").is_none() {
+            request = request.header("host", "localhost");
+        }
+        for (key, value) in &headers {
+            request = request.header(key, value);
+        }
+        let request = request
+            .body(http_body_util::Full::new(bytes::Bytes::from(
+                body.unwrap_or_default(),
+            )))
+            .context("Invalid request for the local server")?;
 
-        Ok(local_response)
+        let response = super::upstream::unix::send(socket, request).await?;
+        let status = response.status();
+        let headers = self.response_headers(response.headers());
+        let body = response.into_body();
+
+        Ok(LocalServerResponse {
+            status: status.as_u16(),
+            status_text: status.canonical_reason().unwrap_or("Unknown").to_string(),
+            headers,
+            body: (!body.is_empty()).then(|| body.to_vec()),
+        })
     }
 
     /// Build target URL from a path
     fn build_url(&self, path: &str) -> Result<Url> {
-        let path = if let Some(stripped) = path.strip_prefix('/') {
-            stripped
+        let path = path.strip_prefix('/').unwrap_or(path);
+        // `./` keeps `https:…` or `//host` from being joined as another origin
+        let path = if path.is_empty() || path.starts_with('?') {
+            path.to_string()
         } else {
-            path
+            format!("./{path}")
         };
 
-        self.base_url
-            .join(path)
-            .context("Failed to build target URL")
+        let url = self
+            .base_url
+            .join(&path)
+            .context("Failed to build target URL")?;
+        if url.origin() != self.base_url.origin() {
+            anyhow::bail!("Request path leaves the local server");
+        }
+        Ok(url)
     }
 
     /// Convert reqwest response to our response type
@@ -135,14 +394,7 @@ impl LocalServerClient {
         let status_text = status.canonical_reason().unwrap_or("Unknown").to_string();
 
         // Extract headers
-        let mut headers = HashMap::new();
-        for (key, value) in response.headers() {
-            if let Ok(value_str) = value.to_str() {
-                if !self.should_skip_response_header(key.as_str()) {
-                    headers.insert(key.to_string(), value_str.to_string());
-                }
-            }
-        }
+        let headers = self.response_headers(response.headers());
 
         // Read body
         let body_bytes = response
@@ -164,20 +416,35 @@ impl LocalServerClient {
         })
     }
 
+    /// Response headers passed back to the relay
+    fn response_headers(&self, headers: &reqwest::header::HeaderMap) -> HashMap<String, String> {
+        let mut result = HashMap::new();
+        for (key, value) in headers {
+            if let Ok(value_str) = value.to_str() {
+                if !self.should_skip_response_header(key.as_str()) {
+                    result.insert(key.to_string(), value_str.to_string());
+                }
+            }
+        }
+        result
+    }
+
     /// Check if the header should be skipped when forwarding requests
+    ///
+    /// `Host` is passed through: the forwarder drops the public Host and only
+    /// re-adds one when a header rule sets it.
     fn should_skip_header(&self, header_name: &str) -> bool {
         let header_lower = header_name.to_lowercase();
         matches!(
             header_lower.as_str(),
-            "host"
-                | "connection"
+            "connection"
                 | "upgrade"
                 | "proxy-connection"
                 | "proxy-authorization"
                 | "te"
                 | "trailers"
                 | "transfer-encoding"
-                | "content-length"  // Skip content-length to let reqwest set it correctly
+                | "content-length" // Skip content-length to let reqwest set it correctly
         )
     }
 
@@ -190,8 +457,6 @@ impl LocalServerClient {
             "connection" | "upgrade" | "proxy-connection" | "transfer-encoding" | "te" | "trailers"
             // Headers that might conflict with proxy operation
             | "x-request-id" | "x-forwarded-by" | "x-forwarded-for" | "x-forwarded-proto"
-            // Server-specific headers that shouldn't be forwarded
-            | "server" | "x-powered-by"
         )
     }
 
@@ -218,28 +483,158 @@ mod tests {
     #[test]
     fn test_url_building() {
         let base_url: Url = "https://localhost:3000".parse().unwrap();
-        let client =
-            LocalServerClient::new(base_url, Duration::from_secs(30), false, &HttpVersion::Auto)
-                .unwrap();
+        let client = LocalServerClient::new(
+            base_url,
+            Duration::from_secs(30),
+            false,
+            &HttpVersion::Auto,
+            &LocalServerTlsConfig::default(),
+            &FileServerConfig::default(),
+            &[],
+        )
+        .unwrap();
 
         let url1 = client.build_url("/api/test").unwrap();
         assert_eq!(url1.to_string(), "https://localhost:3000/api/test");
 
         let url2 = client.build_url("api/test").unwrap();
         assert_eq!(url2.to_string(), "https://localhost:3000/api/test");
+
+        for path in [
+            "/https://evil.example/",
+            "/http:/evil.example/",
+            "///evil.example/",
+        ] {
+            assert_eq!(
+                client.build_url(path).unwrap().host_str(),
+                Some("localhost"),
+                "{path}"
+            );
+        }
     }
 
     #[test]
     fn test_header_filtering() {
         let base_url: Url = "https://localhost:3000".parse().unwrap();
-        let client =
-            LocalServerClient::new(base_url, Duration::from_secs(30), false, &HttpVersion::Auto)
-                .unwrap();
+        let client = LocalServerClient::new(
+            base_url,
+            Duration::from_secs(30),
+            false,
+            &HttpVersion::Auto,
+            &LocalServerTlsConfig::default(),
+            &FileServerConfig::default(),
+            &[],
+        )
+        .unwrap();
 
-        assert!(client.should_skip_header("host"));
+        assert!(!client.should_skip_header("host"));
         assert!(client.should_skip_header("Connection"));
         assert!(client.should_skip_header("PROXY-CONNECTION"));
         assert!(!client.should_skip_header("content-type"));
         assert!(!client.should_skip_header("authorization"));
     }
+
+    #[test]
+    fn test_sni_hostname_override() {
+        let base_url: Url = "https://127.0.0.1:8443".parse().unwrap();
+        let tls = LocalServerTlsConfig {
+            sni_hostname: Some("app.localhost".to_string()),
+            ..LocalServerTlsConfig::default()
+        };
+        let client = LocalServerClient::new(
+            base_url,
+            Duration::from_secs(30),
+            true,
+            &HttpVersion::Auto,
+            &tls,
+            &FileServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            client.build_url("/health").unwrap().as_str(),
+            "https://app.localhost:8443/health"
+        );
+
+        // Domains are only resolved when a request connects
+        let client = LocalServerClient::new(
+            "https://upstream.invalid:8443".parse().unwrap(),
+            Duration::from_secs(30),
+            true,
+            &HttpVersion::Auto,
+            &tls,
+            &FileServerConfig::default(),
+            &[],
+        );
+        assert!(client.is_ok());
+
+        let cert_only = LocalServerTlsConfig {
+            cert_file: Some("client.pem".to_string()),
+            ..LocalServerTlsConfig::default()
+        };
+        assert!(UpstreamTls::from_config(&cert_only).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sni_resolver_maps_to_configured_host() {
+        use reqwest::dns::Resolve;
+
+        let resolver = SniResolver {
+            name: "app.localhost".to_string(),
+            host: "localhost".to_string(),
+        };
+        let addrs: Vec<_> = resolver
+            .resolve("app.localhost".parse().unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert!(!addrs.is_empty());
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_upstream() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let socket = std::env::temp_dir().join(format!("pori-{}.sock", uuid::Uuid::new_v4()));
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(stream.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            let target = request.split(' ').nth(1).unwrap().to_string();
+            assert!(request.contains("host: localhost"));
+            let response = format!(
+                "HTTP/1.1 201 Created\r\nContent-Length: {}\r\nX-Upstream: unix\r\n\r\n{}",
+                target.len(),
+                target
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let url = Url::parse(&format!("unix://{}:/api", socket.display())).unwrap();
+        let client = LocalServerClient::new(
+            url,
+            Duration::from_secs(5),
+            true,
+            &HttpVersion::Auto,
+            &LocalServerTlsConfig::default(),
+            &FileServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let response = client
+            .forward_request("GET", "/items?page=2", HashMap::new(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 201);
+        assert_eq!(response.headers.get("x-upstream").unwrap(), "unix");
+        assert_eq!(response.body.unwrap(), b"/api/items?page=2");
+        std::fs::remove_file(socket).unwrap();
+    }
 }
@@ -0,0 +1,517 @@
+//! Static file upstream
+//!
+//! Serves a directory for `file://` upstreams without a local server: index
+//! files, optional directory listings, single byte ranges, ETags, a fallback
+//! file for single-page apps and optional `PUT` uploads.
+
+use anyhow::{bail, Context, Result};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tracing::info;
+
+use super::client::LocalServerResponse;
+use crate::config::settings::FileServerConfig;
+use crate::utils::http::header_value;
+
+/// Characters escaped in listing links
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Serves files below a root directory
+#[derive(Debug, Clone)]
+pub struct FileServer {
+    root: PathBuf,
+    config: FileServerConfig,
+}
+
+impl FileServer {
+    /// Check the root directory and options
+    pub fn from_config(root: &Path, config: &FileServerConfig) -> Result<Self> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("File upstream {} does not exist", root.display()))?;
+        if !root.is_dir() {
+            bail!("File upstream {} is not a directory", root.display());
+        }
+        for name in &config.index {
+            if name.is_empty() || name.contains('/') {
+                bail!("Index file '{name}' must be a plain file name");
+            }
+        }
+        if let Some(fallback) = &config.spa_fallback {
+            if relative_path(fallback).is_none() {
+                bail!("SPA fallback '{fallback}' must be a path inside the served directory");
+            }
+        }
+        Ok(Self {
+            root,
+            config: config.clone(),
+        })
+    }
+
+    /// Answer a request from the directory
+    pub async fn handle(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HashMap<String, String>,
+        body: Option<Vec<u8>>,
+    ) -> Result<LocalServerResponse> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let Some(relative) = relative_path(path) else {
+            return Ok(text_response(400, "Invalid path"));
+        };
+        // `.env`, `.git/` and the like stay private unless asked for
+        if !self.config.dotfiles && relative.iter().any(is_hidden) {
+            return Ok(text_response(404, "Not found"));
+        }
+
+        match method {
+            "GET" | "HEAD" => self.get(method == "HEAD", path, &relative, headers).await,
+            "PUT" if self.config.upload => self.put(path, &relative, body).await,
+            _ => {
+                let allow = if self.config.upload {
+                    "GET, HEAD, PUT"
+                } else {
+                    "GET, HEAD"
+                };
+                let mut response = text_response(405, "Method not allowed");
+                response
+                    .headers
+                    .insert("allow".to_string(), allow.to_string());
+                Ok(response)
+            }
+        }
+    }
+
+    async fn get(
+        &self,
+        head: bool,
+        path: &str,
+        relative: &Path,
+        headers: &HashMap<String, String>,
+    ) -> Result<LocalServerResponse> {
+        let mut target = self.root.join(relative);
+
+        if let Some(metadata) = self.metadata(&target).await {
+            if metadata.is_dir() {
+                if !path.ends_with('/') {
+                    let mut response = text_response(301, "Moved permanently");
+                    response
+                        .headers
+                        .insert("location".to_string(), format!("{path}/"));
+                    return Ok(response);
+                }
+                for name in &self.config.index {
+                    let index = target.join(name);
+                    if let Some(metadata) = self.metadata(&index).await.filter(Metadata::is_file) {
+                        return serve_file(&index, &metadata, head, headers).await;
+                    }
+                }
+                if self.config.listing {
+                    return self.listing(&target, path, head).await;
+                }
+                return Ok(text_response(404, "Not found"));
+            }
+            return serve_file(&target, &metadata, head, headers).await;
+        }
+
+        // Client-side routes have no extension; let the app handle them
+        let has_extension = relative.extension().is_some();
+        if let (Some(fallback), false) = (&self.config.spa_fallback, has_extension) {
+            target = self.root.join(relative_path(fallback).unwrap_or_default());
+            if let Some(metadata) = self.metadata(&target).await.filter(Metadata::is_file) {
+                return serve_file(&target, &metadata, head, headers).await;
+            }
+        }
+        Ok(text_response(404, "Not found"))
+    }
+
+    async fn put(
+        &self,
+        path: &str,
+        relative: &Path,
+        body: Option<Vec<u8>>,
+    ) -> Result<LocalServerResponse> {
+        if path.ends_with('/') || relative.as_os_str().is_empty() {
+            return Ok(text_response(400, "Upload path must name a file"));
+        }
+        let target = self.root.join(relative);
+        let parent = target.parent().unwrap_or(&self.root);
+        // Check the deepest existing directory before creating any, so a
+        // symlink leaving the root cannot get directories made outside it
+        let mut existing = parent;
+        while tokio::fs::metadata(existing).await.is_err() {
+            match existing.parent() {
+                Some(up) => existing = up,
+                None => break,
+            }
+        }
+        if !self.inside_root(existing).await {
+            return Ok(text_response(403, "Forbidden"));
+        }
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        // Refuse to write through symlinks that leave the root
+        if !self.inside_root(parent).await {
+            return Ok(text_response(403, "Forbidden"));
+        }
+        if tokio::fs::metadata(&target)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Ok(text_response(409, "A directory exists at this path"));
+        }
+
+        let existed = tokio::fs::symlink_metadata(&target).await.is_ok();
+        let temporary = target.with_file_name(format!(
+            ".{}.pori-upload-{}",
+            target.file_name().unwrap_or_default().to_string_lossy(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        let body = body.unwrap_or_default();
+        tokio::fs::write(&temporary, &body)
+            .await
+            .with_context(|| format!("Failed to write {}", temporary.display()))?;
+        tokio::fs::rename(&temporary, &target)
+            .await
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+        info!("Stored upload {} ({} bytes)", target.display(), body.len());
+
+        Ok(if existed {
+            text_response(204, "")
+        } else {
+            text_response(201, "Created")
+        })
+    }
+
+    /// HTML index of a directory
+    async fn listing(&self, dir: &Path, path: &str, head: bool) -> Result<LocalServerResponse> {
+        let mut entries = Vec::new();
+        let mut reader = tokio::fs::read_dir(dir)
+            .await
+            .with_context(|| format!("Failed to list {}", dir.display()))?;
+        while let Some(entry) = reader.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !self.config.dotfiles && name.starts_with('.') {
+                continue;
+            }
+            let is_dir = entry.file_type().await.is_ok_and(|kind| kind.is_dir());
+            entries.push((!is_dir, name, is_dir));
+        }
+        entries.sort();
+
+        let title = html_escape(&percent_decode_str(path).decode_utf8_lossy());
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n<body><h1>Index of {title}</h1>\n<ul>\n"
+        );
+        if path != "/" {
+            html.push_str("<li><a href=\"../\">../</a></li>\n");
+        }
+        for (_, name, is_dir) in entries {
+            let suffix = if is_dir { "/" } else { "" };
+            html.push_str(&format!(
+                "<li><a href=\"{}{suffix}\">{}{suffix}</a></li>\n",
+                utf8_percent_encode(&name, PATH_SEGMENT),
+                html_escape(&name)
+            ));
+        }
+        html.push_str("</ul></body></html>\n");
+
+        let mut response = text_response(200, "");
+        response.headers.insert(
+            "content-type".to_string(),
+            "text/html; charset=utf-8".to_string(),
+        );
+        response
+            .headers
+            .insert("content-length".to_string(), html.len().to_string());
+        response.body = (!head).then(|| html.into_bytes());
+        Ok(response)
+    }
+
+    /// Metadata of a path that resolves inside the root
+    async fn metadata(&self, path: &Path) -> Option<Metadata> {
+        if !self.inside_root(path).await {
+            return None;
+        }
+        tokio::fs::metadata(path).await.ok()
+    }
+
+    async fn inside_root(&self, path: &Path) -> bool {
+        tokio::fs::canonicalize(path)
+            .await
+            .is_ok_and(|resolved| resolved.starts_with(&self.root))
+    }
+}
+
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+/// Decode a request path into a relative path without `..` or root components
+fn relative_path(path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode_str(path).decode_utf8().ok()?;
+    let mut relative = PathBuf::new();
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(relative)
+}
+
+/// Send a file, or the requested byte range of it
+async fn serve_file(
+    path: &Path,
+    metadata: &Metadata,
+    head: bool,
+    headers: &HashMap<String, String>,
+) -> Result<LocalServerResponse> {
+    let header = |name: &str| header_value(headers, name);
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = format!(
+        "\"{:x}-{:x}\"",
+        len,
+        modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |age| age.as_nanos())
+    );
+
+    let mut response = text_response(200, "");
+    response.headers.insert(
+        "content-type".to_string(),
+        mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string(),
+    );
+    response.headers.insert("etag".to_string(), etag.clone());
+    response
+        .headers
+        .insert("accept-ranges".to_string(), "bytes".to_string());
+    if let Some(modified) = modified {
+        let modified: chrono::DateTime<chrono::Utc> = modified.into();
+        response.headers.insert(
+            "last-modified".to_string(),
+            modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        );
+    }
+
+    if header("if-none-match").is_some_and(|tags| {
+        tags.split(',')
+            .any(|tag| tag.trim() == "*" || tag.trim().trim_start_matches("W/") == etag)
+    }) {
+        response.status = 304;
+        response.status_text = "Not Modified".to_string();
+        response.body = None;
+        return Ok(response);
+    }
+
+    // A stale If-Range means the client wants the whole new file
+    let range = header("range").filter(|_| header("if-range").is_none_or(|tag| tag == etag));
+    let (start, end) = match range.map(|range| parse_range(range, len)) {
+        None | Some(Range::Ignored) => (0, len),
+        Some(Range::Unsatisfiable) => {
+            let mut response = text_response(416, "Range not satisfiable");
+            response
+                .headers
+                .insert("content-range".to_string(), format!("bytes */{len}"));
+            return Ok(response);
+        }
+        Some(Range::Bytes(start, end)) => {
+            response.status = 206;
+            response.status_text = "Partial Content".to_string();
+            response.headers.insert(
+                "content-range".to_string(),
+                format!("bytes {}-{}/{}", start, end - 1, len),
+            );
+            (start, end)
+        }
+    };
+
+    response
+        .headers
+        .insert("content-length".to_string(), (end - start).to_string());
+    if head || start == end {
+        response.body = None;
+        return Ok(response);
+    }
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut content = Vec::with_capacity((end - start) as usize);
+    file.take(end - start).read_to_end(&mut content).await?;
+    response.body = Some(content);
+    Ok(response)
+}
+
+enum Range {
+    /// Half-open byte range
+    Bytes(u64, u64),
+    Unsatisfiable,
+    /// Malformed or multiple ranges; the whole file is sent
+    Ignored,
+}
+
+fn parse_range(value: &str, len: u64) -> Range {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Range::Ignored;
+    };
+    if spec.contains(',') {
+        return Range::Ignored;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Range::Ignored;
+    };
+    let range = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        (Some(start), Some(end)) if start <= end => (start, end.saturating_add(1).min(len)),
+        (Some(start), None) if end.is_empty() => (start, len),
+        (None, Some(suffix)) if start.is_empty() => (len.saturating_sub(suffix), len),
+        _ => return Range::Ignored,
+    };
+    if range.0 >= len || range.0 >= range.1 {
+        return Range::Unsatisfiable;
+    }
+    Range::Bytes(range.0, range.1)
+}
+
+fn text_response(status: u16, body: &str) -> LocalServerResponse {
+    let status_text = hyper::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown")
+        .to_string();
+    let mut headers = HashMap::new();
+    if !body.is_empty() {
+        headers.insert(
+            "content-type".to_string(),
+            "text/plain; charset=utf-8".to_string(),
+        );
+    }
+    LocalServerResponse {
+        status,
+        status_text,
+        headers,
+        body: (!body.is_empty()).then(|| body.as_bytes().to_vec()),
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serving_ranges_etags_and_uploads() {
+        let root = std::env::temp_dir().join(format!("pori-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("index.html"), "<h1>home</h1>").unwrap();
+        std::fs::write(root.join("docs/notes.txt"), "0123456789").unwrap();
+
+        let config = FileServerConfig {
+            listing: true,
+            spa_fallback: Some("index.html".to_string()),
+            upload: true,
+            ..FileServerConfig::default()
+        };
+        let server = FileServer::from_config(&root, &config).unwrap();
+        let none = HashMap::new();
+
+        let index = server.handle("GET", "/", &none, None).await.unwrap();
+        assert_eq!(index.status, 200);
+        assert_eq!(index.headers["content-type"], "text/html");
+
+        let range = HashMap::from([("Range".to_string(), "bytes=2-4".to_string())]);
+        let partial = server
+            .handle("GET", "/docs/notes.txt", &range, None)
+            .await
+            .unwrap();
+        assert_eq!(partial.status, 206);
+        assert_eq!(partial.body.unwrap(), b"234");
+        assert_eq!(partial.headers["content-range"], "bytes 2-4/10");
+
+        let etag = partial.headers["etag"].clone();
+        let cached = HashMap::from([("if-none-match".to_string(), etag)]);
+        let not_modified = server
+            .handle("GET", "/docs/notes.txt", &cached, None)
+            .await
+            .unwrap();
+        assert_eq!(not_modified.status, 304);
+
+        let listing = server.handle("GET", "/docs/", &none, None).await.unwrap();
+        assert!(String::from_utf8(listing.body.unwrap())
+            .unwrap()
+            .contains("notes.txt"));
+        assert_eq!(
+            server
+                .handle("GET", "/docs", &none, None)
+                .await
+                .unwrap()
+                .status,
+            301
+        );
+
+        // Client-side routes fall back to the app, missing assets do not
+        let route = server.handle("GET", "/settings/profile", &none, None).await;
+        assert_eq!(route.unwrap().status, 200);
+        let asset = server.handle("GET", "/missing.js", &none, None).await;
+        assert_eq!(asset.unwrap().status, 404);
+
+        let escape = server.handle("GET", "/../secret", &none, None).await;
+        assert_eq!(escape.unwrap().status, 400);
+
+        let upload = server
+            .handle("PUT", "/drop/new.txt", &none, Some(b"hello".to_vec()))
+            .await
+            .unwrap();
+        assert_eq!(upload.status, 201);
+        assert_eq!(std::fs::read(root.join("drop/new.txt")).unwrap(), b"hello");
+
+        std::fs::write(root.join(".env"), "SECRET=1").unwrap();
+        let hidden = server.handle("GET", "/.env", &none, None).await;
+        assert_eq!(hidden.unwrap().status, 404);
+        std::fs::write(root.join("docs/.secret"), "token").unwrap();
+        let listing = server.handle("GET", "/docs/", &none, None).await.unwrap();
+        assert!(!String::from_utf8(listing.body.unwrap())
+            .unwrap()
+            .contains(".secret"));
+
+        #[cfg(unix)]
+        {
+            let outside =
+                std::env::temp_dir().join(format!("pori-outside-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&outside).unwrap();
+            std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+            let escape = server
+                .handle("PUT", "/link/a/b/x.txt", &none, Some(b"x".to_vec()))
+                .await
+                .unwrap();
+            assert_eq!(escape.status, 403);
+            assert!(!outside.join("a").exists());
+            std::fs::remove_dir_all(outside).unwrap();
+        }
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
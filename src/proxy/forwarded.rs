@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use url::Url;
+
+use crate::config::settings::ForwardedConfig;
+use crate::protocol::http::ProxyInfo;
+use crate::utils::http::{find_key, remove_header};
+
+/// Via pseudonym added to forwarded requests
+const VIA_PSEUDONYM: &str = "1.1 pori";
+
+/// Adds standard forwarding headers toward the local app and maps
+/// local URLs in responses back to the public hostname
+#[derive(Debug, Clone)]
+pub struct ForwardedHeaders {
+    enabled: bool,
+    trust_incoming: bool,
+    trusted_hops: usize,
+    public_host: Option<String>,
+    proto: String,
+    rewrite_location: bool,
+    rewrite_cookie_domain: bool,
+    local_url: Url,
+}
+
+impl ForwardedHeaders {
+    /// Build from configuration, defaulting the public scheme from the relay URL
+    pub fn from_config(config: &ForwardedConfig, local_url: &Url, relay_url: &Url) -> Self {
+        let proto = config.proto.clone().unwrap_or_else(|| {
+            if relay_url.scheme() == "wss" {
+                "https".to_string()
+            } else {
+                "http".to_string()
+            }
+        });
+
+        Self {
+            enabled: config.enabled,
+            trust_incoming: config.trust_incoming,
+            trusted_hops: config.trusted_hops.max(1),
+            public_host: config.public_host.clone(),
+            proto,
+            rewrite_location: config.rewrite_location,
+            rewrite_cookie_domain: config.rewrite_cookie_domain,
+            local_url: local_url.clone(),
+        }
+    }
+
+    /// Public hostname for a request, preferring the configured override
+    pub fn public_host(&self, request_host: &str) -> String {
+        self.public_host
+            .clone()
+            .unwrap_or_else(|| request_host.to_string())
+    }
+
+    /// Populate X-Forwarded-*, Forwarded and Via on a request to the local app
+    pub fn apply_request(
+        &self,
+        headers: &mut HashMap<String, String>,
+        proxy_info: &ProxyInfo,
+        request_host: &str,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        let incoming_proto = remove_header(headers, "x-forwarded-proto");
+        let incoming_host = remove_header(headers, "x-forwarded-host");
+        remove_header(headers, "x-forwarded-for");
+        remove_header(headers, "forwarded");
+        remove_header(headers, "via");
+
+        let client_ip = proxy_info
+            .client_addr()
+            .map(|ip| ip.to_string())
+            .filter(|_| proxy_info.client_ip != "unknown");
+
+        // Only keep what the trusted hops appended: the client the relay
+        // observed and the proxies after it, never entries the client sent
+        let mut chain = if self.trust_incoming {
+            trusted_tail(&proxy_info.forwarded_for, self.trusted_hops)
+        } else {
+            Vec::new()
+        };
+        if let Some(ip) = &client_ip {
+            if !chain.contains(ip) {
+                chain.push(ip.clone());
+            }
+        }
+
+        // The relay appends (or sets) its own value last; anything before it
+        // came from the client
+        let (proto, host) = if self.trust_incoming {
+            (
+                incoming_proto
+                    .as_deref()
+                    .and_then(last_entry)
+                    .unwrap_or_else(|| self.proto.clone()),
+                incoming_host
+                    .as_deref()
+                    .and_then(last_entry)
+                    .unwrap_or_else(|| self.public_host(request_host)),
+            )
+        } else {
+            (self.proto.clone(), self.public_host(request_host))
+        };
+
+        if !chain.is_empty() {
+            headers.insert("X-Forwarded-For".to_string(), chain.join(", "));
+        }
+        headers.insert("X-Forwarded-Proto".to_string(), proto.clone());
+        if !host.is_empty() {
+            headers.insert("X-Forwarded-Host".to_string(), host.clone());
+        }
+
+        headers.insert(
+            "Forwarded".to_string(),
+            forwarded_value(&chain, &host, &proto),
+        );
+
+        let mut via = if self.trust_incoming {
+            trusted_tail(&proxy_info.via, self.trusted_hops)
+        } else {
+            Vec::new()
+        };
+        via.push(VIA_PSEUDONYM.to_string());
+        headers.insert("Via".to_string(), via.join(", "));
+    }
+
+    /// Rewrite `Location` and `Set-Cookie` domains that point at the local server
+    pub fn rewrite_response(&self, headers: &mut HashMap<String, String>, request_host: &str) {
+        let public_host = self.public_host(request_host);
+        if public_host.is_empty() {
+            return;
+        }
+
+        if self.rewrite_location {
+            if let Some(key) = find_key(headers, "location") {
+                if let Some(rewritten) = self.rewrite_location_value(&headers[&key], &public_host) {
+                    headers.insert(key, rewritten);
+                }
+            }
+        }
+
+        if self.rewrite_cookie_domain {
+            if let Some(key) = find_key(headers, "set-cookie") {
+                if let Some(rewritten) = self.rewrite_cookie_value(&headers[&key], &public_host) {
+                    headers.insert(key, rewritten);
+                }
+            }
+        }
+    }
+
+    /// Map an absolute local URL onto the public scheme and host
+    fn rewrite_location_value(&self, location: &str, public_host: &str) -> Option<String> {
+        let url = Url::parse(location).ok()?;
+        if url.host_str()? != self.local_url.host_str()?
+            || url.port_or_known_default() != self.local_url.port_or_known_default()
+        {
+            return None;
+        }
+
+        let mut rewritten = format!("{}://{}{}", self.proto, public_host, url.path());
+        if let Some(query) = url.query() {
+            rewritten.push('?');
+            rewritten.push_str(query);
+        }
+        if let Some(fragment) = url.fragment() {
+            rewritten.push('#');
+            rewritten.push_str(fragment);
+        }
+        Some(rewritten)
+    }
+
+    /// Replace a cookie `Domain` attribute naming the local host
+    fn rewrite_cookie_value(&self, cookie: &str, public_host: &str) -> Option<String> {
+        let local_host = self.local_url.host_str()?;
+        let public_domain = public_host.split(':').next().unwrap_or(public_host);
+        let mut changed = false;
+
+        let attributes: Vec<String> = cookie
+            .split(';')
+            .map(|attribute| {
+                let trimmed = attribute.trim();
+                match trimmed.split_once('=') {
+                    Some((name, value))
+                        if name.trim().eq_ignore_ascii_case("domain")
+                            && value.trim().trim_start_matches('.') == local_host =>
+                    {
+                        changed = true;
+                        format!("Domain={public_domain}")
+                    }
+                    _ => trimmed.to_string(),
+                }
+            })
+            .collect();
+
+        changed.then(|| attributes.join("; "))
+    }
+}
+
+/// Build an RFC 7239 `Forwarded` value
+fn forwarded_value(chain: &[String], host: &str, proto: &str) -> String {
+    let mut elements: Vec<String> = chain
+        .iter()
+        .map(|node| format!("for={}", forwarded_node(node)))
+        .collect();
+
+    let mut last = elements.pop().unwrap_or_default();
+    if !host.is_empty() {
+        if !last.is_empty() {
+            last.push(';');
+        }
+        last.push_str(&format!("host={}", quote_if_needed(host)));
+    }
+    if !last.is_empty() {
+        last.push(';');
+    }
+    last.push_str(&format!("proto={proto}"));
+    elements.push(last);
+
+    elements.join(", ")
+}
+
+/// Format a node identifier, quoting IPv6 addresses as RFC 7239 requires
+fn forwarded_node(node: &str) -> String {
+    match node.parse::<IpAddr>() {
+        Ok(IpAddr::V6(v6)) => format!("\"[{v6}]\""),
+        Ok(IpAddr::V4(v4)) => v4.to_string(),
+        Err(_) => quote_if_needed(node),
+    }
+}
+
+/// Quote a value unless it is a plain token
+fn quote_if_needed(value: &str) -> String {
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-._~!$&'*+^`|".contains(c))
+    {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('"', "\\\""))
+    }
+}
+
+/// Entries appended by the last `hops` proxies
+fn trusted_tail(entries: &[String], hops: usize) -> Vec<String> {
+    entries[entries.len().saturating_sub(hops)..].to_vec()
+}
+
+/// Rightmost value of a comma-separated header
+fn last_entry(value: &str) -> Option<String> {
+    value
+        .rsplit(',')
+        .map(str::trim)
+        .find(|entry| !entry.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(config: ForwardedConfig) -> ForwardedHeaders {
+        ForwardedHeaders::from_config(
+            &config,
+            &"http://localhost:3000".parse().unwrap(),
+            &"wss://relay.pori.dev/ws".parse().unwrap(),
+        )
+    }
+
+    fn proxy_info(headers: &[(&str, &str)], trusted_hops: usize) -> ProxyInfo {
+        let headers = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ProxyInfo::from_headers(&headers, trusted_hops)
+    }
+
+    #[test]
+    fn test_request_headers_trusting_relay() {
+        let forwarded = forwarded(ForwardedConfig {
+            trust_incoming: true,
+            trusted_hops: 2,
+            ..ForwardedConfig::default()
+        });
+        // An edge proxy and the relay appended the last two entries
+        let incoming = [
+            ("X-Forwarded-For", "203.0.113.66, 198.51.100.4, 2001:db8::1"),
+            ("Via", "1.1 spoof, 1.1 spoof, 1.1 edge"),
+        ];
+        let info = proxy_info(&incoming, 2);
+        let mut headers = HashMap::from([
+            ("x-forwarded-for".to_string(), "spoof".to_string()),
+            (
+                "X-Forwarded-Host".to_string(),
+                "evil.com, demo.pori.dev".to_string(),
+            ),
+        ]);
+
+        forwarded.apply_request(&mut headers, &info, "demo.pori.dev");
+
+        assert_eq!(
+            headers.get("X-Forwarded-For").unwrap(),
+            "198.51.100.4, 2001:db8::1"
+        );
+        assert_eq!(headers.get("X-Forwarded-Proto").unwrap(), "https");
+        assert_eq!(headers.get("X-Forwarded-Host").unwrap(), "demo.pori.dev");
+        assert_eq!(
+            headers.get("Forwarded").unwrap(),
+            "for=198.51.100.4, for=\"[2001:db8::1]\";host=demo.pori.dev;proto=https"
+        );
+        assert_eq!(headers.get("Via").unwrap(), "1.1 spoof, 1.1 edge, 1.1 pori");
+        assert!(!headers.contains_key("x-forwarded-for"));
+    }
+
+    #[test]
+    fn test_untrusted_chain_is_discarded() {
+        let forwarded = forwarded(ForwardedConfig {
+            trust_incoming: false,
+            public_host: Some("app.example.com".to_string()),
+            ..ForwardedConfig::default()
+        });
+        // The requester wrote the leftmost entry and X-Real-IP; the relay saw 10.0.0.1
+        let info = proxy_info(
+            &[
+                ("X-Forwarded-For", "203.0.113.9, 10.0.0.1"),
+                ("X-Real-IP", "192.0.2.1"),
+                ("Via", "1.1 x"),
+            ],
+            1,
+        );
+        let mut headers = HashMap::from([("X-Forwarded-Host".to_string(), "evil.com".to_string())]);
+
+        forwarded.apply_request(&mut headers, &info, "demo.pori.dev");
+
+        assert_eq!(headers.get("X-Forwarded-For").unwrap(), "10.0.0.1");
+        assert_eq!(
+            headers.get("Forwarded").unwrap(),
+            "for=10.0.0.1;host=app.example.com;proto=https"
+        );
+        assert_eq!(headers.get("X-Forwarded-Host").unwrap(), "app.example.com");
+        assert_eq!(headers.get("Via").unwrap(), "1.1 pori");
+    }
+
+    #[test]
+    fn test_response_rewrites() {
+        let forwarded = forwarded(ForwardedConfig {
+            rewrite_location: true,
+            rewrite_cookie_domain: true,
+            ..ForwardedConfig::default()
+        });
+        let mut headers = HashMap::from([
+            (
+                "location".to_string(),
+                "http://localhost:3000/login?next=%2F".to_string(),
+            ),
+            (
+                "set-cookie".to_string(),
+                "sid=abc; Domain=localhost; Path=/; HttpOnly".to_string(),
+            ),
+        ]);
+
+        forwarded.rewrite_response(&mut headers, "demo.pori.dev");
+
+        assert_eq!(
+            headers.get("location").unwrap(),
+            "https://demo.pori.dev/login?next=%2F"
+        );
+        assert_eq!(
+            headers.get("set-cookie").unwrap(),
+            "sid=abc; Domain=demo.pori.dev; Path=/; HttpOnly"
+        );
+
+        // Foreign redirects are left alone
+        let mut external =
+            HashMap::from([("Location".to_string(), "https://github.com/".to_string())]);
+        forwarded.rewrite_response(&mut external, "demo.pori.dev");
+        assert_eq!(external.get("Location").unwrap(), "https://github.com/");
+    }
+}
@@ -1,79 +1,173 @@
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
-use tracing::{debug, error, instrument, warn};
-
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, instrument, warn, Instrument, Span};
+
+use super::access::AccessControl;
+use super::auth::{AuthDecision, AuthGate};
 use super::client::{LocalServerClient, LocalServerResponse};
-use crate::protocol::http::HttpMessage;
+use super::forwarded::ForwardedHeaders;
+use super::ratelimit::RateLimiter;
+use super::rewrite::{HeaderRewriter, RewriteContext};
+use super::webhook::{WebhookOutcome, WebhookVerifier};
+use crate::config::settings::{AppSettings, DEFAULT_TUNNEL};
+use crate::logging::access::AccessEntry;
+use crate::metrics::{route_key, Outcome, Queue, StatsSnapshot};
+use crate::protocol::http::{HttpMessage, ProxyInfo};
 use crate::protocol::messages::{HttpPayload, MessagePayload};
 use crate::protocol::tunnel::TunnelMessage;
-use crate::{local_log, utils::http::get_status_description, AppState, DashboardEvent};
+use crate::telemetry;
+use crate::utils::http::{get_status_description, header_value, remove_header};
+use crate::{local_log, AppState, DashboardEvent};
 
 /// HTTP proxy forwarder that forwards requests to a local server
 pub struct ProxyForwarder {
-    local_client: LocalServerClient,
+    pipeline: Arc<RwLock<Arc<Pipeline>>>,
     app_state: Arc<AppState>,
-    stats: Arc<RwLock<ProxyStats>>,
+    pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
+}
+
+/// Local client and request rules built from one settings snapshot
+struct Pipeline {
+    settings: Arc<AppSettings>,
+    local_client: LocalServerClient,
+    access_control: AccessControl,
+    rate_limiter: RateLimiter,
+    auth_gate: AuthGate,
+    webhook_verifier: WebhookVerifier,
+    header_rewriter: HeaderRewriter,
+    forwarded_headers: ForwardedHeaders,
+}
+
+impl Pipeline {
+    fn new(settings: Arc<AppSettings>) -> Result<Self> {
+        Ok(Self {
+            local_client: LocalServerClient::new(
+                settings.local_server.url.clone(),
+                settings.local_server.timeout,
+                settings.local_server.verify_ssl,
+                &settings.local_server.http_version,
+                &settings.local_server.tls,
+                &settings.local_server.files,
+                &settings.local_server.mock,
+            )?,
+            access_control: AccessControl::from_config(&settings.access_control)?,
+            rate_limiter: RateLimiter::from_config(&settings.rate_limit)?,
+            auth_gate: AuthGate::from_config(&settings.auth)?,
+            webhook_verifier: WebhookVerifier::from_config(&settings.webhooks)?,
+            header_rewriter: HeaderRewriter::from_config(&settings.headers)?,
+            forwarded_headers: ForwardedHeaders::from_config(
+                &settings.forwarded,
+                &settings.local_server.url,
+                &settings.websocket.url,
+            ),
+            settings,
+        })
+    }
 }
 
-/// Proxy statistics
-#[derive(Debug, Default, Clone)]
-pub struct ProxyStats {
-    pub requests_processed: u64,
-    pub requests_successful: u64,
-    pub requests_failed: u64,
-    pub bytes_forwarded: u64,
-    pub average_response_time_ms: f64,
-    pub active_requests: u64,
+/// Request awaiting its response, used for metrics and the access log
+struct PendingRequest {
+    method: String,
+    path: String,
+    started: Instant,
+    span: Span,
+    cloud_request_id: String,
+    client_ip: String,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    upstream_latency: Option<Duration>,
+}
+
+/// HTTP request extracted from a tunnel message, ready to be forwarded
+#[derive(Debug, Clone)]
+pub struct ForwardRequest {
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<Vec<u8>>,
+    pub cloud_request_id: String,
+    pub proxy_info: ProxyInfo,
+}
+
+impl ForwardRequest {
+    /// Extract a forwardable request from an HTTP message
+    pub fn from_message(message: &HttpMessage) -> Option<Self> {
+        let (method, url, headers, cloud_request_id) = message.extract_request_info_with_id()?;
+        let body = match &message.message.payload {
+            MessagePayload::Http(HttpPayload::Request { body, .. }) => body.clone(),
+            _ => None,
+        };
+
+        Some(Self {
+            request_id: message.request_id().to_string(),
+            method,
+            url,
+            headers,
+            body,
+            cloud_request_id,
+            proxy_info: message.envelope.proxy_info.clone(),
+        })
+    }
 }
 
 impl ProxyForwarder {
     /// Create a new proxy forwarder
     pub fn new(app_state: Arc<AppState>) -> Result<Self> {
-        let local_client = LocalServerClient::new(
-            app_state.settings.local_server.url.clone(),
-            app_state.settings.local_server.timeout,
-            app_state.settings.local_server.verify_ssl,
-            &app_state.settings.local_server.http_version,
-        )?;
+        let pipeline = Pipeline::new(app_state.settings())?;
 
         Ok(Self {
-            local_client,
+            pipeline: Arc::new(RwLock::new(Arc::new(pipeline))),
             app_state,
-            stats: Arc::new(RwLock::new(ProxyStats::default())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// Pipeline for the current settings, rebuilt after a config reload
+    fn pipeline(&self) -> Arc<Pipeline> {
+        let settings = self.app_state.settings();
+        let current = self
+            .pipeline
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if Arc::ptr_eq(&current.settings, &settings) {
+            return current;
+        }
+
+        match Pipeline::new(settings) {
+            Ok(pipeline) => {
+                let pipeline = Arc::new(pipeline);
+                *self.pipeline.write().unwrap_or_else(|e| e.into_inner()) = pipeline.clone();
+                pipeline
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to apply reloaded settings, keeping the previous rules: {}",
+                    e
+                );
+                current
+            }
+        }
+    }
+
     /// Main forwarder run loop
     #[instrument(skip(self, message_rx))]
     pub async fn run(&self, mut message_rx: mpsc::UnboundedReceiver<HttpMessage>) -> Result<()> {
         local_log!("HTTP proxy forwarder started");
 
         while let Some(message) = message_rx.recv().await {
-            // Extract HTTP request information from the message
-            if let Some((method, url, headers, cloud_request_id)) =
-                message.extract_request_info_with_id()
-            {
-                let body = match &message.message.payload {
-                    MessagePayload::Http(HttpPayload::Request { body, .. }) => body.clone(),
-                    _ => None,
-                };
+            self.app_state.metrics.queue_pop(Queue::Proxy);
 
+            // Extract HTTP request information from the message
+            if let Some(request) = ForwardRequest::from_message(&message) {
                 // Process request in the background to avoid blocking
                 let forwarder = self.clone();
-                let request_id = message.request_id().to_string();
                 tokio::spawn(async move {
-                    if let Err(e) = forwarder
-                        .handle_http_request(
-                            request_id,
-                            method,
-                            url,
-                            headers,
-                            body,
-                            cloud_request_id,
-                        )
-                        .await
-                    {
+                    if let Err(e) = forwarder.handle_http_request(request).await {
                         error!("Failed to handle HTTP request: {}", e);
                     }
                 });
@@ -86,94 +180,279 @@ impl ProxyForwarder {
         Ok(())
     }
 
-    /// Handle individual HTTP request
-    #[instrument(skip(self, headers, body))]
-    async fn handle_http_request(
-        &self,
-        request_id: String,
-        method: String,
-        url: String,
-        headers: std::collections::HashMap<String, String>,
-        body: Option<Vec<u8>>,
-        cloud_request_id: String,
-    ) -> Result<()> {
+    /// Handle individual HTTP request inside its server span
+    async fn handle_http_request(&self, request: ForwardRequest) -> Result<()> {
+        let route = self
+            .extract_path_from_url(&request.url)
+            .map(|path| route_key(&path))
+            .unwrap_or_default();
+        let span = telemetry::request_span(
+            &request.method,
+            &route,
+            &request.request_id,
+            &request.cloud_request_id,
+            &request.headers,
+        );
+
+        self.process_request(request, span.clone())
+            .instrument(span)
+            .await
+    }
+
+    /// Run the request through the gates and forward it to the local server
+    async fn process_request(&self, request: ForwardRequest, span: Span) -> Result<()> {
         let start_time = std::time::Instant::now();
+        // One pipeline per request so a reload never mixes old and new rules
+        let pipeline = self.pipeline();
+        let ForwardRequest {
+            request_id,
+            method,
+            url,
+            mut headers,
+            body,
+            cloud_request_id,
+            proxy_info,
+        } = request;
+
+        // Extract path from URL and resolve dot segments once, so every gate
+        // and the local server agree on it; only the redacted form is shown
+        let raw_path = self.extract_path_from_url(&url)?;
+        let path = super::normalize_path(&raw_path);
+        let shown_path = self
+            .app_state
+            .redactor()
+            .url(path.as_deref().unwrap_or(&raw_path));
+
+        let client_ip = proxy_info
+            .client_addr()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        // Start metrics for the request; finished when its response is sent
+        let request_bytes = body.as_ref().map(|b| b.len()).unwrap_or(0);
+        self.app_state.tunnel_metrics.request_started(request_bytes);
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                request_id.clone(),
+                PendingRequest {
+                    method: method.clone(),
+                    path: shown_path.clone(),
+                    started: Instant::now(),
+                    span,
+                    cloud_request_id: cloud_request_id.clone(),
+                    client_ip: client_ip.clone(),
+                    referer: header_value(&headers, "referer").map(str::to_string),
+                    user_agent: header_value(&headers, "user-agent").map(str::to_string),
+                    upstream_latency: None,
+                },
+            );
+
+        // Record the request for the dashboard inspector
+        self.app_state.inspector.begin(
+            &request_id,
+            &method,
+            &shown_path,
+            &client_ip,
+            &self.app_state.redactor().headers(&headers),
+            request_bytes,
+        );
+        if pipeline.settings.tunnel_name != DEFAULT_TUNNEL {
+            self.app_state.inspector.tag(
+                &request_id,
+                format!("tunnel:{}", pipeline.settings.tunnel_name),
+            );
+        }
 
-        // Update active request count
+        // Refuse new requests while draining for a restart or upgrade
+        if self.app_state.is_draining() {
+            return self
+                .handle_draining(request_id, method, shown_path, cloud_request_id)
+                .await;
+        }
+
+        let Some(path) = path else {
+            return self
+                .handle_invalid_path(request_id, method, shown_path, cloud_request_id)
+                .await;
+        };
+
+        // Enforce IP allow/deny lists and origin checks before touching the local server
+        if let Err(denied) = pipeline
+            .access_control
+            .check(proxy_info.client_addr(), &headers)
         {
-            let mut stats = self.stats.write().await;
-            stats.active_requests += 1;
+            return self
+                .handle_access_denied(
+                    request_id,
+                    method,
+                    shown_path,
+                    denied.to_string(),
+                    cloud_request_id,
+                )
+                .await;
         }
 
-        // Extract path from URL
-        let path = self.extract_path_from_url(&url)?;
+        // Throttle clients that exceed the configured request rate
+        if let Err(retry_after) = pipeline.rate_limiter.check(&client_ip) {
+            return self
+                .handle_rate_limited(
+                    request_id,
+                    method,
+                    shown_path,
+                    retry_after,
+                    cloud_request_id,
+                )
+                .await;
+        }
+
+        // Run the authentication gate configured for this route
+        match pipeline
+            .auth_gate
+            .evaluate(&method, &path, &mut headers, body.as_deref())
+            .await
+        {
+            AuthDecision::Allow { user } => {
+                if let Some(user) = user {
+                    debug!("Request authenticated as {}", user);
+                }
+            }
+            AuthDecision::Reject { reason, response } => {
+                return self
+                    .handle_auth_rejected(
+                        request_id,
+                        method,
+                        shown_path,
+                        reason,
+                        response,
+                        cloud_request_id,
+                    )
+                    .await;
+            }
+            AuthDecision::Respond(response) => {
+                return self
+                    .send_response(request_id, response, cloud_request_id, Outcome::Success)
+                    .await;
+            }
+        }
+
+        // Verify webhook signatures on the raw request body
+        match pipeline
+            .webhook_verifier
+            .verify(&path, &headers, body.as_deref())
+        {
+            WebhookOutcome::NotApplicable => {}
+            WebhookOutcome::Verified(provider) => {
+                debug!("Webhook signature verified ({})", provider);
+                self.app_state
+                    .inspector
+                    .tag(&request_id, format!("webhook:{provider}"));
+            }
+            WebhookOutcome::Failed { provider, reason } => {
+                return self
+                    .handle_webhook_rejected(
+                        request_id,
+                        method,
+                        shown_path,
+                        provider,
+                        reason,
+                        cloud_request_id,
+                    )
+                    .await;
+            }
+        }
 
         local_log!(
             "Forwarding request to local server: {} {} (ID: {}, Cloud RequestID: {})",
             method,
-            path,
+            shown_path,
             request_id,
             cloud_request_id
         );
 
         debug!(
             "Processing request: {} {} (ID: {}, Cloud RequestID: {})",
-            method, path, request_id, cloud_request_id
+            method, shown_path, request_id, cloud_request_id
         );
 
-        // Debug log the body content and headers
+        // Debug log the body content and headers with secrets masked
         if let Some(ref body_data) = body {
             debug!("Request body size: {} bytes", body_data.len());
-            if let Ok(body_str) = std::str::from_utf8(body_data) {
-                debug!("Request body content: {}", body_str);
-            } else {
-                debug!("Request body contains binary data");
-            }
+            debug!(
+                "Request body content: {}",
+                self.app_state
+                    .redactor()
+                    .body(body_data, header_value(&headers, "content-type"))
+            );
         } else {
             debug!("Request has no body");
         }
-        debug!("Request headers: {:?}", headers);
-
-        // Add X-Request-ID header with the cloud request ID for tracking (if not already present)
-        let mut headers_with_request_id = headers.clone();
+        debug!(
+            "Request headers: {:?}",
+            self.app_state.redactor().headers(&headers)
+        );
 
-        // Only add X-Request-ID if it doesn't already exist
-        if !headers_with_request_id.contains_key("x-request-id")
-            && !headers_with_request_id.contains_key("X-Request-ID")
-        {
-            headers_with_request_id.insert("X-Request-ID".to_string(), cloud_request_id.clone());
-        }
+        // Drop the public Host so the local server sees its own unless a rule sets one
+        let host = remove_header(&mut headers, "host").unwrap_or_default();
+
+        // Tell the local app who the original client and public host were
+        pipeline
+            .forwarded_headers
+            .apply_request(&mut headers, &proxy_info, &host);
+
+        // Apply configured header rewriting rules
+        let rewrite_context = RewriteContext {
+            request_id: cloud_request_id.clone(),
+            client_ip,
+            tunnel_id: pipeline.settings.tunnel_name.clone(),
+            method: method.clone(),
+            path: path.clone(),
+            host,
+        };
+        pipeline
+            .header_rewriter
+            .rewrite_request(&mut headers, &rewrite_context);
 
-        // Only add X-Forwarded-By if it doesn't already exist
-        if !headers_with_request_id.contains_key("x-forwarded-by")
-            && !headers_with_request_id.contains_key("X-Forwarded-By")
-        {
-            headers_with_request_id.insert("X-Forwarded-By".to_string(), "pori-proxy".to_string());
+        // Continue the trace in the local app
+        if pipeline.settings.telemetry.propagate {
+            telemetry::inject(&Span::current(), &mut headers);
         }
 
         // Notify dashboard
         let _ = self
             .app_state
             .dashboard_tx
-            .send(DashboardEvent::RequestForwarded(format!("{method} {path}")));
+            .send(DashboardEvent::RequestForwarded(format!(
+                "{method} {shown_path}"
+            )));
 
         // Forward request to local server with timeout handling
+        let upstream_start = Instant::now();
         let result = tokio::time::timeout(
-            self.app_state.settings.local_server.timeout,
-            self.local_client
-                .forward_request(&method, &path, headers_with_request_id, body),
+            pipeline.settings.local_server.timeout,
+            pipeline
+                .local_client
+                .forward_request(&method, &path, headers, body),
         )
         .await;
 
+        self.record_upstream_latency(&request_id, upstream_start.elapsed());
         let duration = start_time.elapsed();
 
         match result {
-            Ok(Ok(response)) => {
+            Ok(Ok(mut response)) => {
                 // Successfully received response from a local server
+                pipeline
+                    .forwarded_headers
+                    .rewrite_response(&mut response.headers, &rewrite_context.host);
+                pipeline
+                    .header_rewriter
+                    .rewrite_response(&mut response.headers, &rewrite_context);
                 self.handle_successful_response(
                     request_id,
                     method,
-                    path,
+                    shown_path,
                     response,
                     duration,
                     cloud_request_id,
@@ -192,7 +471,7 @@ impl ProxyForwarder {
                     self.handle_connection_error(
                         request_id,
                         method,
-                        path,
+                        shown_path,
                         e,
                         duration,
                         cloud_request_id,
@@ -203,7 +482,7 @@ impl ProxyForwarder {
                     self.handle_server_error(
                         request_id,
                         method,
-                        path,
+                        shown_path,
                         e,
                         duration,
                         cloud_request_id,
@@ -213,8 +492,14 @@ impl ProxyForwarder {
             }
             Err(_) => {
                 // Request timed out
-                self.handle_timeout_error(request_id, method, path, duration, cloud_request_id)
-                    .await?;
+                self.handle_timeout_error(
+                    request_id,
+                    method,
+                    shown_path,
+                    duration,
+                    cloud_request_id,
+                )
+                .await?;
             }
         }
 
@@ -231,24 +516,6 @@ impl ProxyForwarder {
         duration: std::time::Duration,
         cloud_request_id: String,
     ) -> Result<()> {
-        // Update stats
-        {
-            let mut stats = self.stats.write().await;
-            stats.requests_processed += 1;
-            stats.requests_successful += 1;
-            stats.active_requests -= 1;
-
-            if let Some(ref body) = response.body {
-                stats.bytes_forwarded += body.len() as u64;
-            }
-
-            // Update average response time
-            let duration_ms = duration.as_millis() as f64;
-            let count = stats.requests_processed as f64;
-            let current_avg = stats.average_response_time_ms;
-            stats.average_response_time_ms = (current_avg * (count - 1.0) + duration_ms) / count;
-        }
-
         // Store status before sending
         let status = response.status;
         let body_size = response.body.as_ref().map(|b| b.len()).unwrap_or(0);
@@ -264,7 +531,7 @@ impl ProxyForwarder {
         );
 
         // Send response back via WebSocket
-        self.send_response(request_id, response, cloud_request_id)
+        self.send_response(request_id, response, cloud_request_id, Outcome::Success)
             .await?;
 
         // Notify dashboard of successful response
@@ -286,14 +553,8 @@ impl ProxyForwarder {
         duration: std::time::Duration,
         cloud_request_id: String,
     ) -> Result<()> {
-        // Update error stats
-        {
-            let mut stats = self.stats.write().await;
-            stats.requests_processed += 1;
-            stats.requests_failed += 1;
-            stats.active_requests -= 1;
-        }
-
+        // Client errors include the local URL, query string and all
+        let error = self.app_state.redactor().url(&error.to_string());
         error!(
             "Local server error: {} {} -> Error: {} ({:?})",
             method, path, error, duration
@@ -327,6 +588,218 @@ impl ProxyForwarder {
         Ok(())
     }
 
+    /// Handle a request rejected by access control
+    async fn handle_access_denied(
+        &self,
+        request_id: String,
+        method: String,
+        path: String,
+        reason: String,
+        cloud_request_id: String,
+    ) -> Result<()> {
+        warn!("Access denied: {} {} -> {}", method, path, reason);
+
+        local_log!(
+            "Sending access denied response: {} {} -> 403 Forbidden (ID: {})",
+            method,
+            path,
+            request_id
+        );
+
+        self.send_error_response(
+            request_id,
+            403,
+            "Forbidden",
+            "Access to this resource is forbidden",
+            cloud_request_id,
+        )
+        .await?;
+
+        // Notify dashboard
+        let _ = self
+            .app_state
+            .dashboard_tx
+            .send(DashboardEvent::AccessDenied(format!(
+                "{method} {path}: {reason}"
+            )));
+
+        Ok(())
+    }
+
+    /// Handle a request whose path cannot be normalized safely
+    async fn handle_invalid_path(
+        &self,
+        request_id: String,
+        method: String,
+        path: String,
+        cloud_request_id: String,
+    ) -> Result<()> {
+        warn!("Invalid request path: {} {}", method, path);
+
+        self.send_error_response(
+            request_id,
+            400,
+            "Bad Request",
+            "Encoded slashes and backslashes are not allowed in the path",
+            cloud_request_id,
+        )
+        .await?;
+
+        // Notify dashboard
+        let _ = self
+            .app_state
+            .dashboard_tx
+            .send(DashboardEvent::AccessDenied(format!(
+                "{method} {path}: invalid path"
+            )));
+
+        Ok(())
+    }
+
+    /// Handle a request refused while the client is draining
+    async fn handle_draining(
+        &self,
+        request_id: String,
+        method: String,
+        path: String,
+        cloud_request_id: String,
+    ) -> Result<()> {
+        info!("Draining, refusing {} {}", method, path);
+
+        let response = LocalServerResponse {
+            status: 503,
+            status_text: "Service Unavailable".to_string(),
+            headers: HashMap::from([
+                (
+                    "content-type".to_string(),
+                    "text/plain; charset=utf-8".to_string(),
+                ),
+                ("retry-after".to_string(), "5".to_string()),
+            ]),
+            body: Some(b"Tunnel is draining, please retry shortly".to_vec()),
+        };
+        self.send_response(request_id, response, cloud_request_id, Outcome::Failure)
+            .await
+    }
+
+    /// Handle a request rejected by the rate limiter
+    async fn handle_rate_limited(
+        &self,
+        request_id: String,
+        method: String,
+        path: String,
+        retry_after: Duration,
+        cloud_request_id: String,
+    ) -> Result<()> {
+        let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        warn!(
+            "Rate limit exceeded: {} {} -> retry after {}s",
+            method, path, retry_after
+        );
+
+        let body = "Too many requests, please slow down".to_string();
+        let response = LocalServerResponse {
+            status: 429,
+            status_text: "Too Many Requests".to_string(),
+            headers: HashMap::from([
+                (
+                    "content-type".to_string(),
+                    "text/plain; charset=utf-8".to_string(),
+                ),
+                ("retry-after".to_string(), retry_after.to_string()),
+            ]),
+            body: Some(body.into_bytes()),
+        };
+        self.send_response(request_id, response, cloud_request_id, Outcome::Failure)
+            .await?;
+
+        // Notify dashboard
+        let _ = self
+            .app_state
+            .dashboard_tx
+            .send(DashboardEvent::AccessDenied(format!(
+                "{method} {path}: rate limit exceeded"
+            )));
+
+        Ok(())
+    }
+
+    /// Handle a request rejected by the authentication gate
+    async fn handle_auth_rejected(
+        &self,
+        request_id: String,
+        method: String,
+        path: String,
+        reason: String,
+        response: LocalServerResponse,
+        cloud_request_id: String,
+    ) -> Result<()> {
+        warn!("Authentication failed: {} {} -> {}", method, path, reason);
+
+        local_log!(
+            "Sending authentication challenge: {} {} -> {} (ID: {})",
+            method,
+            path,
+            get_status_description(response.status),
+            request_id
+        );
+
+        self.send_response(request_id, response, cloud_request_id, Outcome::Failure)
+            .await?;
+
+        // Notify dashboard
+        let _ = self
+            .app_state
+            .dashboard_tx
+            .send(DashboardEvent::AuthenticationFailed(format!(
+                "{method} {path}: {reason}"
+            )));
+
+        Ok(())
+    }
+
+    /// Handle a webhook request with a missing or invalid signature
+    async fn handle_webhook_rejected(
+        &self,
+        request_id: String,
+        method: String,
+        path: String,
+        provider: &'static str,
+        reason: String,
+        cloud_request_id: String,
+    ) -> Result<()> {
+        warn!(
+            "Webhook signature rejected ({}): {} {} -> {}",
+            provider, method, path, reason
+        );
+
+        self.app_state
+            .inspector
+            .tag(&request_id, format!("webhook:{provider}"));
+        self.app_state
+            .inspector
+            .tag(&request_id, "webhook:invalid-signature");
+
+        self.send_error_response(
+            request_id,
+            401,
+            "Unauthorized",
+            "Webhook signature verification failed",
+            cloud_request_id,
+        )
+        .await?;
+
+        // Notify dashboard
+        let _ = self
+            .app_state
+            .dashboard_tx
+            .send(DashboardEvent::AuthenticationFailed(format!(
+                "{method} {path}: invalid {provider} webhook signature ({reason})"
+            )));
+
+        Ok(())
+    }
+
     /// Handle timeout when the local server doesn't respond
     async fn handle_timeout_error(
         &self,
@@ -336,14 +809,6 @@ impl ProxyForwarder {
         duration: std::time::Duration,
         cloud_request_id: String,
     ) -> Result<()> {
-        // Update error stats
-        {
-            let mut stats = self.stats.write().await;
-            stats.requests_processed += 1;
-            stats.requests_failed += 1;
-            stats.active_requests -= 1;
-        }
-
         error!(
             "Request timeout: {} {} -> Timeout after {:?}",
             method, path, duration
@@ -363,7 +828,7 @@ impl ProxyForwarder {
             "Gateway Timeout",
             &format!(
                 "Local server did not respond within {:?}",
-                self.app_state.settings.local_server.timeout
+                self.app_state.settings().local_server.timeout
             ),
             cloud_request_id,
         )
@@ -386,6 +851,7 @@ impl ProxyForwarder {
         request_id: String,
         response: LocalServerResponse,
         cloud_request_id: String,
+        outcome: Outcome,
     ) -> Result<()> {
         let body_size = response.body.as_ref().map(|b| b.len()).unwrap_or(0);
         let status_description = get_status_description(response.status);
@@ -398,11 +864,17 @@ impl ProxyForwarder {
         );
 
         // Log response headers for debugging
-        debug!("Response headers: {:?}", response.headers);
+        debug!(
+            "Response headers: {:?}",
+            self.app_state.redactor().headers(&response.headers)
+        );
+
+        self.complete_inspection(&request_id, response.status, body_size);
+        self.finish_request(&request_id, response.status, body_size, outcome);
 
         let tunnel_message = TunnelMessage::http_response_with_id(
-            "default-tunnel".to_string(),
-            "default-client".to_string(),
+            self.app_state.tunnel_id(),
+            self.app_state.client_id().to_string(),
             response.status,
             response.status_text,
             response.headers,
@@ -411,7 +883,9 @@ impl ProxyForwarder {
         );
 
         // Send via WebSocket to a proxy server
+        self.app_state.metrics.queue_push(Queue::Websocket);
         if let Err(e) = self.app_state.websocket_tx.send(tunnel_message) {
+            self.app_state.metrics.queue_pop(Queue::Websocket);
             warn!("Failed to send a response to WebSocket: {}", e);
             return Err(anyhow::anyhow!(
                 "Failed to send a response to the proxy server: {}",
@@ -426,6 +900,73 @@ impl ProxyForwarder {
         Ok(())
     }
 
+    /// Complete the inspector entry for a request and publish it to the dashboard
+    fn complete_inspection(&self, request_id: &str, status: u16, response_bytes: usize) {
+        if let Some(entry) = self
+            .app_state
+            .inspector
+            .complete(request_id, status, response_bytes)
+        {
+            let _ = self
+                .app_state
+                .dashboard_tx
+                .send(DashboardEvent::RequestCompleted(Box::new(entry)));
+        }
+    }
+
+    /// Record metrics for a request whose response is being sent
+    fn finish_request(
+        &self,
+        request_id: &str,
+        status: u16,
+        response_bytes: usize,
+        outcome: Outcome,
+    ) {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(request_id);
+
+        if let Some(pending) = pending {
+            let duration = pending.started.elapsed();
+            telemetry::record_status(&pending.span, status);
+            self.app_state.tunnel_metrics.request_finished(
+                &pending.method,
+                &pending.path,
+                status,
+                duration,
+                response_bytes,
+                outcome,
+            );
+            self.app_state.access_log.record(AccessEntry {
+                timestamp: chrono::Local::now(),
+                request_id: pending.cloud_request_id,
+                client_ip: pending.client_ip,
+                method: pending.method,
+                path: pending.path,
+                status,
+                bytes: response_bytes,
+                upstream_latency: pending.upstream_latency,
+                duration,
+                referer: pending.referer,
+                user_agent: pending.user_agent,
+            });
+        }
+    }
+
+    /// Remember how long the local server took to answer a request
+    fn record_upstream_latency(&self, request_id: &str, latency: Duration) {
+        if let Some(pending) = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(request_id)
+        {
+            pending.upstream_latency = Some(latency);
+        }
+    }
+
     /// Send error response back via WebSocket
     async fn send_error_response(
         &self,
@@ -473,9 +1014,12 @@ impl ProxyForwarder {
 
         let body = Some(html_body.as_bytes().to_vec());
 
+        self.complete_inspection(&request_id, status, html_body.len());
+        self.finish_request(&request_id, status, html_body.len(), Outcome::Failure);
+
         let tunnel_message = TunnelMessage::http_response_with_id(
-            "default-tunnel".to_string(),
-            "default-client".to_string(),
+            self.app_state.tunnel_id(),
+            self.app_state.client_id().to_string(),
             status,
             status_text.to_string(),
             headers,
@@ -483,7 +1027,9 @@ impl ProxyForwarder {
             cloud_request_id,
         );
 
+        self.app_state.metrics.queue_push(Queue::Websocket);
         if let Err(e) = self.app_state.websocket_tx.send(tunnel_message) {
+            self.app_state.metrics.queue_pop(Queue::Websocket);
             warn!("Failed to send an error response to WebSocket: {}", e);
             return Err(anyhow::anyhow!(
                 "Failed to send an error response to the proxy server: {}",
@@ -508,14 +1054,8 @@ impl ProxyForwarder {
         duration: std::time::Duration,
         cloud_request_id: String,
     ) -> Result<()> {
-        // Update error stats
-        {
-            let mut stats = self.stats.write().await;
-            stats.requests_processed += 1;
-            stats.requests_failed += 1;
-            stats.active_requests -= 1;
-        }
-
+        // Client errors include the local URL, query string and all
+        let error = self.app_state.redactor().url(&error.to_string());
         error!(
             "Local server connection failed: {} {} -> Error: {} ({:?})",
             method, path, error, duration
@@ -568,14 +1108,14 @@ impl ProxyForwarder {
         }
     }
 
-    /// Get proxy statistics
-    pub async fn get_stats(&self) -> ProxyStats {
-        self.stats.read().await.clone()
+    /// Get this tunnel's proxy statistics
+    pub fn get_stats(&self) -> StatsSnapshot {
+        self.app_state.tunnel_metrics.snapshot()
     }
 
     /// Get detailed proxy statistics with additional metrics
-    pub async fn get_detailed_stats(&self) -> DetailedProxyStats {
-        let stats = self.stats.read().await;
+    pub fn get_detailed_stats(&self) -> DetailedProxyStats {
+        let stats = self.get_stats();
         let success_rate = if stats.requests_processed > 0 {
             (stats.requests_successful as f64 / stats.requests_processed as f64) * 100.0
         } else {
@@ -583,10 +1123,10 @@ impl ProxyForwarder {
         };
 
         DetailedProxyStats {
-            basic_stats: stats.clone(),
+            basic_stats: stats,
             success_rate_percentage: success_rate,
-            local_server_url: self.app_state.settings.local_server.url.to_string(),
-            timeout_duration: self.app_state.settings.local_server.timeout,
+            local_server_url: self.app_state.settings().local_server.url.to_string(),
+            timeout_duration: self.app_state.settings().local_server.timeout,
         }
     }
 }
@@ -594,7 +1134,7 @@ impl ProxyForwarder {
 /// Detailed proxy statistics
 #[derive(Debug, Clone)]
 pub struct DetailedProxyStats {
-    pub basic_stats: ProxyStats,
+    pub basic_stats: StatsSnapshot,
     pub success_rate_percentage: f64,
     pub local_server_url: String,
     pub timeout_duration: std::time::Duration,
@@ -603,9 +1143,9 @@ pub struct DetailedProxyStats {
 impl Clone for ProxyForwarder {
     fn clone(&self) -> Self {
         Self {
-            local_client: self.local_client.clone(),
+            pipeline: self.pipeline.clone(),
             app_state: self.app_state.clone(),
-            stats: self.stats.clone(),
+            pending: self.pending.clone(),
         }
     }
 }
@@ -616,7 +1156,13 @@ mod tests {
     use crate::config::{cli::CliArgs, settings::AppSettings};
 
     fn create_test_app_state() -> Arc<AppState> {
-        let args = CliArgs {
+        let settings = AppSettings::from_cli(test_args()).unwrap();
+        let (app_state, _) = AppState::new(settings);
+        Arc::new(app_state)
+    }
+
+    fn test_args() -> CliArgs {
+        CliArgs {
             url: Some("ws://localhost:7616".parse().unwrap()),
             token: Some("test-token".to_string()),
             yml: None,
@@ -629,13 +1175,12 @@ mod tests {
             timeout: 30,
             max_reconnects: 0,
             verify_ssl: false,
+            no_verify_ssl: false,
             max_connections: 10,
             http_version: "auto".to_string(),
-        };
-
-        let settings = AppSettings::from_cli(args).unwrap();
-        let (app_state, _) = AppState::new(settings);
-        Arc::new(app_state)
+            subdomain: None,
+            upstream: None,
+        }
     }
 
     #[test]
@@ -658,14 +1203,65 @@ mod tests {
         assert_eq!(full_url_result.unwrap(), "/api/test?param=value");
     }
 
-    #[tokio::test]
-    async fn test_stats_initialization() {
+    #[test]
+    fn test_stats_initialization() {
         let app_state = create_test_app_state();
         let forwarder = ProxyForwarder::new(app_state).unwrap();
 
-        let stats = forwarder.get_stats().await;
+        let stats = forwarder.get_stats();
         assert_eq!(stats.requests_processed, 0);
         assert_eq!(stats.requests_successful, 0);
         assert_eq!(stats.requests_failed, 0);
     }
+
+    #[tokio::test]
+    async fn test_request_through_mock_upstream() {
+        let args = CliArgs {
+            upstream: Some("mock:".to_string()),
+            ..test_args()
+        };
+        let mut settings = AppSettings::from_cli(args).unwrap();
+        settings.local_server.mock = vec![crate::config::settings::MockRouteConfig {
+            method: Some("GET".to_string()),
+            path: "/health".to_string(),
+            body: "ok".to_string(),
+            ..Default::default()
+        }];
+        let (app_state, mut channels) = AppState::new(settings);
+        let forwarder = ProxyForwarder::new(Arc::new(app_state)).unwrap();
+
+        let request = |path: &str| ForwardRequest {
+            request_id: format!("req-{path}"),
+            method: "GET".to_string(),
+            url: path.to_string(),
+            headers: HashMap::new(),
+            body: None,
+            cloud_request_id: format!("cloud-{path}"),
+            proxy_info: ProxyInfo {
+                proxy_id: "relay".to_string(),
+                client_ip: "203.0.113.7".to_string(),
+                user_agent: None,
+                forwarded_for: Vec::new(),
+                via: Vec::new(),
+            },
+        };
+
+        for (path, expected_status, expected_body) in [
+            ("/health", 200, Some(b"ok".to_vec())),
+            ("/missing", 404, None),
+        ] {
+            forwarder.handle_http_request(request(path)).await.unwrap();
+            let message = channels.websocket_rx.recv().await.unwrap();
+            let MessagePayload::Http(HttpPayload::Response { status, body, .. }) =
+                message.message.payload
+            else {
+                panic!("expected an HTTP response");
+            };
+            assert_eq!(status, expected_status);
+            if expected_body.is_some() {
+                assert_eq!(body, expected_body);
+            }
+        }
+        assert_eq!(forwarder.get_stats().requests_processed, 2);
+    }
 }
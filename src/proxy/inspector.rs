@@ -0,0 +1,191 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Default number of requests kept for inspection
+const DEFAULT_CAPACITY: usize = 200;
+
+/// Keeps a bounded history of tunneled requests for the dashboard
+pub struct RequestInspector {
+    entries: Mutex<VecDeque<InspectedRequest>>,
+    capacity: usize,
+}
+
+/// A single inspected request
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedRequest {
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub client_ip: String,
+    pub request_headers: HashMap<String, String>,
+    pub request_bytes: usize,
+    pub started_at: String,
+    pub status: Option<u16>,
+    pub response_bytes: Option<usize>,
+    pub duration_ms: Option<u64>,
+    pub tags: Vec<String>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl RequestInspector {
+    /// Create a new inspector with the default capacity
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create a new inspector keeping at most `capacity` requests
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Record the start of a request
+    pub fn begin(
+        &self,
+        request_id: &str,
+        method: &str,
+        path: &str,
+        client_ip: &str,
+        headers: &HashMap<String, String>,
+        request_bytes: usize,
+    ) {
+        let entry = InspectedRequest {
+            request_id: request_id.to_string(),
+            method: method.to_string(),
+            path: path.to_string(),
+            client_ip: client_ip.to_string(),
+            request_headers: headers.clone(),
+            request_bytes,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            status: None,
+            response_bytes: None,
+            duration_ms: None,
+            tags: Vec::new(),
+            started: Instant::now(),
+        };
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Attach a tag to a request (e.g. `webhook:invalid-signature`)
+    pub fn tag(&self, request_id: &str, tag: impl Into<String>) {
+        let tag = tag.into();
+        self.update(request_id, |entry| {
+            if !entry.tags.contains(&tag) {
+                entry.tags.push(tag);
+            }
+        });
+    }
+
+    /// Record the response sent for a request
+    pub fn complete(
+        &self,
+        request_id: &str,
+        status: u16,
+        response_bytes: usize,
+    ) -> Option<InspectedRequest> {
+        let mut completed = None;
+        self.update(request_id, |entry| {
+            entry.status = Some(status);
+            entry.response_bytes = Some(response_bytes);
+            entry.duration_ms = Some(entry.started.elapsed().as_millis() as u64);
+            completed = Some(entry.clone());
+        });
+        completed
+    }
+
+    /// Get a request by ID
+    pub fn get(&self, request_id: &str) -> Option<InspectedRequest> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .rev()
+            .find(|e| e.request_id == request_id)
+            .cloned()
+    }
+
+    /// Get the most recent requests, newest first
+    pub fn recent(&self, limit: usize) -> Vec<InspectedRequest> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// Remove all recorded requests, returning how many were dropped
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+
+    fn update<F>(&self, request_id: &str, updater: F)
+    where
+        F: FnOnce(&mut InspectedRequest),
+    {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries
+            .iter_mut()
+            .rev()
+            .find(|e| e.request_id == request_id)
+        {
+            updater(entry);
+        }
+    }
+}
+
+impl Default for RequestInspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_lifecycle() {
+        let inspector = RequestInspector::new();
+        inspector.begin("req-1", "POST", "/hook", "203.0.113.7", &HashMap::new(), 42);
+        inspector.tag("req-1", "webhook:github");
+        inspector.tag("req-1", "webhook:github");
+
+        let entry = inspector.complete("req-1", 401, 12).unwrap();
+        assert_eq!(entry.status, Some(401));
+        assert_eq!(entry.response_bytes, Some(12));
+        assert_eq!(entry.tags, vec!["webhook:github".to_string()]);
+        assert!(entry.duration_ms.is_some());
+
+        assert!(inspector.complete("missing", 200, 0).is_none());
+    }
+
+    #[test]
+    fn test_capacity_is_bounded() {
+        let inspector = RequestInspector::with_capacity(2);
+        for i in 0..3 {
+            inspector.begin(
+                &format!("req-{i}"),
+                "GET",
+                "/",
+                "unknown",
+                &HashMap::new(),
+                0,
+            );
+        }
+
+        let recent = inspector.recent(10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].request_id, "req-2");
+        assert!(inspector.get("req-0").is_none());
+    }
+}
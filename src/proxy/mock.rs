@@ -0,0 +1,195 @@
+//! Built-in upstreams that need no local server
+//!
+//! `echo:` answers every request with a JSON description of what reached
+//! Pori. `mock:` answers from the `local_server.mock` routes, in order, with
+//! their canned status, headers, body and latency.
+
+use anyhow::{bail, Result};
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::client::LocalServerResponse;
+use crate::config::settings::MockRouteConfig;
+use crate::utils::http::header_value;
+
+/// Answers requests from configured routes
+#[derive(Debug, Clone)]
+pub struct MockServer {
+    routes: Vec<MockRouteConfig>,
+}
+
+impl MockServer {
+    /// Check the routes' methods and statuses
+    pub fn from_config(routes: &[MockRouteConfig]) -> Result<Self> {
+        for route in routes {
+            if !(100..=999).contains(&route.status) {
+                bail!(
+                    "Mock route '{}' has invalid status {}",
+                    route.path,
+                    route.status
+                );
+            }
+            if let Some(method) = &route.method {
+                if hyper::Method::from_bytes(method.as_bytes()).is_err() {
+                    bail!("Mock route '{}' has invalid method '{method}'", route.path);
+                }
+            }
+            if !route.path.starts_with('/') && route.path != "*" {
+                bail!("Mock route path '{}' must start with '/'", route.path);
+            }
+        }
+        Ok(Self {
+            routes: routes.to_vec(),
+        })
+    }
+
+    /// Answer with the first matching route, or 404
+    pub async fn handle(&self, method: &str, path: &str) -> LocalServerResponse {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let route = self.routes.iter().find(|route| {
+            route
+                .method
+                .as_ref()
+                .is_none_or(|expected| expected.eq_ignore_ascii_case(method))
+                && match route.path.strip_suffix('*') {
+                    Some(prefix) => path.starts_with(prefix),
+                    None => path == route.path,
+                }
+        });
+
+        let Some(route) = route else {
+            return json_response(
+                404,
+                &json!({ "error": format!("No mock route matches {method} {path}") }),
+            );
+        };
+
+        if route.latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(route.latency_ms)).await;
+        }
+        LocalServerResponse {
+            status: route.status,
+            status_text: status_text(route.status),
+            headers: route.headers.clone(),
+            body: (!route.body.is_empty()).then(|| route.body.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// Describe the request as JSON
+pub fn echo(
+    method: &str,
+    path: &str,
+    headers: &HashMap<String, String>,
+    body: Option<&[u8]>,
+) -> LocalServerResponse {
+    let header = |name: &str| header_value(headers, name);
+    let forwarded_for: Vec<&str> = header("x-forwarded-for")
+        .map(|chain| chain.split(',').map(str::trim).collect())
+        .unwrap_or_default();
+
+    let (route, query) = path.split_once('?').unwrap_or((path, ""));
+    let query: Map<String, Value> = url::form_urlencoded::parse(query.as_bytes())
+        .map(|(key, value)| (key.into_owned(), Value::String(value.into_owned())))
+        .collect();
+    let headers: Map<String, Value> = headers
+        .iter()
+        .map(|(key, value)| (key.to_ascii_lowercase(), Value::String(value.clone())))
+        .collect();
+
+    let mut description = json!({
+        "method": method,
+        "path": route,
+        "query": query,
+        "headers": headers,
+        "body": Value::Null,
+        "client": {
+            "ip": forwarded_for.last(),
+            "forwarded_for": forwarded_for,
+            "proto": header("x-forwarded-proto"),
+            "host": header("x-forwarded-host"),
+            "user_agent": header("user-agent"),
+        },
+    });
+    if let Some(body) = body.filter(|body| !body.is_empty()) {
+        description["body_size"] = json!(body.len());
+        match std::str::from_utf8(body) {
+            Ok(text) => description["body"] = json!(text),
+            Err(_) => {
+                description["body_base64"] =
+                    json!(base64::engine::general_purpose::STANDARD.encode(body))
+            }
+        }
+    }
+    json_response(200, &description)
+}
+
+fn json_response(status: u16, value: &Value) -> LocalServerResponse {
+    let body = serde_json::to_vec_pretty(value).unwrap_or_default();
+    LocalServerResponse {
+        status,
+        status_text: status_text(status),
+        headers: HashMap::from([
+            ("content-type".to_string(), "application/json".to_string()),
+            ("content-length".to_string(), body.len().to_string()),
+        ]),
+        body: Some(body),
+    }
+}
+
+fn status_text(status: u16) -> String {
+    hyper::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_routes_and_echo() {
+        let routes = vec![
+            MockRouteConfig {
+                method: Some("POST".to_string()),
+                path: "/orders".to_string(),
+                status: 201,
+                body: r#"{"id":1}"#.to_string(),
+                ..MockRouteConfig::default()
+            },
+            MockRouteConfig {
+                path: "/static/*".to_string(),
+                status: 204,
+                ..MockRouteConfig::default()
+            },
+        ];
+        let server = MockServer::from_config(&routes).unwrap();
+
+        let created = server.handle("post", "/orders?dry=1").await;
+        assert_eq!(created.status, 201);
+        assert_eq!(created.body.unwrap(), br#"{"id":1}"#);
+        assert_eq!(server.handle("GET", "/orders").await.status, 404);
+        assert_eq!(server.handle("GET", "/static/app.js").await.status, 204);
+
+        let invalid = MockRouteConfig {
+            status: 42,
+            ..MockRouteConfig::default()
+        };
+        assert!(MockServer::from_config(&[invalid]).is_err());
+
+        let headers = HashMap::from([(
+            "X-Forwarded-For".to_string(),
+            "198.51.100.1, 203.0.113.7".to_string(),
+        )]);
+        let echoed = echo("PUT", "/items?page=2", &headers, Some(b"hello"));
+        let echoed: Value = serde_json::from_slice(&echoed.body.unwrap()).unwrap();
+        assert_eq!(echoed["path"], "/items");
+        assert_eq!(echoed["query"]["page"], "2");
+        assert_eq!(echoed["body"], "hello");
+        assert_eq!(echoed["client"]["ip"], "203.0.113.7");
+    }
+}
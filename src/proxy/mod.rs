@@ -1,6 +1,16 @@
+pub mod access;
+pub mod auth;
 pub mod client;
+pub mod files;
+pub mod forwarded;
 pub mod forwarder;
+pub mod inspector;
 pub mod messages;
+pub mod mock;
+pub mod ratelimit;
+pub mod rewrite;
+pub mod upstream;
+pub mod webhook;
 
 use anyhow::Result;
 use std::sync::Arc;
@@ -25,3 +35,99 @@ pub async fn run_proxy_forwarder(
     proxy_log!("Proxy forwarder stopped");
     Ok(())
 }
+
+/// Check whether a request path is covered by a route prefix
+pub(crate) fn path_matches(prefix: &str, path: &str) -> bool {
+    if prefix == "/" {
+        return true;
+    }
+    let prefix = prefix.trim_end_matches('/');
+    path == prefix
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Canonical form of a request path, as every gate and the local server see it
+///
+/// Percent-encoded unreserved characters are decoded, `.` and `..` segments
+/// resolved and repeated slashes collapsed, so `/public/%2e%2e/admin` becomes
+/// `/admin`. Encoded slashes and backslashes are refused (`None`), since
+/// upstreams disagree on what they mean.
+pub(crate) fn normalize_path(path: &str) -> Option<String> {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    if path.contains('\\') {
+        return None;
+    }
+
+    let mut decoded = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(pos) = rest.find('%') {
+        decoded.push_str(&rest[..pos]);
+        let hex = rest
+            .get(pos + 1..pos + 3)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        match u8::from_str_radix(hex, 16).ok()? {
+            b'/' | b'\\' => return None,
+            byte if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                decoded.push(byte as char)
+            }
+            _ => {
+                decoded.push('%');
+                decoded.push_str(&hex.to_ascii_uppercase());
+            }
+        }
+        rest = &rest[pos + 3..];
+    }
+    decoded.push_str(rest);
+
+    let mut segments = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut normalized = format!("/{}", segments.join("/"));
+    if !segments.is_empty() && matches!(decoded.rsplit('/').next(), Some("" | "." | "..")) {
+        normalized.push('/');
+    }
+    if let Some(query) = query {
+        normalized.push('?');
+        normalized.push_str(query);
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        let normalize = normalize_path;
+
+        assert_eq!(normalize("/").as_deref(), Some("/"));
+        assert_eq!(normalize("/public/../admin").as_deref(), Some("/admin"));
+        assert_eq!(normalize("/public/%2e%2E/admin").as_deref(), Some("/admin"));
+        assert_eq!(
+            normalize("/../../etc/passwd").as_deref(),
+            Some("/etc/passwd")
+        );
+        assert_eq!(normalize("//admin/./x/").as_deref(), Some("/admin/x/"));
+        assert_eq!(
+            normalize("/a/b/..?next=/../x").as_deref(),
+            Some("/a/?next=/../x")
+        );
+        assert_eq!(normalize("/%7euser/a%2cb").as_deref(), Some("/~user/a%2Cb"));
+        assert!(normalize("/public/..%2fadmin").is_none());
+        assert!(normalize("/public\\..\\admin").is_none());
+        assert!(normalize("/bad%zz").is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::protocol::config::RateLimitConfig;
+
+/// Clients tracked before idle buckets are pruned
+const MAX_CLIENTS: usize = 10_000;
+
+/// Per-client token bucket limiter for tunneled requests
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Validate the limits and create a limiter
+    pub fn from_config(config: &RateLimitConfig) -> Result<Self> {
+        if config.enabled {
+            if config.requests_per_minute == 0 {
+                anyhow::bail!("Rate limit requests_per_minute must be greater than 0");
+            }
+            if config.burst_size == 0 {
+                anyhow::bail!("Rate limit burst_size must be greater than 0");
+            }
+        }
+
+        Ok(Self {
+            config: config.clone(),
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Take a token for the client, or return how long it should wait
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let capacity = self.config.burst_size as f64;
+        let per_second = self.config.requests_per_minute as f64 / 60.0;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(client) {
+            prune(&mut buckets, now, capacity, per_second);
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            blocked_until: None,
+        });
+
+        if let Some(until) = bucket.blocked_until {
+            if now < until {
+                return Err(until - now);
+            }
+            bucket.blocked_until = None;
+        }
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if self.config.block_duration_seconds > 0 {
+            let block = Duration::from_secs(self.config.block_duration_seconds);
+            bucket.blocked_until = Some(now + block);
+            Err(block)
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+/// Drop buckets that have refilled completely and are not blocked
+fn prune(buckets: &mut HashMap<String, Bucket>, now: Instant, capacity: f64, per_second: f64) {
+    buckets.retain(|_, bucket| {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        let blocked = bucket.blocked_until.is_some_and(|until| until > now);
+        blocked || bucket.tokens + elapsed * per_second < capacity
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_refill_and_block() {
+        let limiter = RateLimiter::from_config(&RateLimitConfig {
+            enabled: true,
+            requests_per_minute: 60,
+            burst_size: 2,
+            block_duration_seconds: 0,
+        })
+        .unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at("203.0.113.7", start).is_ok());
+        assert!(limiter.check_at("203.0.113.7", start).is_ok());
+        let wait = limiter.check_at("203.0.113.7", start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+
+        // Other clients have their own bucket, and tokens refill over time
+        assert!(limiter.check_at("198.51.100.1", start).is_ok());
+        assert!(limiter
+            .check_at("203.0.113.7", start + Duration::from_secs(1))
+            .is_ok());
+
+        let blocking = RateLimiter::from_config(&RateLimitConfig {
+            enabled: true,
+            requests_per_minute: 60,
+            burst_size: 1,
+            block_duration_seconds: 30,
+        })
+        .unwrap();
+        assert!(blocking.check_at("a", start).is_ok());
+        assert_eq!(
+            blocking.check_at("a", start).unwrap_err(),
+            Duration::from_secs(30)
+        );
+        assert!(blocking
+            .check_at("a", start + Duration::from_secs(5))
+            .is_err());
+        assert!(blocking
+            .check_at("a", start + Duration::from_secs(31))
+            .is_ok());
+
+        assert!(RateLimiter::from_config(&RateLimitConfig {
+            enabled: true,
+            requests_per_minute: 0,
+            ..RateLimitConfig::default()
+        })
+        .is_err());
+    }
+}
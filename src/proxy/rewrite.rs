@@ -0,0 +1,314 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use super::path_matches;
+use crate::config::settings::{HeaderAction, HeaderRuleConfig, HeaderRulesConfig};
+use crate::utils::http::{find_key, remove_header};
+
+/// Applies configured header rewriting rules to requests and responses
+#[derive(Debug, Clone, Default)]
+pub struct HeaderRewriter {
+    request_rules: Vec<HeaderRule>,
+    response_rules: Vec<HeaderRule>,
+}
+
+/// Compiled header rule
+#[derive(Debug, Clone)]
+struct HeaderRule {
+    action: HeaderAction,
+    name: String,
+    value: Option<Template>,
+    to: Option<String>,
+    path: Option<String>,
+    methods: Vec<String>,
+}
+
+/// Values available to header templates
+#[derive(Debug, Clone, Default)]
+pub struct RewriteContext {
+    pub request_id: String,
+    pub client_ip: String,
+    pub tunnel_id: String,
+    pub method: String,
+    pub path: String,
+    pub host: String,
+}
+
+/// Header value template with `{placeholder}` segments
+#[derive(Debug, Clone)]
+struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    RequestId,
+    ClientIp,
+    TunnelId,
+    Timestamp,
+    Method,
+    Path,
+    Host,
+}
+
+impl HeaderRewriter {
+    /// Compile header rules from configuration
+    pub fn from_config(config: &HeaderRulesConfig) -> Result<Self> {
+        Ok(Self {
+            request_rules: compile_rules(&config.request, "request")?,
+            response_rules: compile_rules(&config.response, "response")?,
+        })
+    }
+
+    /// Apply request rules to headers sent to the local server
+    pub fn rewrite_request(&self, headers: &mut HashMap<String, String>, ctx: &RewriteContext) {
+        apply_rules(&self.request_rules, headers, ctx);
+    }
+
+    /// Apply response rules to headers sent back through the tunnel
+    pub fn rewrite_response(&self, headers: &mut HashMap<String, String>, ctx: &RewriteContext) {
+        apply_rules(&self.response_rules, headers, ctx);
+    }
+}
+
+impl HeaderRule {
+    /// Check the rule conditions against the current request
+    fn applies_to(&self, ctx: &RewriteContext) -> bool {
+        let route_path = ctx.path.split('?').next().unwrap_or(&ctx.path);
+        if let Some(prefix) = &self.path {
+            if !path_matches(prefix, route_path) {
+                return false;
+            }
+        }
+
+        self.methods.is_empty() || self.methods.iter().any(|m| m == &ctx.method.to_uppercase())
+    }
+}
+
+impl Template {
+    /// Parse a template, rejecting unknown placeholders
+    fn parse(input: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = input;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|i| start + i)
+                .ok_or_else(|| anyhow::anyhow!("Unclosed placeholder in header value: {input}"))?;
+
+            segments.push(match &rest[start + 1..end] {
+                "request_id" => Segment::RequestId,
+                "client_ip" => Segment::ClientIp,
+                "tunnel_id" => Segment::TunnelId,
+                "timestamp" => Segment::Timestamp,
+                "method" => Segment::Method,
+                "path" => Segment::Path,
+                "host" => Segment::Host,
+                other => anyhow::bail!("Unknown placeholder {{{other}}} in header value"),
+            });
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Render the template for a request
+    fn render(&self, ctx: &RewriteContext) -> String {
+        let mut value = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => value.push_str(text),
+                Segment::RequestId => value.push_str(&ctx.request_id),
+                Segment::ClientIp => value.push_str(&ctx.client_ip),
+                Segment::TunnelId => value.push_str(&ctx.tunnel_id),
+                Segment::Timestamp => value.push_str(&chrono::Utc::now().to_rfc3339()),
+                Segment::Method => value.push_str(&ctx.method),
+                Segment::Path => value.push_str(&ctx.path),
+                Segment::Host => value.push_str(&ctx.host),
+            }
+        }
+        value
+    }
+}
+
+/// Compile and validate a list of rules
+fn compile_rules(configs: &[HeaderRuleConfig], direction: &str) -> Result<Vec<HeaderRule>> {
+    configs
+        .iter()
+        .map(|config| {
+            if config.name.trim().is_empty() {
+                anyhow::bail!("Header {direction} rule is missing a header name");
+            }
+
+            let value = match config.action {
+                HeaderAction::Add | HeaderAction::Set => {
+                    let value = config.value.as_deref().ok_or_else(|| {
+                        anyhow::anyhow!("Header {direction} rule for {} needs a value", config.name)
+                    })?;
+                    Some(Template::parse(value)?)
+                }
+                HeaderAction::Remove | HeaderAction::Rename => None,
+            };
+
+            if config.action == HeaderAction::Rename && config.to.is_none() {
+                anyhow::bail!(
+                    "Header {direction} rename rule for {} needs a 'to' name",
+                    config.name
+                );
+            }
+
+            Ok(HeaderRule {
+                action: config.action,
+                name: config.name.clone(),
+                value,
+                to: config.to.clone(),
+                path: config.path.clone(),
+                methods: config.methods.iter().map(|m| m.to_uppercase()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Apply rules in order
+fn apply_rules(rules: &[HeaderRule], headers: &mut HashMap<String, String>, ctx: &RewriteContext) {
+    for rule in rules.iter().filter(|rule| rule.applies_to(ctx)) {
+        match rule.action {
+            HeaderAction::Add => {
+                if find_key(headers, &rule.name).is_none() {
+                    let value = rule
+                        .value
+                        .as_ref()
+                        .map(|t| t.render(ctx))
+                        .unwrap_or_default();
+                    headers.insert(rule.name.clone(), value);
+                }
+            }
+            HeaderAction::Set => {
+                remove_header(headers, &rule.name);
+                let value = rule
+                    .value
+                    .as_ref()
+                    .map(|t| t.render(ctx))
+                    .unwrap_or_default();
+                headers.insert(rule.name.clone(), value);
+            }
+            HeaderAction::Remove => {
+                remove_header(headers, &rule.name);
+            }
+            HeaderAction::Rename => {
+                if let (Some(value), Some(to)) = (remove_header(headers, &rule.name), &rule.to) {
+                    remove_header(headers, to);
+                    headers.insert(to.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(action: HeaderAction, name: &str, value: Option<&str>) -> HeaderRuleConfig {
+        HeaderRuleConfig {
+            action,
+            name: name.to_string(),
+            value: value.map(str::to_string),
+            to: None,
+            path: None,
+            methods: Vec::new(),
+        }
+    }
+
+    fn context() -> RewriteContext {
+        RewriteContext {
+            request_id: "req-42".to_string(),
+            client_ip: "203.0.113.7".to_string(),
+            tunnel_id: "default-tunnel".to_string(),
+            method: "GET".to_string(),
+            path: "/api/users?page=2".to_string(),
+            host: "demo.pori.dev".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_default_rules_match_previous_behavior() {
+        let rewriter = HeaderRewriter::from_config(&HeaderRulesConfig::default()).unwrap();
+        let ctx = context();
+
+        let mut request = HashMap::from([("x-request-id".to_string(), "upstream".to_string())]);
+        rewriter.rewrite_request(&mut request, &ctx);
+        assert_eq!(request.get("x-request-id").unwrap(), "upstream");
+        assert_eq!(request.get("X-Forwarded-By").unwrap(), "pori-proxy");
+
+        let mut response = HashMap::from([
+            ("Server".to_string(), "nginx".to_string()),
+            ("x-powered-by".to_string(), "Express".to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+        ]);
+        rewriter.rewrite_response(&mut response, &ctx);
+        assert_eq!(response.len(), 1);
+    }
+
+    #[test]
+    fn test_set_rename_and_templates() {
+        let mut rename = rule(HeaderAction::Rename, "X-Legacy", None);
+        rename.to = Some("X-Modern".to_string());
+        let mut admin_only = rule(HeaderAction::Set, "X-Admin", Some("yes"));
+        admin_only.path = Some("/admin".to_string());
+
+        let config = HeaderRulesConfig {
+            request: vec![
+                rule(HeaderAction::Set, "Host", Some("app.local")),
+                rule(
+                    HeaderAction::Set,
+                    "X-Trace",
+                    Some("{tunnel_id}/{request_id} from {client_ip} via {host}"),
+                ),
+                rename,
+                admin_only,
+            ],
+            response: Vec::new(),
+        };
+        let rewriter = HeaderRewriter::from_config(&config).unwrap();
+
+        let mut headers = HashMap::from([
+            ("host".to_string(), "demo.pori.dev".to_string()),
+            ("x-legacy".to_string(), "1".to_string()),
+        ]);
+        rewriter.rewrite_request(&mut headers, &context());
+
+        assert_eq!(headers.get("Host").unwrap(), "app.local");
+        assert!(!headers.contains_key("host"));
+        assert_eq!(
+            headers.get("X-Trace").unwrap(),
+            "default-tunnel/req-42 from 203.0.113.7 via demo.pori.dev"
+        );
+        assert_eq!(headers.get("X-Modern").unwrap(), "1");
+        assert!(!headers.contains_key("X-Admin"));
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        let bad_placeholder = HeaderRulesConfig {
+            request: vec![rule(HeaderAction::Set, "X-Test", Some("{nope}"))],
+            response: Vec::new(),
+        };
+        assert!(HeaderRewriter::from_config(&bad_placeholder).is_err());
+
+        let missing_value = HeaderRulesConfig {
+            request: vec![rule(HeaderAction::Add, "X-Test", None)],
+            response: Vec::new(),
+        };
+        assert!(HeaderRewriter::from_config(&missing_value).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Kinds of local upstream
+//!
+//! `local_server.url` selects how requests are served: `http://` and
+//! `https://` URLs reach a server over TCP, `unix:///run/app.sock` reaches
+//! an HTTP server on a Unix domain socket, `file:///srv/site` serves a
+//! directory directly, and `echo:` and `mock:` answer without any local
+//! server. A base path for the socket can follow a colon, as in
+//! `unix:///run/app.sock:/api`; a colon inside the socket path is written
+//! as `%3A`.
+
+use anyhow::{bail, Result};
+use std::path::PathBuf;
+use url::Url;
+
+/// Where forwarded requests go
+#[derive(Debug, Clone, PartialEq)]
+pub enum Upstream {
+    /// HTTP(S) server reached over TCP
+    Http(Url),
+    /// HTTP/1.1 server listening on a Unix domain socket
+    Unix {
+        socket: PathBuf,
+        /// Prefix for request paths, without a trailing slash
+        base_path: String,
+    },
+    /// Directory served by Pori itself
+    Files(PathBuf),
+    /// Requests answered with a JSON description of themselves
+    Echo,
+    /// Requests answered from `local_server.mock` routes
+    Mock,
+}
+
+impl Upstream {
+    /// Parse and check a local server URL
+    pub fn from_url(url: &Url) -> Result<Self> {
+        match url.scheme() {
+            "http" | "https" => {
+                if url.host_str().is_none_or(str::is_empty) {
+                    bail!("Local server URL must have a valid host");
+                }
+                Ok(Self::Http(url.clone()))
+            }
+            "unix" => {
+                if !cfg!(unix) {
+                    bail!("Unix socket upstreams are only supported on Unix systems");
+                }
+                // Split before decoding so an encoded colon stays in the socket path
+                let (socket, base_path) = url.path().split_once(':').unwrap_or((url.path(), ""));
+                let decode = |part| percent_encoding::percent_decode_str(part).decode_utf8();
+                let (socket, base_path) = (decode(socket)?, decode(base_path)?);
+                if socket.is_empty() || socket.ends_with('/') {
+                    bail!("Unix socket URL '{url}' must name a socket file, e.g. unix:///run/app.sock");
+                }
+                if !base_path.is_empty() && !base_path.starts_with('/') {
+                    bail!("Base path '{base_path}' in '{url}' must start with '/'");
+                }
+                Ok(Self::Unix {
+                    socket: PathBuf::from(socket.as_ref()),
+                    base_path: base_path.trim_end_matches('/').to_string(),
+                })
+            }
+            "file" => {
+                let root = url.to_file_path().map_err(|_| {
+                    anyhow::anyhow!("File URL '{url}' must be an absolute local path")
+                })?;
+                Ok(Self::Files(root))
+            }
+            "echo" => Ok(Self::Echo),
+            "mock" => Ok(Self::Mock),
+            other => bail!(
+                "Unsupported local server scheme '{other}' (use http, https, unix, file, echo or mock)"
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub mod unix {
+    use anyhow::{Context, Result};
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Incoming;
+    use hyper::{Request, Response};
+    use hyper_util::rt::TokioIo;
+    use std::path::Path;
+    use tokio::net::UnixStream;
+
+    /// Send one request over a new connection to `socket`
+    pub async fn send(socket: &Path, request: Request<Full<Bytes>>) -> Result<Response<Bytes>> {
+        let stream = UnixStream::connect(socket)
+            .await
+            .with_context(|| format!("Failed to connect to {}", socket.display()))?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .context("HTTP handshake over the Unix socket failed")?;
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+
+        let response: Response<Incoming> = sender
+            .send_request(request)
+            .await
+            .context("Failed to send a request over the Unix socket")?;
+        let (parts, body) = response.into_parts();
+        let body = body
+            .collect()
+            .await
+            .context("Failed to read the response body")?
+            .to_bytes();
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_urls() {
+        let parse = |url: &str| Upstream::from_url(&Url::parse(url).unwrap());
+
+        assert!(matches!(
+            parse("http://localhost:3000").unwrap(),
+            Upstream::Http(_)
+        ));
+        assert_eq!(
+            parse("unix:///run/app.sock").unwrap(),
+            Upstream::Unix {
+                socket: PathBuf::from("/run/app.sock"),
+                base_path: String::new(),
+            }
+        );
+        assert_eq!(
+            parse("unix:///var/run/docker.sock:/v1.43/").unwrap(),
+            Upstream::Unix {
+                socket: PathBuf::from("/var/run/docker.sock"),
+                base_path: "/v1.43".to_string(),
+            }
+        );
+        assert_eq!(
+            parse("file:///srv/site").unwrap(),
+            Upstream::Files(PathBuf::from("/srv/site"))
+        );
+        assert_eq!(parse("echo:").unwrap(), Upstream::Echo);
+        assert_eq!(parse("mock://").unwrap(), Upstream::Mock);
+        assert_eq!(
+            parse("unix:///run/user%3A1000/app.sock:/api").unwrap(),
+            Upstream::Unix {
+                socket: PathBuf::from("/run/user:1000/app.sock"),
+                base_path: "/api".to_string(),
+            }
+        );
+        assert!(parse("unix:///run/").is_err());
+        assert!(parse("ftp://localhost").is_err());
+    }
+}
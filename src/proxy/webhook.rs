@@ -0,0 +1,432 @@
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use std::collections::HashMap;
+use subtle::ConstantTimeEq;
+
+use super::{normalize_path, path_matches};
+use crate::config::settings::{WebhookConfig, WebhookProvider};
+use crate::utils::http::header_value;
+
+/// Default timestamp tolerance for providers that sign timestamps
+const DEFAULT_TOLERANCE_SECS: u64 = 300;
+
+/// Verifies webhook signatures on the raw request body before forwarding
+#[derive(Debug, Clone, Default)]
+pub struct WebhookVerifier {
+    rules: Vec<WebhookRule>,
+}
+
+/// Compiled verification rule
+#[derive(Debug, Clone)]
+struct WebhookRule {
+    path: String,
+    provider: WebhookProvider,
+    secret: Vec<u8>,
+    tolerance: u64,
+    header: String,
+    algorithm: HmacAlgorithm,
+    encoding: SignatureEncoding,
+    prefix: String,
+}
+
+/// HMAC digest used by the generic provider
+#[derive(Debug, Clone, Copy)]
+enum HmacAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Signature encoding used by the generic provider
+#[derive(Debug, Clone, Copy)]
+enum SignatureEncoding {
+    Hex,
+    Base64,
+}
+
+/// Result of verifying a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookOutcome {
+    /// No webhook rule covers this request
+    NotApplicable,
+    /// Signature is valid for the given provider
+    Verified(&'static str),
+    /// Signature is missing or invalid
+    Failed {
+        provider: &'static str,
+        reason: String,
+    },
+}
+
+impl WebhookVerifier {
+    /// Compile verification rules from configuration
+    pub fn from_config(configs: &[WebhookConfig]) -> Result<Self> {
+        let mut rules = Vec::with_capacity(configs.len());
+
+        for config in configs {
+            if !config.path.starts_with('/') {
+                anyhow::bail!("Webhook path must start with '/': {}", config.path);
+            }
+            if config.secret.is_empty() {
+                anyhow::bail!("Webhook secret for {} cannot be empty", config.path);
+            }
+
+            let algorithm = match config
+                .algorithm
+                .as_deref()
+                .unwrap_or("sha256")
+                .to_lowercase()
+                .as_str()
+            {
+                "sha1" => HmacAlgorithm::Sha1,
+                "sha256" => HmacAlgorithm::Sha256,
+                "sha512" => HmacAlgorithm::Sha512,
+                other => anyhow::bail!("Unsupported webhook HMAC algorithm: {}", other),
+            };
+            let encoding = match config
+                .encoding
+                .as_deref()
+                .unwrap_or("hex")
+                .to_lowercase()
+                .as_str()
+            {
+                "hex" => SignatureEncoding::Hex,
+                "base64" => SignatureEncoding::Base64,
+                other => anyhow::bail!("Unsupported webhook signature encoding: {}", other),
+            };
+
+            let header = match config.provider {
+                WebhookProvider::Github => "x-hub-signature-256".to_string(),
+                WebhookProvider::Stripe => "stripe-signature".to_string(),
+                WebhookProvider::Slack => "x-slack-signature".to_string(),
+                WebhookProvider::Hmac => config
+                    .header
+                    .clone()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Generic HMAC webhook {} needs a header", config.path)
+                    })?
+                    .to_lowercase(),
+            };
+
+            rules.push(WebhookRule {
+                path: config.path.clone(),
+                provider: config.provider,
+                secret: config.secret.as_bytes().to_vec(),
+                tolerance: config.tolerance.unwrap_or(DEFAULT_TOLERANCE_SECS),
+                header,
+                algorithm,
+                encoding,
+                prefix: config.prefix.clone().unwrap_or_default(),
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Check whether any rule is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Verify a request against the first matching rule
+    ///
+    /// `body` must be the bytes exactly as received in the request payload. Relays that
+    /// send JSON bodies as objects rather than strings are re-serialized on decode and
+    /// will generally fail verification.
+    pub fn verify(
+        &self,
+        path: &str,
+        headers: &HashMap<String, String>,
+        body: Option<&[u8]>,
+    ) -> WebhookOutcome {
+        // Match the canonical path so `/x/../hooks/github` cannot skip the check
+        let path = normalize_path(path).unwrap_or_else(|| path.to_string());
+        let route_path = path.split('?').next().unwrap_or_default();
+        let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| path_matches(&rule.path, route_path))
+        else {
+            return WebhookOutcome::NotApplicable;
+        };
+
+        let provider = rule.provider.name();
+        let body = body.unwrap_or_default();
+        let now = chrono::Utc::now().timestamp();
+
+        match rule.verify(headers, body, now) {
+            Ok(()) => WebhookOutcome::Verified(provider),
+            Err(reason) => WebhookOutcome::Failed { provider, reason },
+        }
+    }
+}
+
+impl WebhookRule {
+    fn verify(
+        &self,
+        headers: &HashMap<String, String>,
+        body: &[u8],
+        now: i64,
+    ) -> std::result::Result<(), String> {
+        let signature = header_value(headers, &self.header)
+            .ok_or_else(|| format!("missing {} header", self.header))?;
+
+        match self.provider {
+            WebhookProvider::Github => {
+                let provided = signature
+                    .strip_prefix("sha256=")
+                    .ok_or("signature is not prefixed with sha256=")?;
+                let expected = hmac(HmacAlgorithm::Sha256, &self.secret, body);
+                check_hex(provided, &expected)
+            }
+            WebhookProvider::Stripe => {
+                let mut timestamp = None;
+                let mut candidates = Vec::new();
+                for part in signature.split(',') {
+                    match part.trim().split_once('=') {
+                        Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                        Some(("v1", value)) => candidates.push(value),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.ok_or("missing timestamp in Stripe-Signature")?;
+                self.check_tolerance(timestamp, now)?;
+
+                let mut payload = format!("{timestamp}.").into_bytes();
+                payload.extend_from_slice(body);
+                let expected = hmac(HmacAlgorithm::Sha256, &self.secret, &payload);
+
+                if candidates
+                    .iter()
+                    .any(|candidate| check_hex(candidate, &expected).is_ok())
+                {
+                    Ok(())
+                } else {
+                    Err("no matching v1 signature".to_string())
+                }
+            }
+            WebhookProvider::Slack => {
+                let timestamp = header_value(headers, "x-slack-request-timestamp")
+                    .and_then(|t| t.trim().parse::<i64>().ok())
+                    .ok_or("missing X-Slack-Request-Timestamp header")?;
+                self.check_tolerance(timestamp, now)?;
+
+                let provided = signature
+                    .strip_prefix("v0=")
+                    .ok_or("signature is not prefixed with v0=")?;
+                let mut payload = format!("v0:{timestamp}:").into_bytes();
+                payload.extend_from_slice(body);
+                let expected = hmac(HmacAlgorithm::Sha256, &self.secret, &payload);
+                check_hex(provided, &expected)
+            }
+            WebhookProvider::Hmac => {
+                let provided = signature
+                    .strip_prefix(self.prefix.as_str())
+                    .ok_or_else(|| format!("signature is not prefixed with {}", self.prefix))?;
+                let expected = hmac(self.algorithm, &self.secret, body);
+                match self.encoding {
+                    SignatureEncoding::Hex => check_hex(provided, &expected),
+                    SignatureEncoding::Base64 => {
+                        let decoded = general_purpose::STANDARD
+                            .decode(provided.trim())
+                            .map_err(|_| "signature is not valid base64".to_string())?;
+                        check_bytes(&decoded, &expected)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reject replayed requests whose timestamp is outside the tolerance window
+    fn check_tolerance(&self, timestamp: i64, now: i64) -> std::result::Result<(), String> {
+        if now.abs_diff(timestamp) > self.tolerance {
+            Err(format!(
+                "timestamp is outside the {}s tolerance window",
+                self.tolerance
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl WebhookProvider {
+    /// Provider name used in logs and inspector tags
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookProvider::Github => "github",
+            WebhookProvider::Stripe => "stripe",
+            WebhookProvider::Slack => "slack",
+            WebhookProvider::Hmac => "hmac",
+        }
+    }
+}
+
+/// Compute an HMAC digest
+fn hmac(algorithm: HmacAlgorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    macro_rules! digest {
+        ($hash:ty) => {{
+            let mut mac =
+                Hmac::<$hash>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }};
+    }
+
+    match algorithm {
+        HmacAlgorithm::Sha1 => digest!(sha1::Sha1),
+        HmacAlgorithm::Sha256 => digest!(sha2::Sha256),
+        HmacAlgorithm::Sha512 => digest!(sha2::Sha512),
+    }
+}
+
+/// Compare a hex-encoded signature against the expected digest
+fn check_hex(provided: &str, expected: &[u8]) -> std::result::Result<(), String> {
+    let decoded = hex::decode(provided.trim()).map_err(|_| "signature is not valid hex")?;
+    check_bytes(&decoded, expected)
+}
+
+/// Compare signature bytes in constant time
+fn check_bytes(provided: &[u8], expected: &[u8]) -> std::result::Result<(), String> {
+    if provided.ct_eq(expected).into() {
+        Ok(())
+    } else {
+        Err("signature mismatch".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(path: &str, provider: WebhookProvider) -> WebhookConfig {
+        WebhookConfig {
+            path: path.to_string(),
+            provider,
+            secret: "whsec_test".to_string(),
+            tolerance: None,
+            header: None,
+            algorithm: None,
+            encoding: None,
+            prefix: None,
+        }
+    }
+
+    fn headers(pairs: &[(&str, String)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_github_signature() {
+        let verifier =
+            WebhookVerifier::from_config(&[rule("/hooks/github", WebhookProvider::Github)])
+                .unwrap();
+        let body = br#"{"action":"opened"}"#;
+        let signature = hex::encode(hmac(HmacAlgorithm::Sha256, b"whsec_test", body));
+
+        let valid = headers(&[("X-Hub-Signature-256", format!("sha256={signature}"))]);
+        assert_eq!(
+            verifier.verify("/hooks/github", &valid, Some(body)),
+            WebhookOutcome::Verified("github")
+        );
+
+        let tampered = br#"{"action":"closed"}"#;
+        assert!(matches!(
+            verifier.verify("/hooks/github", &valid, Some(tampered)),
+            WebhookOutcome::Failed { .. }
+        ));
+        assert!(matches!(
+            verifier.verify("/hooks/github", &HashMap::new(), Some(body)),
+            WebhookOutcome::Failed { .. }
+        ));
+        assert!(matches!(
+            verifier.verify("/public/%2e%2e/hooks/github", &HashMap::new(), Some(body)),
+            WebhookOutcome::Failed { .. }
+        ));
+        assert_eq!(
+            verifier.verify("/other", &HashMap::new(), Some(body)),
+            WebhookOutcome::NotApplicable
+        );
+    }
+
+    #[test]
+    fn test_stripe_signature_with_tolerance() {
+        let verifier =
+            WebhookVerifier::from_config(&[rule("/hooks/stripe", WebhookProvider::Stripe)])
+                .unwrap();
+        let body = b"{}";
+        let sign = |timestamp: i64| {
+            let payload = format!("{timestamp}.{{}}");
+            hex::encode(hmac(
+                HmacAlgorithm::Sha256,
+                b"whsec_test",
+                payload.as_bytes(),
+            ))
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let valid = headers(&[(
+            "Stripe-Signature",
+            format!("t={now},v1=deadbeef,v1={}", sign(now)),
+        )]);
+        assert_eq!(
+            verifier.verify("/hooks/stripe", &valid, Some(body)),
+            WebhookOutcome::Verified("stripe")
+        );
+
+        for old in [now - 3600, i64::MIN] {
+            let stale = headers(&[("Stripe-Signature", format!("t={old},v1={}", sign(old)))]);
+            assert!(matches!(
+                verifier.verify("/hooks/stripe", &stale, Some(body)),
+                WebhookOutcome::Failed { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_slack_signature() {
+        let verifier =
+            WebhookVerifier::from_config(&[rule("/slack", WebhookProvider::Slack)]).unwrap();
+        let body = b"token=abc&command=%2Fdeploy";
+        let now = chrono::Utc::now().timestamp();
+        let mut payload = format!("v0:{now}:").into_bytes();
+        payload.extend_from_slice(body);
+        let signature = hex::encode(hmac(HmacAlgorithm::Sha256, b"whsec_test", &payload));
+
+        let valid = headers(&[
+            ("X-Slack-Signature", format!("v0={signature}")),
+            ("X-Slack-Request-Timestamp", now.to_string()),
+        ]);
+        assert_eq!(
+            verifier.verify("/slack/commands", &valid, Some(body)),
+            WebhookOutcome::Verified("slack")
+        );
+    }
+
+    #[test]
+    fn test_generic_hmac_base64() {
+        let mut config = rule("/hooks", WebhookProvider::Hmac);
+        config.header = Some("X-Signature".to_string());
+        config.algorithm = Some("sha512".to_string());
+        config.encoding = Some("base64".to_string());
+        config.prefix = Some("sig=".to_string());
+        let verifier = WebhookVerifier::from_config(&[config]).unwrap();
+
+        let body = b"payload";
+        let signature =
+            general_purpose::STANDARD.encode(hmac(HmacAlgorithm::Sha512, b"whsec_test", body));
+        let valid = headers(&[("x-signature", format!("sig={signature}"))]);
+        assert_eq!(
+            verifier.verify("/hooks", &valid, Some(body)),
+            WebhookOutcome::Verified("hmac")
+        );
+
+        let mut missing_header = rule("/hooks", WebhookProvider::Hmac);
+        missing_header.header = None;
+        assert!(WebhookVerifier::from_config(&[missing_header]).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use http_body_util::Full;
+use http_body_util::{BodyExt, Full, Limited};
 use hyper::body::Bytes;
 use hyper::body::Incoming;
 use hyper::{Method, Request, Response, StatusCode};
@@ -9,6 +9,9 @@ use tracing::{debug, warn};
 
 use crate::AppState;
 
+/// Maximum accepted API request body
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
 /// API handler for dashboard endpoints
 pub struct ApiHandler {
     app_state: Arc<AppState>,
@@ -32,6 +35,10 @@ impl ApiHandler {
             (&Method::GET, "/api/stats") => self.handle_stats().await,
             (&Method::GET, "/api/config") => self.handle_config().await,
             (&Method::GET, "/api/endpoints") => self.handle_endpoints().await,
+            (&Method::GET, "/api/requests") => self.handle_requests(req.uri().query()).await,
+            (&Method::GET, "/api/tunnels") => self.handle_tunnels(),
+            (&Method::GET, "/api/logging") => self.handle_get_logging(),
+            (&Method::PUT, "/api/logging") => self.handle_set_logging(req).await,
             (&Method::POST, "/api/reconnect") => self.handle_reconnect().await,
             (&Method::POST, "/api/shutdown") => self.handle_shutdown().await,
             _ => self.handle_not_found(),
@@ -40,14 +47,20 @@ impl ApiHandler {
 
     /// Handle status endpoint
     async fn handle_status(&self) -> Result<Response<Full<Bytes>>> {
-        let stats = self.app_state.get_stats().await;
+        let stats = self.app_state.metrics.snapshot();
+
+        let session = self.app_state.relay_session();
+        let tunnels: Vec<_> = self.tunnels().iter().map(|t| tunnel_summary(t)).collect();
 
         let status = json!({
             "status": "ok",
             "connection_status": stats.connection_status,
             "uptime_seconds": stats.uptime_seconds,
             "requests_processed": stats.requests_processed,
-            "websocket_reconnects": stats.websocket_reconnects
+            "websocket_reconnects": stats.websocket_reconnects,
+            "client_id": self.app_state.client_id(),
+            "public_url": session.public_url,
+            "tunnels": tunnels
         });
 
         self.json_response(StatusCode::OK, status)
@@ -55,24 +68,17 @@ impl ApiHandler {
 
     /// Handle stats endpoint
     async fn handle_stats(&self) -> Result<Response<Full<Bytes>>> {
-        let stats = self.app_state.get_stats().await;
+        let metrics = &self.app_state.metrics;
 
-        let response = json!({
-            "requests_processed": stats.requests_processed,
-            "requests_successful": stats.requests_successful,
-            "requests_failed": stats.requests_failed,
-            "bytes_forwarded": stats.bytes_forwarded,
-            "uptime_seconds": stats.uptime_seconds,
-            "websocket_reconnects": stats.websocket_reconnects,
-            "connection_status": stats.connection_status
-        });
+        let mut response = json!(metrics.snapshot());
+        response["routes"] = json!(metrics.routes());
 
         self.json_response(StatusCode::OK, response)
     }
 
     /// Handle config endpoint
     async fn handle_config(&self) -> Result<Response<Full<Bytes>>> {
-        let settings = &self.app_state.settings;
+        let settings = self.app_state.settings();
 
         let config = json!({
             "websocket_url": settings.websocket.url.to_string(),
@@ -87,11 +93,96 @@ impl ApiHandler {
         self.json_response(StatusCode::OK, config)
     }
 
+    /// Handle requests endpoint - recent requests recorded by the inspector
+    async fn handle_requests(&self, query: Option<&str>) -> Result<Response<Full<Bytes>>> {
+        let limit = query
+            .and_then(|q| {
+                q.split('&')
+                    .find_map(|pair| pair.strip_prefix("limit="))
+                    .and_then(|v| v.parse::<usize>().ok())
+            })
+            .unwrap_or(50);
+
+        let requests = self.app_state.inspector.recent(limit);
+        let response = json!({ "requests": requests });
+
+        self.json_response(StatusCode::OK, response)
+    }
+
+    /// Handle tunnels endpoint - every tunnel served by this process
+    fn handle_tunnels(&self) -> Result<Response<Full<Bytes>>> {
+        let tunnels: Vec<_> = self.tunnels().iter().map(|t| tunnel_summary(t)).collect();
+
+        self.json_response(StatusCode::OK, json!({ "tunnels": tunnels }))
+    }
+
+    /// Registered tunnels, or just this one when none are registered
+    fn tunnels(&self) -> Vec<Arc<AppState>> {
+        let tunnels = self.app_state.tunnel_states();
+        if tunnels.is_empty() {
+            vec![self.app_state.clone()]
+        } else {
+            tunnels
+        }
+    }
+
+    /// Handle reading the active log filter
+    fn handle_get_logging(&self) -> Result<Response<Full<Bytes>>> {
+        self.json_response(
+            StatusCode::OK,
+            json!({ "filter": crate::logging::current_filter() }),
+        )
+    }
+
+    /// Handle changing the log filter at runtime
+    async fn handle_set_logging(&self, req: Request<Incoming>) -> Result<Response<Full<Bytes>>> {
+        // Trace logging exposes request data, so changing it needs the relay token
+        if !super::has_token(&self.app_state, &req) {
+            return self.json_response(
+                StatusCode::UNAUTHORIZED,
+                json!({
+                    "error": "Unauthorized",
+                    "message": "Changing the log filter requires ?token= or a Bearer token"
+                }),
+            );
+        }
+
+        let body = Limited::new(req.into_body(), MAX_BODY_SIZE).collect().await;
+        let filter = body
+            .ok()
+            .and_then(|body| serde_json::from_slice::<serde_json::Value>(&body.to_bytes()).ok())
+            .and_then(|value| value.get("filter")?.as_str().map(str::to_string));
+
+        let Some(filter) = filter else {
+            return self.json_response(
+                StatusCode::BAD_REQUEST,
+                json!({
+                    "error": "Bad Request",
+                    "message": "Expected a JSON body like {\"filter\": \"PROXY=debug,hyper=warn\"}"
+                }),
+            );
+        };
+
+        self.apply_log_filter(&filter)
+    }
+
+    /// Apply a log filter and report the result
+    fn apply_log_filter(&self, filter: &str) -> Result<Response<Full<Bytes>>> {
+        match crate::logging::set_filter(filter) {
+            Ok(filter) => self.json_response(StatusCode::OK, json!({ "filter": filter })),
+            Err(e) => self.json_response(
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "Bad Request", "message": e.to_string() }),
+            ),
+        }
+    }
+
     /// Handle reconnect endpoint
     async fn handle_reconnect(&self) -> Result<Response<Full<Bytes>>> {
-        // This would trigger a WebSocket reconnection
-        // For now, we'll just return a success message
         warn!("Reconnect requested via API");
+        for tunnel in self.tunnels() {
+            tunnel.request_reconnect();
+        }
 
         let response = json!({
             "status": "success",
@@ -117,7 +208,7 @@ impl ApiHandler {
 
     /// Handle endpoints endpoint - lists available WebSocket endpoints
     async fn handle_endpoints(&self) -> Result<Response<Full<Bytes>>> {
-        let settings = &self.app_state.settings;
+        let settings = self.app_state.settings();
         let base_url = format!(
             "{}://{}:{}",
             if settings.websocket.url.scheme() == "wss" {
@@ -135,14 +226,14 @@ impl ApiHandler {
                     "path": "/",
                     "name": "Main Proxy Endpoint",
                     "description": "WebSocket endpoint for HTTP request forwarding",
-                    "url": format!("{}/?token={}", base_url, settings.websocket.token),
+                    "url": format!("{}/?token=<token>", base_url),
                     "usage": "Main proxy endpoint for request forwarding"
                 },
                 {
                     "path": "/metrics",
                     "name": "Real-time Analytics Dashboard",
                     "description": "WebSocket endpoint for real-time metrics and analytics",
-                    "url": format!("{}/metrics?token={}", base_url, settings.websocket.token),
+                    "url": format!("{}/metrics?token=<token>", base_url),
                     "usage": "Real-time analytics and metrics dashboard"
                 }
             ],
@@ -167,6 +258,21 @@ impl ApiHandler {
                     "method": "GET",
                     "description": "Get a list of available endpoints (this endpoint)"
                 },
+                {
+                    "path": "/api/requests",
+                    "method": "GET",
+                    "description": "Get recently tunneled requests with inspector tags"
+                },
+                {
+                    "path": "/api/tunnels",
+                    "method": "GET",
+                    "description": "List the tunnels served by this process and their status"
+                },
+                {
+                    "path": "/api/logging",
+                    "method": "GET, PUT",
+                    "description": "Read or change (with the relay token) the log filter directives at runtime"
+                },
                 {
                     "path": "/api/reconnect",
                     "method": "POST",
@@ -226,6 +332,22 @@ impl ApiHandler {
     }
 }
 
+/// Name, addresses and relay session of one tunnel
+fn tunnel_summary(state: &AppState) -> serde_json::Value {
+    let settings = state.settings();
+    let session = state.relay_session();
+    json!({
+        "name": settings.tunnel_name,
+        "websocket_url": settings.websocket.url.to_string(),
+        "local_server_url": settings.local_server.url.to_string(),
+        "subdomain": settings.websocket.subdomain,
+        "public_url": session.public_url,
+        "server_id": session.server_id,
+        "connection_status": state.connection_status().as_str(),
+        "draining": state.is_draining(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,8 +367,11 @@ mod tests {
             timeout: 30,
             max_reconnects: 0,
             verify_ssl: false,
+            no_verify_ssl: false,
             max_connections: 10,
             http_version: "http1".to_string(),
+            subdomain: None,
+            upstream: None,
         };
 
         let settings = AppSettings::from_cli(args).unwrap();
@@ -272,6 +397,23 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_requests_endpoint() {
+        let app_state = create_test_app_state();
+        app_state.inspector.begin(
+            "req-1",
+            "POST",
+            "/hooks/github",
+            "unknown",
+            &std::collections::HashMap::new(),
+            0,
+        );
+        let handler = ApiHandler::new(app_state);
+
+        let response = handler.handle_requests(Some("limit=10")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn test_endpoints_endpoint() {
         let app_state = create_test_app_state();
@@ -279,6 +421,22 @@ mod tests {
 
         let response = handler.handle_endpoints().await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+
+        // The listing is public, so it must not leak the relay token
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(!String::from_utf8_lossy(&body).contains("test-token"));
+    }
+
+    #[test]
+    fn test_logging_endpoint_rejects_invalid_filter() {
+        let app_state = create_test_app_state();
+        let handler = ApiHandler::new(app_state);
+
+        let response = handler.handle_get_logging().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = handler.apply_log_filter("PROXY=chatty").unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
@@ -291,6 +449,38 @@ mod tests {
 
         let headers = response.headers();
         assert!(headers.contains_key("access-control-allow-origin"));
-        assert!(headers.contains_key("access-control-allow-methods"));
+        assert!(!headers["access-control-allow-methods"]
+            .to_str()
+            .unwrap()
+            .contains("PUT"));
+    }
+
+    #[test]
+    fn test_log_filter_change_requires_token() {
+        let app_state = create_test_app_state();
+        let request = |uri: &str, authorization: Option<&str>| {
+            let mut builder = Request::builder().method(Method::PUT).uri(uri);
+            if let Some(value) = authorization {
+                builder = builder.header("authorization", value);
+            }
+            builder.body(()).unwrap()
+        };
+
+        assert!(!crate::server::has_token(
+            &app_state,
+            &request("/api/logging", None)
+        ));
+        assert!(!crate::server::has_token(
+            &app_state,
+            &request("/api/logging?token=wrong", None)
+        ));
+        assert!(crate::server::has_token(
+            &app_state,
+            &request("/api/logging?token=test-token", None)
+        ));
+        assert!(crate::server::has_token(
+            &app_state,
+            &request("/api/logging", Some("Bearer test-token"))
+        ));
     }
 }
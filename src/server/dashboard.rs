@@ -5,21 +5,26 @@ use hyper::body::Incoming;
 use hyper::{Method, Request, Response, StatusCode};
 use std::convert::Infallible;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tracing::{debug, error};
 
 use super::{
     api::ApiHandler,
+    live,
     static_files::{create_default_static_files, StaticFileHandler},
 };
 use crate::{AppState, DashboardEvent};
 
+/// Events buffered per live client before it starts lagging
+const LIVE_CHANNEL_CAPACITY: usize = 256;
+
 /// Dashboard service for handling HTTP requests
 pub struct DashboardService {
     app_state: Arc<AppState>,
     static_handler: Arc<StaticFileHandler>,
     api_handler: Arc<ApiHandler>,
     events: Arc<RwLock<Vec<DashboardEvent>>>,
+    live_tx: broadcast::Sender<serde_json::Value>,
 }
 
 impl DashboardService {
@@ -32,12 +37,14 @@ impl DashboardService {
         // but for now we'll just use the empty handler
 
         let api_handler = Arc::new(ApiHandler::new(app_state.clone()));
+        let (live_tx, _) = broadcast::channel(LIVE_CHANNEL_CAPACITY);
 
         Self {
             app_state,
             static_handler,
             api_handler,
             events: Arc::new(RwLock::new(Vec::new())),
+            live_tx,
         }
     }
 
@@ -45,6 +52,9 @@ impl DashboardService {
     pub async fn handle_event(&self, event: DashboardEvent) {
         debug!("Dashboard received event: {:?}", event);
 
+        // Push to live clients; an error only means nobody is connected
+        let _ = self.live_tx.send(live::event_frame(&event));
+
         // Store event for dashboard display
         let mut events = self.events.write().await;
         events.push(event);
@@ -116,6 +126,11 @@ impl DashboardService {
             return self.handle_metrics_websocket_upgrade(req).await;
         }
 
+        // Prometheus scrape endpoint
+        if path == "/metrics" && method == Method::GET {
+            return self.serve_prometheus_metrics();
+        }
+
         // Handle WebSocket upgrade for main proxy endpoint
         if path == "/" && self.is_websocket_upgrade(&req) {
             return self.handle_proxy_websocket_upgrade(req).await;
@@ -167,7 +182,7 @@ impl DashboardService {
             .header("cache-control", "public, max-age=3600"); // 1 hour cache
 
         // Add CORS headers
-        if self.app_state.settings.dashboard.enable_cors {
+        if self.app_state.settings().dashboard.enable_cors {
             response_builder = response_builder
                 .header("access-control-allow-origin", "*")
                 .header("access-control-allow-methods", "GET, OPTIONS")
@@ -179,6 +194,28 @@ impl DashboardService {
         Ok(response)
     }
 
+    /// Serve metrics in the Prometheus text exposition format
+    fn serve_prometheus_metrics(&self) -> Result<Response<Full<Bytes>>> {
+        let tunnels = self.app_state.tunnel_states();
+        let tunnels = if tunnels.is_empty() {
+            vec![self.app_state.clone()]
+        } else {
+            tunnels
+        };
+        let registries: Vec<_> = tunnels
+            .iter()
+            .map(|t| (t.settings().tunnel_name.clone(), t.tunnel_metrics.as_ref()))
+            .collect();
+        let body = crate::metrics::prometheus::render(&self.app_state.metrics, &registries);
+
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", crate::metrics::prometheus::CONTENT_TYPE)
+            .header("cache-control", "no-cache")
+            .body(Full::new(Bytes::from(body)))?;
+        Ok(response)
+    }
+
     /// Serve default dashboard when no static files are available
     fn serve_default_dashboard(&self) -> Result<Response<Full<Bytes>>> {
         let default_files = create_default_static_files();
@@ -244,29 +281,7 @@ impl DashboardService {
         req: Request<Incoming>,
     ) -> Result<Response<Full<Bytes>>> {
         debug!("WebSocket upgrade requested for proxy endpoint");
-
-        // Validate token from query parameter
-        if let Some(query) = req.uri().query() {
-            if self.validate_websocket_token(query) {
-                // For now, return a placeholder response
-                // In a full implementation, this would upgrade to WebSocket
-                let response = Response::builder()
-                    .status(StatusCode::SWITCHING_PROTOCOLS)
-                    .header("upgrade", "websocket")
-                    .header("connection", "upgrade")
-                    .body(Full::new(Bytes::from("WebSocket upgrade - Proxy endpoint")))?;
-                return Ok(response);
-            }
-        }
-
-        // Invalid token or missing token
-        let response = Response::builder()
-            .status(StatusCode::UNAUTHORIZED)
-            .header("content-type", "text/plain")
-            .body(Full::new(Bytes::from(
-                "Unauthorized: Invalid or missing token",
-            )))?;
-        Ok(response)
+        self.upgrade_to_live_stream(req)
     }
 
     /// Handle WebSocket upgrade for metrics endpoint (/metrics)
@@ -275,47 +290,31 @@ impl DashboardService {
         req: Request<Incoming>,
     ) -> Result<Response<Full<Bytes>>> {
         debug!("WebSocket upgrade requested for metrics endpoint");
+        self.upgrade_to_live_stream(req)
+    }
 
-        // Validate token from query parameter
-        if let Some(query) = req.uri().query() {
-            if self.validate_websocket_token(query) {
-                // For now, return a placeholder response
-                // In a full implementation, this would upgrade to WebSocket
-                // and provide real-time metrics data
-                let response = Response::builder()
-                    .status(StatusCode::SWITCHING_PROTOCOLS)
-                    .header("upgrade", "websocket")
-                    .header("connection", "upgrade")
-                    .body(Full::new(Bytes::from(
-                        "WebSocket upgrade - Metrics endpoint",
-                    )))?;
-                return Ok(response);
-            }
+    /// Upgrade an authenticated request to the live event stream
+    fn upgrade_to_live_stream(&self, mut req: Request<Incoming>) -> Result<Response<Full<Bytes>>> {
+        if !super::has_token(&self.app_state, &req) {
+            let response = Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header("content-type", "text/plain")
+                .body(Full::new(Bytes::from(
+                    "Unauthorized: Invalid or missing token",
+                )))?;
+            return Ok(response);
         }
 
-        // Invalid token or missing token
-        let response = Response::builder()
-            .status(StatusCode::UNAUTHORIZED)
-            .header("content-type", "text/plain")
-            .body(Full::new(Bytes::from(
-                "Unauthorized: Invalid or missing token",
-            )))?;
-        Ok(response)
-    }
-
-    /// Validate WebSocket token from query parameters
-    fn validate_websocket_token(&self, query: &str) -> bool {
-        // Parse query parameters to find token
-        for param in query.split('&') {
-            if let Some((key, value)) = param.split_once('=') {
-                if key == "token" {
-                    // Compare with configured token
-                    let configured_token = &self.app_state.settings.websocket.token;
-                    return value == configured_token;
-                }
+        let (response, on_upgrade) = live::accept_upgrade(&mut req)?;
+        let app_state = self.app_state.clone();
+        let events = self.live_tx.subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = live::serve(on_upgrade, app_state, events).await {
+                debug!("Live dashboard stream ended: {}", e);
             }
-        }
-        false
+        });
+
+        Ok(response)
     }
 }
 
@@ -326,6 +325,7 @@ impl Clone for DashboardService {
             static_handler: self.static_handler.clone(),
             api_handler: self.api_handler.clone(),
             events: self.events.clone(),
+            live_tx: self.live_tx.clone(),
         }
     }
 }
@@ -349,8 +349,11 @@ mod tests {
             timeout: 30,
             max_reconnects: 0,
             verify_ssl: false,
+            no_verify_ssl: false,
             max_connections: 10,
             http_version: "http1".to_string(),
+            subdomain: None,
+            upstream: None,
         };
 
         let settings = AppSettings::from_cli(args).unwrap();
@@ -367,6 +370,64 @@ mod tests {
         assert_eq!(events.len(), 0);
     }
 
+    #[test]
+    fn test_prometheus_metrics() {
+        let app_state = create_test_app_state();
+        let service = DashboardService::new(app_state);
+
+        let response = service.serve_prometheus_metrics().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            crate::metrics::prometheus::CONTENT_TYPE
+        );
+    }
+
+    #[tokio::test]
+    async fn test_live_stream_upgrade() {
+        use futures_util::StreamExt;
+        use hyper::server::conn::http1;
+        use hyper::service::service_fn;
+        use hyper_util::rt::TokioIo;
+
+        let service = Arc::new(DashboardService::new(create_test_app_state()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn({
+            let service = service.clone();
+            async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let _ = http1::Builder::new()
+                    .serve_connection(
+                        TokioIo::new(stream),
+                        service_fn(move |req| {
+                            let service = service.clone();
+                            async move { service.handle_request(req).await }
+                        }),
+                    )
+                    .with_upgrades()
+                    .await;
+            }
+        });
+
+        let url = format!("ws://{addr}/metrics?token=test-token");
+        let (mut ws, response) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        let snapshot = ws.next().await.unwrap().unwrap();
+        let snapshot: serde_json::Value =
+            serde_json::from_str(snapshot.to_text().unwrap()).unwrap();
+        assert_eq!(snapshot["type"], "stats_update");
+
+        service
+            .handle_event(DashboardEvent::Error("boom".to_string()))
+            .await;
+        let event = ws.next().await.unwrap().unwrap();
+        let event: serde_json::Value = serde_json::from_str(event.to_text().unwrap()).unwrap();
+        assert_eq!(event["data"]["kind"], "error");
+    }
+
     #[tokio::test]
     async fn test_event_handling() {
         let app_state = create_test_app_state();
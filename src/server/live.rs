@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::upgrade::OnUpgrade;
+use hyper::{header, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, warn};
+
+use crate::{AppState, ConnectionStatus, DashboardEvent};
+
+/// How often stats snapshots are pushed to live clients
+const STATS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of recent inspector entries sent when a client connects
+const INITIAL_REQUESTS: usize = 50;
+
+/// Build the 101 response for a WebSocket upgrade and take the pending upgrade
+pub fn accept_upgrade(req: &mut Request<Incoming>) -> Result<(Response<Full<Bytes>>, OnUpgrade)> {
+    let key = req
+        .headers()
+        .get(header::SEC_WEBSOCKET_KEY)
+        .context("Missing Sec-WebSocket-Key header")?;
+    let accept = derive_accept_key(key.as_bytes());
+    let on_upgrade = hyper::upgrade::on(req);
+
+    let response = Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::UPGRADE, "websocket")
+        .header(header::CONNECTION, "upgrade")
+        .header(header::SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Full::new(Bytes::new()))?;
+
+    Ok((response, on_upgrade))
+}
+
+/// Stream dashboard events, stats snapshots and inspector entries to one client
+pub async fn serve(
+    on_upgrade: OnUpgrade,
+    app_state: Arc<AppState>,
+    mut events: broadcast::Receiver<Value>,
+) -> Result<()> {
+    let upgraded = on_upgrade.await.context("WebSocket upgrade failed")?;
+    let mut ws = WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+    debug!("Live dashboard client connected");
+
+    // Catch the client up before streaming
+    send(&mut ws, stats_frame(&app_state)).await?;
+    for entry in app_state
+        .inspector
+        .recent(INITIAL_REQUESTS)
+        .into_iter()
+        .rev()
+    {
+        send(&mut ws, frame("request", json!(entry))).await?;
+    }
+
+    let mut ticker = tokio::time::interval(STATS_INTERVAL);
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(frame) => send(&mut ws, frame).await?,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Live dashboard client lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ticker.tick() => send(&mut ws, stats_frame(&app_state)).await?,
+            incoming = ws.next() => match incoming {
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    debug!("Live dashboard client error: {}", e);
+                    break;
+                }
+            },
+        }
+    }
+
+    let _ = ws.close(None).await;
+    debug!("Live dashboard client disconnected");
+    Ok(())
+}
+
+/// Convert a dashboard event into a live frame
+pub fn event_frame(event: &DashboardEvent) -> Value {
+    match event {
+        DashboardEvent::RequestForwarded(request) => frame(
+            "event",
+            json!({ "kind": "request_forwarded", "message": request }),
+        ),
+        DashboardEvent::ResponseReceived(status, bytes) => frame(
+            "event",
+            json!({ "kind": "response_received", "status": status, "bytes": bytes }),
+        ),
+        DashboardEvent::Error(message) => {
+            frame("event", json!({ "kind": "error", "message": message }))
+        }
+        DashboardEvent::AccessDenied(message) => frame(
+            "event",
+            json!({ "kind": "access_denied", "message": message }),
+        ),
+        DashboardEvent::AuthenticationFailed(message) => frame(
+            "event",
+            json!({ "kind": "authentication_failed", "message": message }),
+        ),
+        DashboardEvent::ConfigReloaded(message) => frame(
+            "event",
+            json!({ "kind": "config_reloaded", "message": message }),
+        ),
+        DashboardEvent::ConfigReloadFailed(message) => frame(
+            "event",
+            json!({ "kind": "config_reload_failed", "message": message }),
+        ),
+        DashboardEvent::RequestCompleted(entry) => frame("request", json!(entry)),
+        DashboardEvent::ConnectionStatus(status) => {
+            let (state, message) = match status {
+                ConnectionStatus::Connecting => ("connecting", None),
+                ConnectionStatus::Connected => ("connected", None),
+                ConnectionStatus::Disconnected => ("disconnected", None),
+                ConnectionStatus::Reconnecting => ("reconnecting", None),
+                ConnectionStatus::Error(message) => ("error", Some(message.clone())),
+            };
+            frame(
+                "connection_status",
+                json!({ "status": state, "message": message }),
+            )
+        }
+        DashboardEvent::Statistics(stats) => frame("stats_update", json!(stats)),
+    }
+}
+
+/// Build a stats snapshot frame
+fn stats_frame(app_state: &AppState) -> Value {
+    frame("stats_update", json!(app_state.metrics.snapshot()))
+}
+
+/// Wrap data in the live message envelope
+fn frame(kind: &str, data: Value) -> Value {
+    json!({
+        "type": kind,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "data": data
+    })
+}
+
+async fn send<S>(ws: &mut WebSocketStream<S>, frame: Value) -> Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    ws.send(Message::text(frame.to_string()))
+        .await
+        .context("Failed to send to live dashboard client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_frames() {
+        let frame = event_frame(&DashboardEvent::ResponseReceived(404, 12));
+        assert_eq!(frame["type"], "event");
+        assert_eq!(frame["data"]["kind"], "response_received");
+        assert_eq!(frame["data"]["status"], 404);
+
+        let frame = event_frame(&DashboardEvent::ConnectionStatus(ConnectionStatus::Error(
+            "boom".to_string(),
+        )));
+        assert_eq!(frame["type"], "connection_status");
+        assert_eq!(frame["data"]["status"], "error");
+        assert_eq!(frame["data"]["message"], "boom");
+    }
+}
@@ -1,5 +1,6 @@
 pub mod api;
 pub mod dashboard;
+pub mod live;
 pub mod static_files;
 
 use anyhow::{Context, Result};
@@ -8,6 +9,7 @@ use hyper::service::service_fn;
 use hyper_util::rt::TokioIo;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use subtle::ConstantTimeEq;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc;
 use tracing::{debug, error};
@@ -15,13 +17,34 @@ use tracing::{debug, error};
 use crate::{local_log, AppState, DashboardEvent};
 use dashboard::DashboardService;
 
+/// Whether a request carries the relay token as `?token=` or `Authorization: Bearer`
+pub(crate) fn has_token<B>(app_state: &AppState, req: &hyper::Request<B>) -> bool {
+    let configured = app_state.settings().websocket.token.clone();
+    let from_query = req.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+    let from_header = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    [from_query, from_header]
+        .into_iter()
+        .flatten()
+        .any(|token| bool::from(token.as_bytes().ct_eq(configured.as_bytes())))
+}
+
 /// Run the dashboard HTTP server
 pub async fn run_dashboard_server(
     app_state: Arc<AppState>,
     mut event_rx: mpsc::UnboundedReceiver<DashboardEvent>,
 ) -> Result<()> {
-    let bind_addr = app_state.settings.dashboard.bind_address.clone();
-    let port = app_state.settings.dashboard.port;
+    let bind_addr = app_state.settings().dashboard.bind_address.clone();
+    let port = app_state.settings().dashboard.port;
     let addr: SocketAddr = format!("{bind_addr}:{port}")
         .parse()
         .context("Invalid dashboard address")?;
@@ -65,6 +88,7 @@ pub async fn run_dashboard_server(
                                     async move { service.handle_request(req).await }
                                 }),
                             )
+                            .with_upgrades()
                             .await
                         {
                             error!("Error serving connection: {:?}", err);
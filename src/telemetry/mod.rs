@@ -0,0 +1,290 @@
+//! OpenTelemetry tracing for tunneled requests
+//!
+//! Every tunneled request gets a server span. With the `otel` cargo feature
+//! the spans are exported over OTLP/HTTP, an incoming W3C `traceparent` from
+//! the relay becomes the span's parent and a new `traceparent` is injected
+//! into the request sent to the local server. Without the feature the spans
+//! only feed the regular log output and headers pass through unchanged.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_subscriber::{Layer, Registry};
+
+use crate::config::settings::TelemetryConfig;
+
+/// Boxed subscriber layer added alongside the log formatter
+pub type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Create a server span for a tunneled request
+pub fn request_span(
+    method: &str,
+    route: &str,
+    request_id: &str,
+    cloud_request_id: &str,
+    headers: &HashMap<String, String>,
+) -> Span {
+    let span = tracing::info_span!(
+        "tunnel_request",
+        otel.name = %format!("{method} {route}"),
+        otel.kind = "server",
+        otel.status_code = tracing::field::Empty,
+        http.request.method = %method,
+        http.route = %route,
+        http.response.status_code = tracing::field::Empty,
+        requestId = %request_id,
+        cloud_request_id = %cloud_request_id,
+    );
+    set_remote_parent(&span, headers);
+    span
+}
+
+/// Record the response status on a request span
+pub fn record_status(span: &Span, status: u16) {
+    span.record("http.response.status_code", status);
+    if status >= 500 {
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use anyhow::{Context as _, Result};
+    use opentelemetry::propagation::{Extractor, Injector};
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry::{global, Context};
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
+    use tracing::{warn, Span};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::{EnvFilter, Layer};
+
+    use super::BoxedLayer;
+    use crate::config::settings::TelemetryConfig;
+    use crate::utils::http::remove_header;
+
+    /// Provider kept around so buffered spans can be flushed on exit
+    static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+    /// Build a tracer provider exporting to the configured OTLP endpoint
+    pub fn build_provider(config: &TelemetryConfig) -> Result<SdkTracerProvider> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&config.endpoint)
+            .build()
+            .context("Failed to create the OTLP span exporter")?;
+
+        Ok(SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(config.service_name.clone())
+                    .build(),
+            )
+            .build())
+    }
+
+    pub fn layer(config: &TelemetryConfig) -> Result<Option<BoxedLayer>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let provider = build_provider(config)?;
+        let tracer = provider.tracer("pori");
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let _ = PROVIDER.set(provider);
+
+        let filter = EnvFilter::try_new(&config.filter)
+            .with_context(|| format!("Invalid telemetry filter '{}'", config.filter))?;
+        Ok(Some(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(filter)
+                .boxed(),
+        ))
+    }
+
+    pub fn shutdown() {
+        if let Some(provider) = PROVIDER.get() {
+            if let Err(e) = provider.shutdown() {
+                warn!("Failed to flush OpenTelemetry spans: {}", e);
+            }
+        }
+    }
+
+    pub fn set_remote_parent(span: &Span, headers: &HashMap<String, String>) {
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(headers))
+        });
+        // Only adopt a parent when the relay actually sent one
+        if parent.has_active_span() {
+            let _ = span.set_parent(parent);
+        }
+    }
+
+    pub fn inject(span: &Span, headers: &mut HashMap<String, String>) {
+        let context: Context = span.context();
+        if !context.has_active_span() {
+            return;
+        }
+
+        remove_header(headers, "traceparent");
+        remove_header(headers, "tracestate");
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut HeaderInjector(headers))
+        });
+    }
+
+    /// Case-insensitive view of request headers for trace context extraction
+    struct HeaderExtractor<'a>(&'a HashMap<String, String>);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.as_str())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(String::as_str).collect()
+        }
+    }
+
+    struct HeaderInjector<'a>(&'a mut HashMap<String, String>);
+
+    impl Injector for HeaderInjector<'_> {
+        fn set(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// Create the OpenTelemetry subscriber layer if tracing export is enabled
+#[cfg(feature = "otel")]
+pub fn layer(config: &TelemetryConfig) -> Result<Option<BoxedLayer>> {
+    otel::layer(config)
+}
+
+/// Create the OpenTelemetry subscriber layer if tracing export is enabled
+#[cfg(not(feature = "otel"))]
+pub fn layer(config: &TelemetryConfig) -> Result<Option<BoxedLayer>> {
+    if config.enabled {
+        eprintln!("telemetry.enabled is set but pori was built without the `otel` feature");
+    }
+    Ok(None)
+}
+
+/// Flush and stop the span exporter
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    otel::shutdown();
+}
+
+/// Inject the current span's `traceparent` into headers for the local server
+pub fn inject(span: &Span, headers: &mut HashMap<String, String>) {
+    #[cfg(feature = "otel")]
+    otel::inject(span, headers);
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, headers);
+}
+
+/// Make an incoming `traceparent` the parent of a request span
+fn set_remote_parent(span: &Span, headers: &HashMap<String, String>) {
+    #[cfg(feature = "otel")]
+    otel::set_remote_parent(span, headers);
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, headers);
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use opentelemetry::global;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Minimal OTLP/HTTP collector stand-in that captures one export request
+    fn spawn_collector() -> (String, mpsc::Receiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let mut stream = stream;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            tx.send((request_line, body)).unwrap();
+        });
+
+        (endpoint, rx)
+    }
+
+    #[test]
+    fn test_exports_span_and_propagates_context() {
+        let (endpoint, collector) = spawn_collector();
+        let config = TelemetryConfig {
+            enabled: true,
+            endpoint,
+            ..TelemetryConfig::default()
+        };
+        let provider = otel::build_provider(&config).unwrap();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let mut headers = HashMap::from([("Traceparent".to_string(), incoming.to_string())]);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = request_span("GET", "/users/:id", "req-1", "cloud-1", &headers);
+            inject(&span, &mut headers);
+            record_status(&span, 200);
+        });
+
+        // Same trace as the relay, new parent span, old header replaced
+        let outgoing = headers.get("traceparent").unwrap();
+        assert!(outgoing.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert_ne!(outgoing, incoming);
+        assert!(!headers.contains_key("Traceparent"));
+
+        provider.force_flush().unwrap();
+        let (request_line, body) = collector.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(request_line.starts_with("POST /v1/traces"));
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("GET /users/:id"));
+        assert!(body.contains("req-1"));
+    }
+}
@@ -12,6 +12,9 @@ pub enum TunnelError {
     #[error("HTTP proxy error: {0}")]
     HttpProxy(String),
 
+    #[error("Relay proxy error: {0}")]
+    RelayProxy(String),
+
     #[error("Local server error: {0}")]
     LocalServer(String),
 
@@ -52,6 +55,7 @@ impl TunnelError {
         match self {
             TunnelError::WebSocketConnection(_) => true,
             TunnelError::HttpProxy(_) => true,
+            TunnelError::RelayProxy(_) => true,
             TunnelError::LocalServer(_) => true,
             TunnelError::Timeout(_) => true,
             TunnelError::Io(_) => true,
@@ -73,6 +77,7 @@ impl TunnelError {
             TunnelError::WebSocketConnection(_) | TunnelError::WebSocket(_) => "websocket",
             TunnelError::WebSocketAuth(_) => "authentication",
             TunnelError::HttpProxy(_) => "proxy",
+            TunnelError::RelayProxy(_) => "relay_proxy",
             TunnelError::LocalServer(_) | TunnelError::Request(_) => "local_server",
             TunnelError::Dashboard(_) | TunnelError::Http(_) => "dashboard",
             TunnelError::Configuration(_) => "configuration",
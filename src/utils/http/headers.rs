@@ -0,0 +1,48 @@
+//! Case-insensitive lookups on the header maps carried through the tunnel
+
+use std::collections::HashMap;
+
+/// Find a header value, ignoring case
+pub fn header_value<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Find the stored key for a header name, ignoring case
+pub fn find_key(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    headers
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+/// Remove every spelling of a header, returning one of the removed values
+pub fn remove_header(headers: &mut HashMap<String, String>, name: &str) -> Option<String> {
+    let value = header_value(headers, name).map(str::to_string);
+    headers.retain(|key, _| !key.eq_ignore_ascii_case(name));
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_insensitive_helpers() {
+        let mut headers = HashMap::from([
+            ("Content-Type".to_string(), "text/plain".to_string()),
+            ("x-token".to_string(), "a".to_string()),
+            ("X-Token".to_string(), "b".to_string()),
+        ]);
+
+        assert_eq!(header_value(&headers, "content-type"), Some("text/plain"));
+        assert_eq!(find_key(&headers, "CONTENT-TYPE").unwrap(), "Content-Type");
+        assert!(header_value(&headers, "accept").is_none());
+
+        assert!(remove_header(&mut headers, "x-token").is_some());
+        assert!(find_key(&headers, "x-token").is_none());
+        assert_eq!(headers.len(), 1);
+    }
+}
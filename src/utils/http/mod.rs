@@ -1,3 +1,5 @@
+pub mod headers;
 pub mod status;
 
+pub use headers::{find_key, header_value, remove_header};
 pub use status::get_status_description;
@@ -1,6 +1,9 @@
+use std::sync::Arc;
 use tokio::signal;
 use tracing::{info, warn};
 
+use crate::AppState;
+
 /// Wait for shutdown signals (Ctrl+C or SIGTERM)
 pub async fn wait_for_shutdown() {
     let ctrl_c = async {
@@ -32,24 +35,25 @@ pub async fn wait_for_shutdown() {
     info!("Starting a graceful shutdown...");
 }
 
-/// Setup signal handlers for graceful shutdown
-pub fn setup_signal_handlers() {
-    // This function can be used to set up additional signal handling
-    // if needed in the future
-
+/// Setup signal handlers for non-shutdown signals
+///
+/// SIGUSR1 reopens the access log so external tools can rotate it.
+pub fn setup_signal_handlers(app_state: Arc<AppState>) {
     #[cfg(unix)]
     {
-        // On Unix systems, we might want to handle additional signals
-        // like SIGUSR1 for log rotation, etc.
-        tokio::spawn(async {
+        tokio::spawn(async move {
             let mut sigusr1 = signal::unix::signal(signal::unix::SignalKind::user_defined1())
                 .expect("Failed to install SIGUSR1 handler");
 
             while sigusr1.recv().await.is_some() {
-                info!("Received SIGUSR1 signal - could be used for log rotation");
+                info!("Received SIGUSR1 signal - reopening the access log");
+                app_state.access_log.reopen();
             }
         });
     }
+
+    #[cfg(not(unix))]
+    let _ = app_state;
 }
 
 /// Force shutdown after timeout
@@ -4,13 +4,17 @@ use serde_json;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{
+    client_async_tls_with_config, connect_async_tls_with_config, tungstenite::Message,
+    MaybeTlsStream, WebSocketStream,
+};
 use tracing::{debug, error, info, instrument, warn};
 
-use super::{reconnect::ReconnectManager, tunnel::TunnelHandler};
+use super::{proxy::RelayProxy, reconnect::ReconnectManager, tls::RelayTls, tunnel::TunnelHandler};
 use crate::protocol::messages::{HttpPayload, MessagePayload};
 use crate::protocol::tunnel::TunnelMessage;
-use crate::{proxy_log, AppState, ConnectionStatus, DashboardEvent};
+use crate::utils::error::TunnelError;
+use crate::{proxy_log, AppState, ConnectionStatus};
 
 type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
 
@@ -31,7 +35,7 @@ impl WebSocketClient {
 
         let reconnect_manager = Arc::new(Mutex::new(
             ReconnectManager::new()
-                .with_max_attempts(app_state.settings.websocket.max_reconnects)
+                .with_max_attempts(app_state.settings().websocket.max_reconnects)
                 .with_base_delay(Duration::from_secs(1))
                 .with_max_delay(Duration::from_secs(300)),
         ));
@@ -50,7 +54,7 @@ impl WebSocketClient {
     pub async fn run(&self) -> Result<()> {
         proxy_log!(
             "Starting WebSocket client for URL: {}",
-            self.app_state.settings.websocket.url
+            self.app_state.settings().websocket.url
         );
 
         loop {
@@ -66,12 +70,8 @@ impl WebSocketClient {
             }
 
             // Update connection status
-            let _ = self
-                .app_state
-                .dashboard_tx
-                .send(DashboardEvent::ConnectionStatus(
-                    ConnectionStatus::Connecting,
-                ));
+            self.app_state
+                .report_connection_status(ConnectionStatus::Connecting);
 
             // Attempt connection
             match self.connect_and_run().await {
@@ -83,15 +83,19 @@ impl WebSocketClient {
                     manager.reset();
                 }
                 Err(e) => {
-                    error!("WebSocket connection failed: {}", e);
+                    match e.downcast_ref::<TunnelError>() {
+                        Some(proxy_error @ TunnelError::RelayProxy(_)) => {
+                            error!(
+                                "Could not reach the relay through the proxy: {}",
+                                proxy_error
+                            )
+                        }
+                        _ => error!("WebSocket connection failed: {}", e),
+                    }
 
                     // Update connection status
-                    let _ = self
-                        .app_state
-                        .dashboard_tx
-                        .send(DashboardEvent::ConnectionStatus(
-                            ConnectionStatus::Disconnected,
-                        ));
+                    self.app_state
+                        .report_connection_status(ConnectionStatus::Disconnected);
 
                     // Calculate reconnection delay
                     let delay = {
@@ -103,12 +107,9 @@ impl WebSocketClient {
                         info!("Waiting {:?} Before reconnection attempt", delay);
 
                         // Update status to reconnecting
-                        let _ = self
-                            .app_state
-                            .dashboard_tx
-                            .send(DashboardEvent::ConnectionStatus(
-                                ConnectionStatus::Reconnecting,
-                            ));
+                        self.app_state.tunnel_metrics.record_reconnect();
+                        self.app_state
+                            .report_connection_status(ConnectionStatus::Reconnecting);
 
                         tokio::time::sleep(delay).await;
                     } else {
@@ -119,12 +120,8 @@ impl WebSocketClient {
         }
 
         // Update final status
-        let _ = self
-            .app_state
-            .dashboard_tx
-            .send(DashboardEvent::ConnectionStatus(
-                ConnectionStatus::Disconnected,
-            ));
+        self.app_state
+            .report_connection_status(ConnectionStatus::Disconnected);
 
         Ok(())
     }
@@ -132,25 +129,54 @@ impl WebSocketClient {
     /// Single connection attempt and message handling
     #[instrument(skip(self))]
     async fn connect_and_run(&self) -> Result<()> {
-        // Build URL with token query parameter
-        let mut connection_url = self.app_state.settings.websocket.url.clone();
-        connection_url
-            .query_pairs_mut()
-            .append_pair("token", &self.app_state.settings.websocket.token);
+        // Build URL with the token and this client's identity as query parameters
+        let settings = self.app_state.settings();
+        let mut connection_url = settings.websocket.url.clone();
+        {
+            let mut query = connection_url.query_pairs_mut();
+            query
+                .append_pair("token", &settings.websocket.token)
+                .append_pair("client_id", self.app_state.client_id())
+                .append_pair("tunnel", &settings.tunnel_name);
+            if let Some(subdomain) = &settings.websocket.subdomain {
+                query.append_pair("subdomain", subdomain);
+            }
+        }
 
         proxy_log!(
             "Attempting WebSocket connection to {}",
-            self.app_state.settings.websocket.url
+            self.app_state.settings().websocket.url
         );
 
-        // Establish WebSocket connection
-        let (ws_stream, response) = tokio::time::timeout(
-            self.app_state.settings.websocket.timeout,
-            connect_async(connection_url.as_str()),
-        )
-        .await
-        .context("Connection timeout")?
-        .context("Failed to connect to the WebSocket server")?;
+        // Certificates are loaded on every attempt so rotated files are picked up
+        let connector = RelayTls::from_config(&settings.websocket.tls)?.connector();
+        let proxy = RelayProxy::from_config(&settings.websocket.proxy, &settings.websocket.url)?;
+
+        // Establish WebSocket connection, through the proxy if one applies
+        let connect = async {
+            let result = match proxy.proxy_for(&connection_url) {
+                Some(proxy_url) => {
+                    proxy_log!(
+                        "Connecting through {} proxy {}:{}",
+                        proxy_url.scheme(),
+                        proxy_url.host_str().unwrap_or_default(),
+                        proxy_url.port_or_known_default().unwrap_or_default()
+                    );
+                    let stream = RelayProxy::connect(proxy_url, &connection_url).await?;
+                    client_async_tls_with_config(connection_url.as_str(), stream, None, connector)
+                        .await
+                }
+                None => {
+                    connect_async_tls_with_config(connection_url.as_str(), None, false, connector)
+                        .await
+                }
+            };
+            result.context("Failed to connect to the WebSocket server")
+        };
+        let (ws_stream, response) =
+            tokio::time::timeout(self.app_state.settings().websocket.timeout, connect)
+                .await
+                .context("Connection timeout")??;
 
         proxy_log!(
             "WebSocket connected, response status: {}",
@@ -202,6 +228,13 @@ impl WebSocketClient {
                     }
                 }
 
+                // Close and reconnect when the relay settings change
+                _ = self.app_state.reconnect_requested() => {
+                    proxy_log!("Reconnect requested, closing the relay connection");
+                    let _ = ws_sink.send(Message::Close(None)).await;
+                    break;
+                }
+
                 // Handle outbound messages
                 outbound_message = outbound_rx.recv() => {
                     match outbound_message {
@@ -237,7 +270,10 @@ impl WebSocketClient {
     async fn handle_incoming_message(&self, message: Message) -> Result<()> {
         match message {
             Message::Text(text) => {
-                debug!("Received a text message: {}", text);
+                debug!(
+                    "Received a text message: {}",
+                    self.app_state.redactor().frame(&text)
+                );
 
                 // Try to parse as a tunnel message first
                 match TunnelMessage::from_json(&text) {
@@ -246,7 +282,11 @@ impl WebSocketClient {
                         if let MessagePayload::Http(HttpPayload::Request { method, url, .. }) =
                             &tunnel_message.message.payload
                         {
-                            crate::proxy_log!("INCOMING REQUEST: {} {}", method, url);
+                            crate::proxy_log!(
+                                "INCOMING REQUEST: {} {}",
+                                method,
+                                self.app_state.redactor().url(url)
+                            );
                         }
 
                         if let Some(response) =
@@ -270,10 +310,8 @@ impl WebSocketClient {
                                             proxy_log!("Authentication status: {}", status);
                                             if status == "authenticated" {
                                                 // Update connection status to connected
-                                                let _ = self.app_state.dashboard_tx.send(
-                                                    DashboardEvent::ConnectionStatus(
-                                                        ConnectionStatus::Connected,
-                                                    ),
+                                                self.app_state.report_connection_status(
+                                                    ConnectionStatus::Connected,
                                                 );
                                             }
                                         }
@@ -293,10 +331,16 @@ impl WebSocketClient {
                                     }
                                 }
                             } else {
-                                debug!("Received a non-tunnel message: {}", text);
+                                debug!(
+                                    "Received a non-tunnel message: {}",
+                                    self.app_state.redactor().frame(&text)
+                                );
                             }
                         } else {
-                            warn!("Received an unparseable text message: {}", text);
+                            warn!(
+                                "Received an unparseable text message: {}",
+                                self.app_state.redactor().frame(&text)
+                            );
                         }
                     }
                 }
@@ -314,7 +358,11 @@ impl WebSocketClient {
                 if let MessagePayload::Http(HttpPayload::Request { method, url, .. }) =
                     &tunnel_message.message.payload
                 {
-                    crate::proxy_log!("INCOMING REQUEST: {} {}", method, url);
+                    crate::proxy_log!(
+                        "INCOMING REQUEST: {} {}",
+                        method,
+                        self.app_state.redactor().url(url)
+                    );
                 }
 
                 if let Some(response) = self.tunnel_handler.handle_message(tunnel_message).await? {
@@ -364,7 +412,12 @@ impl WebSocketClient {
     }
 
     /// Send a message via WebSocket (queue if not connected)
-    pub async fn send_message(&self, message: TunnelMessage) -> Result<()> {
+    pub async fn send_message(&self, mut message: TunnelMessage) -> Result<()> {
+        // Address the relay server that is handling this connection
+        if message.envelope.server_id.is_none() {
+            message.envelope.server_id = self.app_state.relay_session().server_id;
+        }
+
         let tx_guard = self.outbound_tx.lock().await;
 
         if let Some(ref tx) = *tx_guard {
@@ -417,7 +470,7 @@ impl WebSocketClient {
             current_attempt: manager.current_attempt(),
             max_attempts: manager.max_attempts(),
             queued_messages: queue.len(),
-            url: self.app_state.settings.websocket.url.to_string(),
+            url: self.app_state.settings().websocket.url.to_string(),
         }
     }
 }
@@ -451,8 +504,11 @@ mod tests {
             timeout: 30,
             max_reconnects: 0,
             verify_ssl: false,
+            no_verify_ssl: false,
             max_connections: 10,
             http_version: "auto".to_string(),
+            subdomain: None,
+            upstream: None,
         };
 
         let settings = AppSettings::from_cli(args).unwrap();
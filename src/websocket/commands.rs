@@ -0,0 +1,311 @@
+//! Remote commands sent by the relay
+//!
+//! Only commands listed in `commands.allowed` run. Built-in commands act on the
+//! running client; anything else must be an operator-defined `commands.exec`
+//! entry, which runs a fixed program without a shell.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::config::settings::{CommandsConfig, ExecCommandConfig};
+use crate::proxy::client::LocalServerClient;
+use crate::AppState;
+
+/// Commands handled by the client itself
+pub const BUILTIN_COMMANDS: &[&str] = &[
+    "reconnect",
+    "drain",
+    "dump-stats",
+    "set-log-level",
+    "flush-cache",
+    "ping-upstream",
+];
+
+/// How long `drain` waits for in-flight requests by default
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bytes of program output returned to the relay
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// Validated command allowlist
+#[derive(Debug)]
+pub struct CommandRunner {
+    config: CommandsConfig,
+}
+
+impl CommandRunner {
+    /// Check that every allowed command is known and exec entries are usable
+    pub fn from_config(config: &CommandsConfig) -> Result<Self> {
+        for (name, exec) in &config.exec {
+            if BUILTIN_COMMANDS.contains(&name.as_str()) {
+                bail!("Exec command '{name}' shadows a built-in command");
+            }
+            if exec.program.trim().is_empty() {
+                bail!("Exec command '{name}' has no program");
+            }
+            if exec.timeout_seconds == 0 {
+                bail!("Exec command '{name}' timeout_seconds must be greater than 0");
+            }
+        }
+
+        for name in &config.allowed {
+            if !BUILTIN_COMMANDS.contains(&name.as_str()) && !config.exec.contains_key(name) {
+                bail!(
+                    "Unknown command '{name}' in commands.allowed (built-in: {})",
+                    BUILTIN_COMMANDS.join(", ")
+                );
+            }
+        }
+
+        Ok(Self {
+            config: config.clone(),
+        })
+    }
+
+    /// Run an allowed command and return its output
+    pub async fn run(
+        &self,
+        app_state: &AppState,
+        command: &str,
+        arguments: &[String],
+        environment: &HashMap<String, String>,
+    ) -> Result<Value> {
+        if !self.config.allowed.iter().any(|name| name == command) {
+            bail!("Command '{command}' is not allowed on this client");
+        }
+
+        if let Some(exec) = self.config.exec.get(command) {
+            return run_exec(command, exec, arguments, environment).await;
+        }
+        if !environment.is_empty() {
+            bail!("Command '{command}' does not accept environment variables");
+        }
+
+        match command {
+            "reconnect" => {
+                app_state.request_reconnect();
+                Ok(json!({ "reconnecting": true }))
+            }
+            "drain" => drain(app_state, arguments).await,
+            "dump-stats" => Ok(json!({
+                "tunnel": app_state.tunnel_id(),
+                "stats": app_state.tunnel_metrics.snapshot(),
+                "routes": app_state.tunnel_metrics.routes(),
+                "draining": app_state.is_draining(),
+            })),
+            "set-log-level" => {
+                let [filter] = arguments else {
+                    bail!("Usage: set-log-level <level or filter directives>");
+                };
+                let filter = crate::logging::set_filter(filter)?;
+                Ok(json!({ "filter": filter }))
+            }
+            "flush-cache" => {
+                let cleared = app_state.inspector.clear();
+                // A fresh settings snapshot makes the forwarder rebuild its pipeline,
+                // dropping pooled local connections and rate limit buckets
+                app_state.apply_settings(app_state.settings().as_ref().clone());
+                Ok(json!({ "inspector_entries_cleared": cleared, "connection_pool_reset": true }))
+            }
+            "ping-upstream" => ping_upstream(app_state, arguments).await,
+            other => bail!("Command '{other}' is not implemented"),
+        }
+    }
+}
+
+/// Stop taking new requests and wait for in-flight ones, or resume with `off`
+async fn drain(app_state: &AppState, arguments: &[String]) -> Result<Value> {
+    let timeout = match arguments {
+        [] => DEFAULT_DRAIN_TIMEOUT,
+        [arg] if arg == "off" => {
+            app_state.set_draining(false);
+            info!("Drain ended, accepting requests again");
+            return Ok(json!({ "draining": false }));
+        }
+        [seconds] => Duration::from_secs(
+            seconds
+                .parse()
+                .with_context(|| format!("Invalid drain timeout '{seconds}'"))?,
+        ),
+        _ => bail!("Usage: drain [timeout_seconds | off]"),
+    };
+
+    app_state.set_draining(true);
+    info!("Draining: refusing new requests");
+
+    let deadline = Instant::now() + timeout;
+    while app_state.tunnel_metrics.in_flight() > 0 && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let in_flight = app_state.tunnel_metrics.in_flight();
+    Ok(json!({
+        "draining": true,
+        "drained": in_flight == 0,
+        "in_flight": in_flight,
+    }))
+}
+
+/// Send a HEAD request to the local server and report its status and latency
+async fn ping_upstream(app_state: &AppState, arguments: &[String]) -> Result<Value> {
+    let path = ping_path(arguments)?;
+
+    let settings = app_state.settings();
+    let client = LocalServerClient::new(
+        settings.local_server.url.clone(),
+        settings.local_server.timeout,
+        settings.local_server.verify_ssl,
+        &settings.local_server.http_version,
+        &settings.local_server.tls,
+        &settings.local_server.files,
+        &settings.local_server.mock,
+    )?;
+
+    let started = Instant::now();
+    let response = client
+        .forward_request("HEAD", &path, HashMap::new(), None)
+        .await?;
+    Ok(json!({
+        "url": settings.local_server.url.as_str(),
+        "path": path,
+        "status": response.status,
+        "latency_ms": started.elapsed().as_millis() as u64,
+    }))
+}
+
+/// Path for `ping-upstream`, which must stay on the configured local server
+fn ping_path(arguments: &[String]) -> Result<String> {
+    let path = match arguments {
+        [] => "/",
+        [path] => path.as_str(),
+        _ => bail!("Usage: ping-upstream [path]"),
+    };
+    // `//host/` or `http://host/` would send the request somewhere else
+    let first_segment = path.trim_start_matches('/').split('/').next();
+    if !path.starts_with('/')
+        || path.starts_with("//")
+        || first_segment.is_some_and(|segment| segment.contains(':'))
+    {
+        bail!("ping-upstream path must be a local path starting with a single '/'");
+    }
+    crate::proxy::normalize_path(path).with_context(|| format!("Invalid path '{path}'"))
+}
+
+/// Run an operator-defined program with the permitted arguments and environment
+async fn run_exec(
+    name: &str,
+    exec: &ExecCommandConfig,
+    arguments: &[String],
+    environment: &HashMap<String, String>,
+) -> Result<Value> {
+    if !arguments.is_empty() && !exec.allow_arguments {
+        bail!("Command '{name}' does not accept arguments");
+    }
+    if let Some(key) = environment
+        .keys()
+        .find(|key| !exec.environment.contains(key))
+    {
+        bail!("Command '{name}' does not accept environment variable '{key}'");
+    }
+
+    info!("Running command '{}': {}", name, exec.program);
+    // Only PATH and the permitted variables; the client's own secrets stay out
+    let mut command = tokio::process::Command::new(&exec.program);
+    command.env_clear();
+    if let Some(path) = std::env::var_os("PATH") {
+        command.env("PATH", path);
+    }
+    let child = command
+        .args(&exec.args)
+        .args(arguments)
+        .envs(environment)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start '{}'", exec.program))?;
+
+    let output = tokio::time::timeout(
+        Duration::from_secs(exec.timeout_seconds),
+        child.wait_with_output(),
+    )
+    .await
+    .with_context(|| format!("Command '{name}' timed out after {}s", exec.timeout_seconds))??;
+
+    let stdout = truncate(&output.stdout);
+    let stderr = truncate(&output.stderr);
+    if !output.status.success() {
+        bail!(
+            "Command '{name}' failed ({}): {}",
+            output.status,
+            stderr.trim()
+        );
+    }
+    Ok(json!({
+        "exit_code": output.status.code(),
+        "stdout": stdout,
+        "stderr": stderr,
+    }))
+}
+
+fn truncate(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_OUTPUT)]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_validation() {
+        let mut config = CommandsConfig {
+            allowed: vec!["dump-stats".to_string(), "restart-app".to_string()],
+            ..CommandsConfig::default()
+        };
+        assert!(CommandRunner::from_config(&config).is_err());
+
+        config.exec.insert(
+            "restart-app".to_string(),
+            ExecCommandConfig {
+                program: "systemctl".to_string(),
+                args: vec!["restart".to_string(), "app".to_string()],
+                ..ExecCommandConfig::default()
+            },
+        );
+        assert!(CommandRunner::from_config(&config).is_ok());
+
+        config.exec.insert(
+            "reconnect".to_string(),
+            ExecCommandConfig {
+                program: "true".to_string(),
+                ..ExecCommandConfig::default()
+            },
+        );
+        assert!(CommandRunner::from_config(&config)
+            .unwrap_err()
+            .to_string()
+            .contains("shadows"));
+    }
+
+    #[test]
+    fn test_ping_path_stays_local() {
+        let ping = |path: &str| ping_path(&[path.to_string()]);
+
+        assert_eq!(ping_path(&[]).unwrap(), "/");
+        assert_eq!(ping("/health/../ready").unwrap(), "/ready");
+        for path in [
+            "http://169.254.169.254/latest/meta-data/",
+            "//internal-host/",
+            "/http://169.254.169.254/",
+            "health",
+            "/a%2fb",
+        ] {
+            assert!(ping(path).is_err(), "{path} should be refused");
+        }
+    }
+}
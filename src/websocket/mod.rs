@@ -1,5 +1,9 @@
 pub mod client;
+pub mod commands;
+pub mod proxy;
 pub mod reconnect;
+pub mod reporter;
+pub mod tls;
 pub mod tunnel;
 
 use anyhow::Result;
@@ -7,6 +11,7 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::error;
 
+use crate::metrics::Queue;
 use crate::protocol::tunnel::TunnelMessage;
 use crate::{proxy_log, AppState};
 
@@ -30,9 +35,14 @@ pub async fn run_websocket_client(
         }
     });
 
+    // Send periodic stats reports to the relay
+    let reporter_handle = tokio::spawn(reporter::StatsReporter::new(app_state.clone()).run());
+
     // Handle outgoing messages
+    let metrics = app_state.metrics.clone();
     let message_handle = tokio::spawn(async move {
         while let Some(message) = message_rx.recv().await {
+            metrics.queue_pop(Queue::Websocket);
             if let Err(e) = ws_client.send_message(message).await {
                 error!("Failed to send WebSocket message: {}", e);
             }
@@ -53,6 +63,7 @@ pub async fn run_websocket_client(
         }
     }
 
+    reporter_handle.abort();
     proxy_log!("WebSocket client stopped");
     Ok(())
 }
@@ -0,0 +1,427 @@
+//! Outbound proxy for the relay connection
+//!
+//! Dials the relay through an HTTP CONNECT (`http://`) or SOCKS5
+//! (`socks5://`, `socks5h://`) proxy. Credentials come from the proxy URL's
+//! user info or `websocket.proxy.auth`. Without `websocket.proxy.url`, `HTTPS_PROXY` (`HTTP_PROXY` for
+//! `ws://` relays), `ALL_PROXY` and `NO_PROXY` are honored.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use ipnet::IpNet;
+use percent_encoding::percent_decode_str;
+use std::net::IpAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::protocol::http::HttpProxyConfig;
+use crate::utils::error::TunnelError;
+
+/// Largest CONNECT response header accepted from a proxy
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+/// Proxy used to reach the relay, if any
+#[derive(Debug, Clone)]
+pub struct RelayProxy {
+    url: Option<Url>,
+    bypass: Vec<String>,
+}
+
+impl RelayProxy {
+    /// Use the configured proxy, or the standard environment variables when none is set
+    pub fn from_config(config: &HttpProxyConfig, relay: &Url) -> Result<Self> {
+        let mut bypass = config.bypass.clone();
+        let url = match &config.url {
+            Some(url) => Some(url.clone()),
+            None => {
+                let names: &[&str] = if relay.scheme() == "wss" {
+                    &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+                } else {
+                    &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+                };
+                let from_env = names.iter().find_map(|name| env_var(name));
+                if from_env.is_some() {
+                    let no_proxy = env_var("NO_PROXY").or_else(|| env_var("no_proxy"));
+                    bypass.extend(
+                        no_proxy
+                            .iter()
+                            .flat_map(|list| list.split(','))
+                            .map(|entry| entry.trim().to_string())
+                            .filter(|entry| !entry.is_empty()),
+                    );
+                }
+                from_env
+            }
+        };
+
+        let url = url
+            .map(|url| {
+                // Bare `host:port` values mean an HTTP proxy, as with curl
+                let url = if url.contains("://") {
+                    url
+                } else {
+                    format!("http://{url}")
+                };
+                let mut parsed =
+                    Url::parse(&url).with_context(|| format!("Invalid proxy URL '{url}'"))?;
+                if !matches!(parsed.scheme(), "http" | "socks5" | "socks5h") {
+                    bail!(
+                        "Unsupported proxy scheme '{}' (use http, socks5 or socks5h)",
+                        parsed.scheme()
+                    );
+                }
+                if parsed.host_str().is_none() {
+                    bail!("Proxy URL '{url}' has no host");
+                }
+                if let Some(auth) = &config.auth {
+                    if !auth.auth_type.eq_ignore_ascii_case("basic") {
+                        bail!(
+                            "Unsupported proxy auth type '{}' (use basic)",
+                            auth.auth_type
+                        );
+                    }
+                    let credentials = parsed
+                        .set_username(&auth.username)
+                        .and_then(|_| parsed.set_password(Some(&auth.password)));
+                    if credentials.is_err() {
+                        bail!("Proxy URL '{url}' cannot carry credentials");
+                    }
+                }
+                Ok(parsed)
+            })
+            .transpose()?;
+
+        Ok(Self { url, bypass })
+    }
+
+    /// The proxy to use for `target`, unless it is bypassed
+    pub fn proxy_for(&self, target: &Url) -> Option<&Url> {
+        let host = target.host_str()?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.bypass.iter().any(|entry| bypassed(entry, host)) {
+            return None;
+        }
+        self.url.as_ref()
+    }
+
+    /// Open a tunnelled TCP stream to `target` through `proxy`
+    pub async fn connect(proxy: &Url, target: &Url) -> Result<TcpStream, TunnelError> {
+        let host = target
+            .host_str()
+            .ok_or_else(|| TunnelError::RelayProxy(format!("Relay URL {target} has no host")))?
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let port = target.port_or_known_default().unwrap_or(443);
+        let proxy_addr = format!(
+            "{}:{}",
+            proxy.host_str().unwrap_or_default(),
+            proxy.port_or_known_default().unwrap_or(1080)
+        );
+
+        let mut stream = TcpStream::connect(&proxy_addr).await.map_err(|e| {
+            TunnelError::RelayProxy(format!("Failed to connect to {proxy_addr}: {e}"))
+        })?;
+        let result = match proxy.scheme() {
+            "http" => http_connect(&mut stream, proxy, host, port).await,
+            _ => socks5_connect(&mut stream, proxy, host, port).await,
+        };
+        result.map_err(|e| TunnelError::RelayProxy(format!("{proxy_addr}: {e:#}")))?;
+        Ok(stream)
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// `NO_PROXY` matching: `*`, exact hosts, domain suffixes and IP ranges
+fn bypassed(entry: &str, host: &str) -> bool {
+    let entry = entry.trim();
+    if entry == "*" {
+        return true;
+    }
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return match entry.parse::<IpNet>() {
+            Ok(net) => net.contains(&ip),
+            Err(_) => entry.parse::<IpAddr>().is_ok_and(|entry| entry == ip),
+        };
+    }
+    let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+    let host = host.to_ascii_lowercase();
+    let domain = domain.to_ascii_lowercase();
+    host == domain || host.ends_with(&format!(".{domain}"))
+}
+
+fn credentials(proxy: &Url) -> Option<(String, String)> {
+    if proxy.username().is_empty() {
+        return None;
+    }
+    let decode = |value: &str| percent_decode_str(value).decode_utf8_lossy().into_owned();
+    Some((
+        decode(proxy.username()),
+        decode(proxy.password().unwrap_or_default()),
+    ))
+}
+
+/// Ask an HTTP proxy to open a tunnel with `CONNECT`
+async fn http_connect(stream: &mut TcpStream, proxy: &Url, host: &str, port: u16) -> Result<()> {
+    let authority = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some((user, password)) = credentials(proxy) {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing past the header is consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            bail!("CONNECT response header too large");
+        }
+        let byte = stream
+            .read_u8()
+            .await
+            .context("Proxy closed the connection")?;
+        response.push(byte);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .with_context(|| format!("Invalid CONNECT response '{status_line}'"))?;
+    match status {
+        200..=299 => Ok(()),
+        407 => bail!("proxy authentication required ({status_line})"),
+        _ => bail!("CONNECT to {authority} refused ({status_line})"),
+    }
+}
+
+/// SOCKS5 handshake (RFC 1928) with optional username/password auth (RFC 1929)
+async fn socks5_connect(stream: &mut TcpStream, proxy: &Url, host: &str, port: u16) -> Result<()> {
+    let credentials = credentials(proxy);
+    let method = if credentials.is_some() { 0x02 } else { 0x00 };
+    stream.write_all(&[0x05, 0x01, method]).await?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0x05 {
+        bail!("not a SOCKS5 proxy");
+    }
+    match (reply[1], &credentials) {
+        (0x00, _) => {}
+        (0x02, Some((user, password))) => {
+            if user.len() > 255 || password.len() > 255 {
+                bail!("SOCKS5 username and password are limited to 255 bytes");
+            }
+            let mut auth = vec![0x01, user.len() as u8];
+            auth.extend_from_slice(user.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).await?;
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                bail!("proxy authentication failed");
+            }
+        }
+        _ => bail!("proxy requires an unsupported authentication method"),
+    }
+
+    // socks5h lets the proxy resolve names; socks5 resolves them locally
+    let mut request = vec![0x05, 0x01, 0x00];
+    let ip = match host.parse::<IpAddr>() {
+        Ok(ip) => Some(ip),
+        Err(_) if proxy.scheme() == "socks5" => Some(
+            tokio::net::lookup_host((host, port))
+                .await?
+                .next()
+                .with_context(|| format!("Could not resolve {host}"))?
+                .ip(),
+        ),
+        Err(_) => None,
+    };
+    match ip {
+        Some(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Some(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        None => {
+            if host.len() > 255 {
+                bail!("Host name {host} is too long for SOCKS5");
+            }
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    if header[1] != 0x00 {
+        bail!(
+            "SOCKS5 connect to {host}:{port} failed (reply code {})",
+            header[1]
+        );
+    }
+    // Skip the bound address
+    let address_len = match header[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        other => bail!("Invalid SOCKS5 address type {other}"),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::http::ProxyAuth;
+    use tokio::io::AsyncBufReadExt;
+    use tokio::net::TcpListener;
+
+    /// Echo server standing in for the relay
+    async fn echo_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (mut reader, mut writer) = socket.split();
+            tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+        });
+        port
+    }
+
+    /// Minimal CONNECT proxy that requires `user:secret`
+    async fn connect_proxy() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut client = tokio::io::BufReader::new(socket);
+                    let mut target = String::new();
+                    let mut authorized = false;
+                    loop {
+                        let mut line = String::new();
+                        client.read_line(&mut line).await.unwrap();
+                        if let Some(authority) = line.strip_prefix("CONNECT ") {
+                            target = authority.split(' ').next().unwrap().to_string();
+                        }
+                        // dXNlcjpzZWNyZXQ= is user:secret
+                        authorized |= line.trim() == "Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=";
+                        if line == "\r\n" {
+                            break;
+                        }
+                    }
+                    let mut client = client.into_inner();
+                    if !authorized {
+                        client
+                            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                            .await
+                            .unwrap();
+                        return;
+                    }
+                    let mut upstream = TcpStream::connect(target).await.unwrap();
+                    client
+                        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                        .await
+                        .unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_through_local_proxy() {
+        let relay_port = echo_server().await;
+        let proxy_port = connect_proxy().await;
+        let target = Url::parse(&format!("ws://127.0.0.1:{relay_port}/ws")).unwrap();
+
+        let config = HttpProxyConfig {
+            url: Some(format!("http://127.0.0.1:{proxy_port}")),
+            auth: Some(ProxyAuth {
+                auth_type: "basic".to_string(),
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            }),
+            bypass: vec!["internal.example.com".to_string(), "10.0.0.0/8".to_string()],
+        };
+        let proxy = RelayProxy::from_config(&config, &target).unwrap();
+        let proxy_url = proxy.proxy_for(&target).unwrap().clone();
+        assert!(proxy
+            .proxy_for(&Url::parse("wss://relay.internal.example.com").unwrap())
+            .is_none());
+        assert!(proxy
+            .proxy_for(&Url::parse("wss://10.1.2.3").unwrap())
+            .is_none());
+
+        let mut stream = RelayProxy::connect(&proxy_url, &target).await.unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        let mut echoed = [0u8; 5];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"hello");
+
+        // Wrong credentials are reported as a proxy error
+        let mut wrong = proxy_url.clone();
+        wrong.set_password(Some("nope")).unwrap();
+        let error = RelayProxy::connect(&wrong, &target).await.unwrap_err();
+        assert!(matches!(error, TunnelError::RelayProxy(_)));
+        assert!(error.to_string().contains("407"));
+    }
+
+    #[tokio::test]
+    async fn test_socks5_through_local_proxy() {
+        let relay_port = echo_server().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            client.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [0x05, 0x01, 0x00]);
+            client.write_all(&[0x05, 0x00]).await.unwrap();
+
+            let mut request = [0u8; 10];
+            client.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request[..8], &[0x05, 0x01, 0x00, 0x01, 127, 0, 0, 1]);
+            let port = u16::from_be_bytes([request[8], request[9]]);
+            let mut upstream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            client
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+        });
+
+        let proxy = Url::parse(&format!("socks5://127.0.0.1:{proxy_port}")).unwrap();
+        let target = Url::parse(&format!("ws://127.0.0.1:{relay_port}/ws")).unwrap();
+        let mut stream = RelayProxy::connect(&proxy, &target).await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut echoed = [0u8; 4];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"ping");
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use super::tunnel::TunnelHandler;
+use crate::metrics::Queue;
+use crate::protocol::tunnel::TunnelMessage;
+use crate::{AppState, ConnectionStatus};
+
+/// Clock ticks per second used by `/proc/<pid>/stat` (USER_HZ, fixed at 100 on Linux)
+const CLOCK_TICKS_PER_SECOND: f64 = 100.0;
+
+/// Process resource usage read from `/proc`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemUsage {
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub memory_total_bytes: u64,
+}
+
+/// Periodically sends traffic and system stats to the relay
+pub struct StatsReporter {
+    app_state: Arc<AppState>,
+    tunnel_handler: TunnelHandler,
+    cpu: CpuSampler,
+}
+
+/// Tracks process CPU time between samples
+#[derive(Default)]
+struct CpuSampler {
+    last: Option<(Instant, u64)>,
+}
+
+impl StatsReporter {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        Self {
+            tunnel_handler: TunnelHandler::new(app_state.clone()),
+            app_state,
+            cpu: CpuSampler::default(),
+        }
+    }
+
+    /// Send a report every interval while the tunnel is connected
+    ///
+    /// Settings are read on every tick, so a config reload can change the
+    /// interval or turn reporting on and off.
+    pub async fn run(mut self) {
+        let mut interval = self.app_state.settings().stats_reporting.interval;
+        let mut ticker = ticker(interval);
+        // Prime the CPU baseline so the first report has a real value
+        self.cpu.sample();
+
+        loop {
+            ticker.tick().await;
+
+            let settings = self.app_state.settings();
+            let config = &settings.stats_reporting;
+            if config.interval != interval {
+                debug!("Stats reporting interval changed to {}s", config.interval);
+                interval = config.interval;
+                ticker = ticker_after(interval);
+            }
+
+            if !settings.features.metrics_collection {
+                continue;
+            }
+
+            // Reports are only useful live; don't let them pile up in the reconnect queue
+            if !matches!(
+                self.app_state.connection_status(),
+                ConnectionStatus::Connected
+            ) {
+                continue;
+            }
+
+            if !self.send(self.tunnel_handler.create_stats_message().await) {
+                break;
+            }
+
+            if config.system {
+                match self.cpu.sample() {
+                    Some(usage) => {
+                        let message = self.tunnel_handler.create_system_stats_message(&usage);
+                        if !self.send(message) {
+                            break;
+                        }
+                    }
+                    None => debug!("System stats unavailable on this platform"),
+                }
+            }
+        }
+    }
+
+    /// Queue a message for the relay, returning false once the channel is closed
+    fn send(&self, message: TunnelMessage) -> bool {
+        self.app_state.metrics.queue_push(Queue::Websocket);
+        if self.app_state.websocket_tx.send(message).is_err() {
+            self.app_state.metrics.queue_pop(Queue::Websocket);
+            warn!("WebSocket channel closed, stopping stats reporter");
+            return false;
+        }
+        true
+    }
+}
+
+/// Ticker firing every `seconds`, starting now
+fn ticker(seconds: u64) -> tokio::time::Interval {
+    let mut ticker = tokio::time::interval(Duration::from_secs(seconds.max(1)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker
+}
+
+/// Ticker firing every `seconds`, starting one period from now
+fn ticker_after(seconds: u64) -> tokio::time::Interval {
+    let period = Duration::from_secs(seconds.max(1));
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker
+}
+
+impl CpuSampler {
+    /// Read current usage; CPU is averaged since the previous sample
+    fn sample(&mut self) -> Option<SystemUsage> {
+        let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+
+        let ticks = parse_cpu_ticks(&stat)?;
+        let now = Instant::now();
+        let cpu_percent = match self.last.replace((now, ticks)) {
+            Some((then, previous)) => {
+                let elapsed = now.duration_since(then).as_secs_f64();
+                if elapsed > 0.0 {
+                    let used = ticks.saturating_sub(previous) as f64 / CLOCK_TICKS_PER_SECOND;
+                    used / elapsed * 100.0
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+
+        Some(SystemUsage {
+            cpu_percent,
+            rss_bytes: parse_kb_field(&status, "VmRSS:").unwrap_or(0),
+            memory_total_bytes: parse_kb_field(&meminfo, "MemTotal:").unwrap_or(0),
+        })
+    }
+}
+
+/// Sum of user and system CPU ticks from `/proc/<pid>/stat`
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    // The command name may contain spaces, so skip past its closing parenthesis
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+    // utime and stime are fields 14 and 15; the remainder starts at field 3
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// Read a `Name:   1234 kB` line and return bytes
+fn parse_kb_field(contents: &str, name: &str) -> Option<u64> {
+    let line = contents.lines().find(|line| line.starts_with(name))?;
+    let kb: u64 = line[name.len()..].split_whitespace().next()?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        let stat = "4242 (pori (client)) S 1 4242 4242 0 -1 4194560 1200 0 0 0 150 25 0 0 20 0 8 0";
+        assert_eq!(parse_cpu_ticks(stat), Some(175));
+
+        let status = "Name:\tpori\nVmPeak:\t  204800 kB\nVmRSS:\t   10240 kB\n";
+        assert_eq!(parse_kb_field(status, "VmRSS:"), Some(10240 * 1024));
+        assert_eq!(parse_kb_field(status, "VmSwap:"), None);
+    }
+}
@@ -0,0 +1,244 @@
+//! TLS for the relay connection
+//!
+//! Builds the rustls configuration used for `wss://` relays from the
+//! `websocket.tls` section: a client certificate for mutual TLS, extra CA
+//! bundles on top of the system roots, SPKI pins, and an insecure mode that
+//! skips chain and hostname checks for local testing.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio_tungstenite::Connector;
+use tracing::warn;
+
+use crate::protocol::tunnel::TlsConfig;
+
+/// Validated TLS settings for the relay connection
+#[derive(Debug)]
+pub struct RelayTls {
+    config: Option<Arc<ClientConfig>>,
+}
+
+impl RelayTls {
+    /// Load the certificates and keys named in the config
+    pub fn from_config(config: &TlsConfig) -> Result<Self> {
+        if *config == TlsConfig::default() {
+            return Ok(Self { config: None });
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let pins = config
+            .pin_sha256
+            .iter()
+            .map(|pin| parse_pin(pin))
+            .collect::<Result<Vec<_>>>()?;
+
+        let webpki = if config.insecure {
+            None
+        } else {
+            let mut roots = RootCertStore::empty();
+            let native = rustls_native_certs::load_native_certs();
+            for error in native.errors {
+                warn!("Failed to load a system CA certificate: {}", error);
+            }
+            roots.add_parsable_certificates(native.certs);
+            for path in &config.ca_files {
+                for cert in read_certs(path)? {
+                    roots
+                        .add(cert)
+                        .with_context(|| format!("Invalid CA certificate in {path}"))?;
+                }
+            }
+            Some(
+                WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .build()
+                    .context("Failed to build the relay certificate verifier")?,
+            )
+        };
+
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .context("Failed to configure TLS protocol versions")?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(RelayVerifier {
+                webpki,
+                pins,
+                provider,
+            }));
+
+        let client_config = match (&config.cert_file, &config.key_file) {
+            (Some(cert_file), Some(key_file)) => {
+                let certs = read_certs(cert_file)?;
+                let key = PrivateKeyDer::from_pem_file(key_file)
+                    .map_err(|e| anyhow::anyhow!("Failed to read private key {key_file}: {e}"))?;
+                builder.with_client_auth_cert(certs, key).with_context(|| {
+                    format!("Client certificate {cert_file} does not match its key")
+                })?
+            }
+            (None, None) => builder.with_no_client_auth(),
+            _ => bail!("websocket.tls.cert_file and websocket.tls.key_file must be set together"),
+        };
+
+        Ok(Self {
+            config: Some(Arc::new(client_config)),
+        })
+    }
+
+    /// Connector for `connect_async_tls_with_config`, or `None` for the defaults
+    pub fn connector(&self) -> Option<Connector> {
+        self.config.clone().map(Connector::Rustls)
+    }
+}
+
+/// Read every certificate from a PEM file
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid PEM in {path}: {e}"))?;
+    if certs.is_empty() {
+        bail!("No certificates found in {path}");
+    }
+    Ok(certs)
+}
+
+/// Decode a base64 SHA-256 SPKI hash, with or without a `sha256/` prefix
+fn parse_pin(pin: &str) -> Result<[u8; 32]> {
+    let encoded = pin.strip_prefix("sha256/").unwrap_or(pin);
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .with_context(|| format!("Invalid SPKI pin '{pin}': expected a base64 SHA-256 hash"))
+}
+
+/// SHA-256 hash of a certificate's SubjectPublicKeyInfo
+fn spki_sha256(cert: &CertificateDer<'_>) -> Result<[u8; 32], rustls::Error> {
+    let cert = webpki::EndEntityCert::try_from(cert)
+        .map_err(|_| rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding))?;
+    Ok(Sha256::digest(cert.subject_public_key_info().as_ref()).into())
+}
+
+/// Checks the chain (unless insecure) and then the SPKI pins
+#[derive(Debug)]
+struct RelayVerifier {
+    webpki: Option<Arc<WebPkiServerVerifier>>,
+    pins: Vec<[u8; 32]>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for RelayVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match &self.webpki {
+            Some(webpki) => {
+                webpki.verify_server_cert(
+                    end_entity,
+                    intermediates,
+                    server_name,
+                    ocsp_response,
+                    now,
+                )?;
+            }
+            None => warn!(
+                "Relay certificate for {:?} accepted without verification (websocket.tls.insecure), pin: sha256/{}",
+                server_name,
+                base64::engine::general_purpose::STANDARD.encode(spki_sha256(end_entity)?)
+            ),
+        }
+
+        if !self.pins.is_empty() && !self.pins.contains(&spki_sha256(end_entity)?) {
+            return Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_config_validation() {
+        assert!(RelayTls::from_config(&TlsConfig::default())
+            .unwrap()
+            .connector()
+            .is_none());
+
+        let insecure = TlsConfig {
+            insecure: true,
+            pin_sha256: vec![format!("sha256/{}", "A".repeat(43) + "=")],
+            ..TlsConfig::default()
+        };
+        assert!(RelayTls::from_config(&insecure)
+            .unwrap()
+            .connector()
+            .is_some());
+
+        let bad_pin = TlsConfig {
+            pin_sha256: vec!["not-a-hash".to_string()],
+            ..TlsConfig::default()
+        };
+        assert!(RelayTls::from_config(&bad_pin).is_err());
+
+        let cert_only = TlsConfig {
+            cert_file: Some("client.pem".to_string()),
+            insecure: true,
+            ..TlsConfig::default()
+        };
+        assert!(RelayTls::from_config(&cert_only)
+            .unwrap_err()
+            .to_string()
+            .contains("set together"));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::protocol::http::HttpMessage;
+use super::commands::CommandRunner;
+use super::reporter::SystemUsage;
+use crate::metrics::Queue;
+use crate::protocol::http::{HttpMessage, ProxyInfo};
 use crate::protocol::messages::{
     AuthPayload, ControlPayload, HttpPayload, MessagePayload, StatsPayload,
 };
@@ -20,15 +23,23 @@ pub struct TunnelHandler {
 impl TunnelHandler {
     pub fn new(app_state: Arc<AppState>) -> Self {
         Self {
+            tunnel_id: app_state.tunnel_id(),
+            client_id: app_state.client_id().to_string(),
             app_state,
-            tunnel_id: "default-tunnel".to_string(),
-            client_id: "pori-client".to_string(),
         }
     }
 
     /// Process incoming tunnel message from WebSocket
     #[instrument(skip(self, message))]
     pub async fn handle_message(&self, message: TunnelMessage) -> Result<Option<TunnelMessage>> {
+        if let Some(server_id) = &message.envelope.server_id {
+            if self.app_state.relay_session().server_id.as_ref() != Some(server_id) {
+                debug!("Relay server id: {}", server_id);
+                self.app_state
+                    .update_relay_session(|session| session.server_id = Some(server_id.clone()));
+            }
+        }
+
         match &message.message.payload {
             MessagePayload::Auth(auth_payload) => {
                 match auth_payload {
@@ -37,23 +48,20 @@ impl TunnelHandler {
                         warn!("Received an unexpected auth message");
                         Ok(None)
                     }
-                    AuthPayload::Success { session_id, .. } => {
+                    AuthPayload::Success {
+                        session_id,
+                        public_url,
+                        ..
+                    } => {
                         info!("Authentication successful, session ID: {}", session_id);
+                        self.app_state.update_relay_session(|session| {
+                            session.session_id = Some(session_id.clone())
+                        });
+                        self.record_public_url(public_url.as_deref());
 
                         // Update connection status
-                        let _ = self
-                            .app_state
-                            .dashboard_tx
-                            .send(DashboardEvent::ConnectionStatus(
-                                ConnectionStatus::Connected,
-                            ));
-
-                        // Update stats
                         self.app_state
-                            .update_stats(|stats| {
-                                stats.connection_status = "connected".to_string();
-                            })
-                            .await;
+                            .report_connection_status(ConnectionStatus::Connected);
 
                         Ok(None)
                     }
@@ -61,12 +69,10 @@ impl TunnelHandler {
                         error!("Authentication failed: {}", error_message);
 
                         // Update connection status
-                        let _ = self
-                            .app_state
-                            .dashboard_tx
-                            .send(DashboardEvent::ConnectionStatus(ConnectionStatus::Error(
+                        self.app_state
+                            .report_connection_status(ConnectionStatus::Error(
                                 error_message.clone(),
-                            )));
+                            ));
 
                         // Return error for a client to handle
                         Err(anyhow::anyhow!("Authentication failed: {}", error_message))
@@ -100,7 +106,10 @@ impl TunnelHandler {
                         // Log incoming request
                         info!("→ {} {} [{}]", method, path, cloud_request_id);
 
-                        debug!("Request headers: {:?}", headers);
+                        debug!(
+                            "Request headers: {:?}",
+                            self.app_state.redactor().headers(headers)
+                        );
 
                         // Create HTTP message for proxy with the cloud request ID
                         let http_message = HttpMessage::http_request_with_id(
@@ -110,9 +119,15 @@ impl TunnelHandler {
                             headers.clone(),
                             body.clone(),
                             cloud_request_id.clone(),
-                        );
+                        )
+                        .with_proxy_info(ProxyInfo::from_headers(
+                            headers,
+                            self.app_state.settings().forwarded.trusted_hops,
+                        ));
 
+                        self.app_state.metrics.queue_push(Queue::Proxy);
                         if let Err(e) = self.app_state.proxy_tx.send(http_message) {
+                            self.app_state.metrics.queue_pop(Queue::Proxy);
                             error!("Failed to forward an HTTP request to proxy: {}", e);
 
                             // Log error response
@@ -138,13 +153,6 @@ impl TunnelHandler {
                             .dashboard_tx
                             .send(DashboardEvent::RequestForwarded(format!("{method} {path}")));
 
-                        // Update stats
-                        self.app_state
-                            .update_stats(|stats| {
-                                stats.requests_processed += 1;
-                            })
-                            .await;
-
                         crate::proxy_log!(
                             "Request forwarded to local server: {} {} [Message ID: {}, Cloud RequestID: {}]",
                             method,
@@ -180,13 +188,7 @@ impl TunnelHandler {
                     .app_state
                     .dashboard_tx
                     .send(DashboardEvent::Error(error_payload.message.clone()));
-
-                // Update error stats
-                self.app_state
-                    .update_stats(|stats| {
-                        stats.requests_failed += 1;
-                    })
-                    .await;
+                self.app_state.tunnel_metrics.record_relay_error();
 
                 Ok(None)
             }
@@ -209,15 +211,40 @@ impl TunnelHandler {
                         info!("Server status: {:?} - {:?}", status, message);
 
                         // Update connection status
-                        let _ = self
-                            .app_state
-                            .dashboard_tx
-                            .send(DashboardEvent::ConnectionStatus(
-                                ConnectionStatus::Connected,
-                            ));
+                        self.app_state
+                            .report_connection_status(ConnectionStatus::Connected);
 
                         Ok(None)
                     }
+                    ControlPayload::Authentication {
+                        status, public_url, ..
+                    } => {
+                        info!("Relay authentication status: {}", status);
+                        self.record_public_url(public_url.as_deref());
+                        Ok(None)
+                    }
+                    ControlPayload::Config {
+                        config_type,
+                        config_data,
+                    } => Ok(self.handle_config_update(config_type, config_data)),
+                    ControlPayload::Command {
+                        command,
+                        arguments,
+                        environment,
+                    } => {
+                        let metadata = &message.message.metadata;
+                        let correlation_id = metadata
+                            .correlation_id
+                            .clone()
+                            .unwrap_or_else(|| metadata.id.clone());
+                        self.spawn_command(
+                            correlation_id,
+                            command.clone(),
+                            arguments.clone(),
+                            environment.clone(),
+                        );
+                        Ok(None)
+                    }
                     _ => {
                         debug!("Received control message: {:?}", control_payload);
                         Ok(None)
@@ -245,12 +272,114 @@ impl TunnelHandler {
         }
     }
 
+    /// Remember the public URL assigned by the relay and announce it when it changes
+    fn record_public_url(&self, public_url: Option<&str>) {
+        let Some(public_url) = public_url else {
+            return;
+        };
+        if self.app_state.relay_session().public_url.as_deref() == Some(public_url) {
+            return;
+        }
+
+        self.app_state
+            .update_relay_session(|session| session.public_url = Some(public_url.to_string()));
+        let settings = self.app_state.settings();
+        info!(
+            "Tunnel {} is public at {}",
+            settings.tunnel_name, public_url
+        );
+        // Completes the startup banner printed by main
+        println!(
+            "Public URL ({}): {} -> {}",
+            settings.tunnel_name, public_url, settings.local_server.url
+        );
+    }
+
+    /// Apply a configuration update pushed by the relay, replying with the result
+    fn handle_config_update(
+        &self,
+        config_type: &str,
+        config_data: &serde_json::Value,
+    ) -> Option<TunnelMessage> {
+        let (reply, event) =
+            match crate::config::relay::apply_update(&self.app_state, config_type, config_data) {
+                Ok(version) => {
+                    let message =
+                        format!("Applied '{config_type}' config version {version} from the relay");
+                    info!("{}", message);
+                    let mut reply = json!({ "status": "applied", "version": version });
+                    if config_type == "logging" {
+                        reply["filter"] = json!(crate::logging::current_filter());
+                    }
+                    (reply, DashboardEvent::ConfigReloaded(message))
+                }
+                Err(e) => {
+                    let message = format!("Rejected '{config_type}' config from the relay: {e:#}");
+                    warn!("{}", message);
+                    (
+                        json!({ "status": "error", "error": format!("{e:#}") }),
+                        DashboardEvent::ConfigReloadFailed(message),
+                    )
+                }
+            };
+        let _ = self.app_state.dashboard_tx.send(event);
+
+        let message = crate::protocol::messages::ProtocolMessage::new(
+            "control".to_string(),
+            MessagePayload::Control(ControlPayload::Config {
+                config_type: config_type.to_string(),
+                config_data: reply,
+            }),
+        );
+        Some(TunnelMessage::new(
+            self.tunnel_id.clone(),
+            self.client_id.clone(),
+            message,
+        ))
+    }
+
+    /// Run a relay command in the background and send its result when done
+    fn spawn_command(
+        &self,
+        correlation_id: String,
+        command: String,
+        arguments: Vec<String>,
+        environment: HashMap<String, String>,
+    ) {
+        let app_state = self.app_state.clone();
+        let tunnel_id = self.tunnel_id.clone();
+        let client_id = self.client_id.clone();
+
+        tokio::spawn(async move {
+            let result = match CommandRunner::from_config(&app_state.settings().commands) {
+                Ok(runner) => {
+                    runner
+                        .run(&app_state, &command, &arguments, &environment)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match &result {
+                Ok(_) => info!("Relay command '{}' completed", command),
+                Err(e) => warn!("Relay command '{}' failed: {:#}", command, e),
+            }
+
+            let message =
+                command_result_message(tunnel_id, client_id, correlation_id, command, result);
+            app_state.metrics.queue_push(Queue::Websocket);
+            if app_state.websocket_tx.send(message).is_err() {
+                app_state.metrics.queue_pop(Queue::Websocket);
+                warn!("WebSocket channel closed, dropping a command result");
+            }
+        });
+    }
+
     /// Create an authentication message for the initial connection
     pub fn create_auth_message(&self) -> TunnelMessage {
         TunnelMessage::auth_token(
             self.tunnel_id.clone(),
             self.client_id.clone(),
-            self.app_state.settings.websocket.token.clone(),
+            self.app_state.settings().websocket.token.clone(),
             "Bearer".to_string(),
             vec!["tunnel".to_string()],
         )
@@ -258,15 +387,32 @@ impl TunnelHandler {
 
     /// Create a statistics message
     pub async fn create_stats_message(&self) -> TunnelMessage {
-        let stats = self.app_state.get_stats().await;
+        let stats = self.app_state.tunnel_metrics.snapshot();
         let message = crate::protocol::messages::ProtocolMessage::new(
             "stats".to_string(),
             MessagePayload::Stats(StatsPayload::Traffic {
                 requests_processed: stats.requests_processed,
                 requests_successful: stats.requests_successful,
                 requests_failed: stats.requests_failed,
-                bytes_transferred: stats.bytes_forwarded,
-                average_response_time_ms: 0.0,
+                bytes_transferred: stats.bytes_received + stats.bytes_sent,
+                average_response_time_ms: stats.average_response_time_ms,
+            }),
+        );
+        TunnelMessage::new(self.tunnel_id.clone(), self.client_id.clone(), message)
+    }
+
+    /// Create a system resource statistics message
+    pub fn create_system_stats_message(&self, usage: &SystemUsage) -> TunnelMessage {
+        let metrics = &self.app_state.tunnel_metrics;
+        let message = crate::protocol::messages::ProtocolMessage::new(
+            "stats".to_string(),
+            MessagePayload::Stats(StatsPayload::System {
+                cpu_usage_percent: usage.cpu_percent,
+                memory_usage_bytes: usage.rss_bytes,
+                memory_total_bytes: usage.memory_total_bytes,
+                disk_usage_bytes: 0,
+                network_bytes_sent: metrics.bytes_sent(),
+                network_bytes_received: metrics.bytes_received(),
             }),
         );
         TunnelMessage::new(self.tunnel_id.clone(), self.client_id.clone(), message)
@@ -294,14 +440,6 @@ impl TunnelHandler {
             .dashboard_tx
             .send(DashboardEvent::ResponseReceived(status, body_size));
 
-        // Update stats
-        self.app_state
-            .update_stats(|stats| {
-                stats.requests_successful += 1;
-                stats.bytes_forwarded += body_size as u64;
-            })
-            .await;
-
         crate::proxy_log!("OUTGOING: {} {}", status, status_text);
 
         TunnelMessage::http_response_with_id(
@@ -331,13 +469,6 @@ impl TunnelHandler {
             .dashboard_tx
             .send(DashboardEvent::Error(format!("Proxy error: {error}")));
 
-        // Update error stats
-        self.app_state
-            .update_stats(|stats| {
-                stats.requests_failed += 1;
-            })
-            .await;
-
         self.create_error_response_with_request_id(request_id, error, status_code, cloud_request_id)
     }
 
@@ -511,6 +642,36 @@ impl TunnelHandler {
     }
 }
 
+/// Build the reply to a relay command, correlated with the command message
+fn command_result_message(
+    tunnel_id: String,
+    client_id: String,
+    correlation_id: String,
+    command: String,
+    result: Result<serde_json::Value>,
+) -> TunnelMessage {
+    let payload = match result {
+        Ok(output) => ControlPayload::CommandResult {
+            command,
+            success: true,
+            output,
+            error: None,
+        },
+        Err(e) => ControlPayload::CommandResult {
+            command,
+            success: false,
+            output: serde_json::Value::Null,
+            error: Some(format!("{e:#}")),
+        },
+    };
+    let message = crate::protocol::messages::ProtocolMessage::new(
+        "control".to_string(),
+        MessagePayload::Control(payload),
+    )
+    .with_correlation_id(correlation_id);
+    TunnelMessage::new(tunnel_id, client_id, message)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -532,14 +693,91 @@ mod tests {
             timeout: 30,
             max_reconnects: 0,
             verify_ssl: false,
+            no_verify_ssl: false,
             max_connections: 10,
             http_version: "http1".to_string(),
+            subdomain: None,
+            upstream: None,
         };
         let settings = AppSettings::from_cli(args).unwrap();
         let (app_state, _) = AppState::new(settings);
         Arc::new(app_state)
     }
 
+    #[tokio::test]
+    async fn test_commands_reply_with_correlation_id() {
+        let app_state = create_test_app_state();
+        let mut settings = app_state.settings().as_ref().clone();
+        settings.commands.allowed = vec!["dump-stats".to_string()];
+        let (app_state, mut channels) = AppState::new(settings);
+        let handler = TunnelHandler::new(Arc::new(app_state));
+
+        for (command, correlation_id) in [("dump-stats", "cmd-1"), ("reconnect", "cmd-2")] {
+            let message = crate::protocol::messages::ProtocolMessage::new(
+                "control".to_string(),
+                MessagePayload::Control(ControlPayload::Command {
+                    command: command.to_string(),
+                    arguments: Vec::new(),
+                    environment: HashMap::new(),
+                }),
+            )
+            .with_correlation_id(correlation_id.to_string());
+            let message = TunnelMessage::new("t".to_string(), "c".to_string(), message);
+            assert!(handler.handle_message(message).await.unwrap().is_none());
+
+            let reply = channels.websocket_rx.recv().await.unwrap();
+            assert_eq!(
+                reply.message.metadata.correlation_id.as_deref(),
+                Some(correlation_id)
+            );
+            match reply.message.payload {
+                MessagePayload::Control(ControlPayload::CommandResult {
+                    success,
+                    output,
+                    error,
+                    ..
+                }) if command == "dump-stats" => {
+                    assert!(success, "{error:?}");
+                    assert!(output["stats"].is_object());
+                }
+                MessagePayload::Control(ControlPayload::CommandResult {
+                    success, error, ..
+                }) => {
+                    assert!(!success);
+                    assert!(error.unwrap().contains("not allowed"));
+                }
+                other => panic!("Unexpected reply: {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_success_records_relay_session() {
+        let app_state = create_test_app_state();
+        let handler = TunnelHandler::new(app_state.clone());
+
+        let message = crate::protocol::messages::ProtocolMessage::new(
+            "auth".to_string(),
+            MessagePayload::Auth(AuthPayload::Success {
+                session_id: "sess-1".to_string(),
+                expires_at: None,
+                permissions: Vec::new(),
+                public_url: Some("https://alice.pori.dev".to_string()),
+            }),
+        );
+        let mut message = TunnelMessage::new("t".to_string(), "c".to_string(), message);
+        message.envelope.server_id = Some("relay-eu-1".to_string());
+        handler.handle_message(message).await.unwrap();
+
+        let session = app_state.relay_session();
+        assert_eq!(session.session_id.as_deref(), Some("sess-1"));
+        assert_eq!(session.server_id.as_deref(), Some("relay-eu-1"));
+        assert_eq!(
+            session.public_url.as_deref(),
+            Some("https://alice.pori.dev")
+        );
+    }
+
     #[tokio::test]
     async fn test_ping_pong_handling() {
         let app_state = create_test_app_state();
@@ -560,6 +798,52 @@ mod tests {
         assert!(response.is_none());
     }
 
+    #[test]
+    fn test_logging_config_update_reports_errors() {
+        let app_state = create_test_app_state();
+        let handler = TunnelHandler::new(app_state.clone());
+
+        let reply = handler
+            .handle_config_update("logging", &json!({ "filter": "debug" }))
+            .unwrap();
+        match reply.message.payload {
+            MessagePayload::Control(ControlPayload::Config { config_data, .. }) => {
+                assert!(config_data["error"].as_str().unwrap().contains("disabled"));
+            }
+            other => panic!("Unexpected reply: {other:?}"),
+        }
+
+        let mut settings = app_state.settings().as_ref().clone();
+        settings.relay_overrides.enabled = true;
+        app_state.apply_settings(settings);
+
+        let reply = handler
+            .handle_config_update("logging", &json!({ "filter": "PROXY=chatty" }))
+            .unwrap();
+        match reply.message.payload {
+            MessagePayload::Control(ControlPayload::Config { config_data, .. }) => {
+                assert_eq!(config_data["status"], "error");
+                assert!(config_data["error"].is_string());
+            }
+            other => panic!("Unexpected reply: {other:?}"),
+        }
+
+        let reply = handler.handle_config_update("unknown", &json!({})).unwrap();
+        match reply.message.payload {
+            MessagePayload::Control(ControlPayload::Config {
+                config_type,
+                config_data,
+            }) => {
+                assert_eq!(config_type, "unknown");
+                assert!(config_data["error"]
+                    .as_str()
+                    .unwrap()
+                    .contains("Unsupported config type"));
+            }
+            other => panic!("Unexpected reply: {other:?}"),
+        }
+    }
+
     #[test]
     fn test_header_validation() {
         let app_state = create_test_app_state();
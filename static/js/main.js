@@ -1,7 +1,6 @@
 class Dashboard {
   constructor() {
     this.ws = null;
-    this.isConnected = false;
     this.init();
   }
 
@@ -22,14 +21,65 @@ class Dashboard {
   }
 
   connectWebSocket() {
-    // WebSocket connection for real-time updates
-    // This would connect to the dashboard WebSocket endpoint
-    console.log('WebSocket connection would be established here');
-
-    // Simulate connection for demo
-    setTimeout(() => {
-      this.updateConnectionStatus('connected');
-    }, 1000);
+    // Live updates need the relay token, passed to the dashboard as ?token=...
+    const token = new URLSearchParams(window.location.search).get('token');
+    if (!token) {
+      this.addLogEntry('No ?token= in the URL, falling back to polling', 'info');
+      this.refreshStats();
+      return;
+    }
+
+    const scheme = window.location.protocol === 'https:' ? 'wss' : 'ws';
+    const url = `${scheme}://${window.location.host}/metrics?token=${encodeURIComponent(token)}`;
+    this.ws = new WebSocket(url);
+
+    this.ws.onopen = () => {
+      this.addLogEntry('Live updates connected', 'success');
+    };
+
+    this.ws.onmessage = (message) => {
+      try {
+        this.handleLiveMessage(JSON.parse(message.data));
+      } catch (error) {
+        console.error('Invalid live message', error);
+      }
+    };
+
+    this.ws.onclose = () => {
+      this.ws = null; // Polling takes over while the stream is down
+      this.addLogEntry('Live updates disconnected, retrying in 5s', 'error');
+      setTimeout(() => this.connectWebSocket(), 5000);
+    };
+  }
+
+  handleLiveMessage(message) {
+    const data = message.data || {};
+    switch (message.type) {
+      case 'stats_update':
+        this.updateStats(data);
+        if (data.connection_status) {
+          this.updateConnectionStatus(data.connection_status);
+        }
+        break;
+      case 'connection_status':
+        this.updateConnectionStatus(data.status);
+        this.addLogEntry(`Connection ${data.status}${data.message ? `: ${data.message}` : ''}`);
+        break;
+      case 'request':
+        if (data.status) {
+          const type = data.status >= 400 ? 'error' : 'success';
+          const tags = data.tags && data.tags.length ? ` [${data.tags.join(', ')}]` : '';
+          this.addLogEntry(`${data.method} ${data.path} -> ${data.status} (${data.duration_ms} ms)${tags}`, type);
+        }
+        break;
+      case 'event':
+        if (data.kind === 'error' || data.kind === 'access_denied' || data.kind === 'authentication_failed' || data.kind === 'config_reload_failed') {
+          this.addLogEntry(data.message, 'error');
+        } else if (data.kind === 'config_reloaded') {
+          this.addLogEntry(data.message, 'success');
+        }
+        break;
+    }
   }
 
   updateConnectionStatus(status) {
@@ -41,17 +91,19 @@ class Dashboard {
       case 'connected':
         statusDot.classList.add('connected');
         statusText.textContent = 'Connected';
-        this.isConnected = true;
         break;
       case 'connecting':
         statusDot.classList.remove('connected');
         statusText.textContent = 'Connecting...';
-        this.isConnected = false;
+        break;
+      case 'reconnecting':
+        statusDot.classList.remove('connected');
+        statusText.textContent = 'Reconnecting...';
         break;
       case 'disconnected':
+      case 'error':
         statusDot.classList.remove('connected');
         statusText.textContent = 'Disconnected';
-        this.isConnected = false;
         break;
     }
   }
@@ -107,9 +159,9 @@ class Dashboard {
   }
 
   startStatsRefresh() {
-    // Refresh stats every 5 seconds
+    // Poll stats every 5 seconds only while the live stream is unavailable
     setInterval(() => {
-      if (this.isConnected) {
+      if (!this.ws || this.ws.readyState !== WebSocket.OPEN) {
         this.refreshStats();
       }
     }, 5000);